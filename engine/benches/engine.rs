@@ -0,0 +1,296 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use itertools::izip;
+use rusty_pedestrians_engine::internals::*;
+use rusty_pedestrians_engine::Universe;
+
+// Every input is generated from fixed seeds, runs are comparable across
+// changes.
+const SEED: u64 = 42;
+const AGENTS_COUNTS: [usize; 3] = [100, 1_000, 10_000];
+const DT: f64 = 0.1;
+// Steps simulated before measuring, for the agents to be in each other's way.
+const WARMUP_STEPS_COUNT: usize = 10;
+
+// Objective direction, maximum norm and half planes constraints.
+type LinearProgram = (Vec2, f64, Vec<(Vec2, Vec2)>);
+
+// Agents on a circle heading to its antipode, spaced by about a meter
+// whatever their number.
+fn antipodal_circle_scenario(agents_count: usize) -> String {
+  format!(
+    "{{
+      \"scenario\": \"AntipodalCircle\",
+      \"agents_count\": {},
+      \"radius\": {},
+      \"jitter\": 0.1,
+      \"seed\": {}
+    }}",
+    agents_count,
+    (agents_count as f64 / (2. * std::f64::consts::PI)).max(5.),
+    SEED
+  )
+}
+
+// Two crowds crossing each other in a corridor as wide as needed for about a
+// meter between agents.
+fn corridor_scenario(agents_count: usize) -> String {
+  format!(
+    "{{
+      \"scenario\": \"Corridor\",
+      \"agents_per_side_count\": {},
+      \"width\": {},
+      \"length\": 20.0,
+      \"seed\": {}
+    }}",
+    agents_count / 2,
+    (agents_count / 2) as f64,
+    SEED
+  )
+}
+
+fn scenarios(agents_count: usize) -> Vec<(&'static str, String)> {
+  vec![
+    ("antipodal_circle", antipodal_circle_scenario(agents_count)),
+    ("corridor", corridor_scenario(agents_count)),
+  ]
+}
+
+fn warmed_up_universe(scenario: &str) -> Universe {
+  let mut universe = Universe::new();
+  universe.try_load_scenario(scenario).unwrap();
+  (0..WARMUP_STEPS_COUNT).for_each(|_| universe.try_update(DT).unwrap());
+  universe
+}
+
+fn generate_agents(scenario: &str) -> Agents {
+  let scenario = load_scenario(scenario).unwrap();
+  let (agents, _) = scenario
+    .generate(&mut Random::new(scenario.seed()))
+    .unwrap();
+  agents
+}
+
+fn compute_neighborhoods(agents: &Agents) -> Vec<AgentNeighborhood> {
+  let mut neighborhoods = vec![AgentNeighborhood::default(); agents.len()];
+  AgentNeighborhood::compute_agents_neighborhood(
+    agents.get_positions(),
+    agents.get_velocities(),
+    agents.get_radii(),
+    agents.get_kinds(),
+    agents.get_arrived(),
+    agents.get_group_ids(),
+    agents.get_groups(),
+    &mut neighborhoods,
+  );
+  neighborhoods
+}
+
+fn desired_velocities(agents: &Agents) -> Vec<Vec2> {
+  izip!(
+    agents.get_positions(),
+    agents.get_targets(),
+    agents.get_desired_speeds()
+  )
+  .map(|(&position, &target, &desired_speed)| (target - position).normalize_to(desired_speed))
+  .collect()
+}
+
+// Square grid navmesh of `size` by `size` cells of 1m, each made of two
+// triangles.
+fn build_grid_navmesh(size: usize) -> Navmesh {
+  (0..size)
+    .flat_map(|i| (0..size).map(move |j| (i as f64, j as f64)))
+    .fold(NavmeshBuilder::new(), |builder, (x, y)| {
+      builder
+        .add_cell(
+          Vec2::new(x, y),
+          Vec2::new(x + 1., y),
+          Vec2::new(x + 1., y + 1.),
+        )
+        .add_cell(
+          Vec2::new(x, y),
+          Vec2::new(x + 1., y + 1.),
+          Vec2::new(x, y + 1.),
+        )
+    })
+    .build()
+    .unwrap()
+}
+
+fn bench_neighborhood(c: &mut Criterion) {
+  let mut group = c.benchmark_group("neighborhood");
+  AGENTS_COUNTS.iter().for_each(|&agents_count| {
+    let agents = generate_agents(&antipodal_circle_scenario(agents_count));
+    let mut neighborhoods = vec![AgentNeighborhood::default(); agents.len()];
+    group.throughput(Throughput::Elements(agents.len() as u64));
+    group.bench_function(BenchmarkId::from_parameter(agents_count), |b| {
+      b.iter(|| {
+        AgentNeighborhood::compute_agents_neighborhood(
+          agents.get_positions(),
+          agents.get_velocities(),
+          agents.get_radii(),
+          agents.get_kinds(),
+          agents.get_arrived(),
+          agents.get_group_ids(),
+          agents.get_groups(),
+          &mut neighborhoods,
+        )
+      })
+    });
+  });
+  group.finish();
+}
+
+fn bench_constraints(c: &mut Criterion) {
+  let mut group = c.benchmark_group("constraints");
+  AGENTS_COUNTS.iter().for_each(|&agents_count| {
+    let agents = generate_agents(&antipodal_circle_scenario(agents_count));
+    let neighborhoods = compute_neighborhoods(&agents);
+    let desired_velocities = desired_velocities(&agents);
+    group.throughput(Throughput::Elements(agents.len() as u64));
+    group.bench_function(BenchmarkId::from_parameter(agents_count), |b| {
+      b.iter(|| {
+        izip!(
+          0..agents.len(),
+          agents.get_positions(),
+          &desired_velocities,
+          agents.get_radii(),
+          &neighborhoods
+        )
+        .map(
+          |(index, &position, &desired_velocity, &radius, neighborhood)| {
+            compute_constraints(
+              index,
+              position,
+              desired_velocity,
+              radius,
+              neighborhood,
+              DEFAULT_TIME_HORIZON,
+              DT,
+            )
+            .get_constraints()
+            .len()
+          },
+        )
+        .sum::<usize>()
+      })
+    });
+  });
+  group.finish();
+}
+
+fn bench_linear_program(c: &mut Criterion) {
+  let mut group = c.benchmark_group("linear_program");
+  AGENTS_COUNTS.iter().for_each(|&agents_count| {
+    // Constraints of two crowds heading at each other in the corridor.
+    let agents = generate_agents(&corridor_scenario(agents_count));
+    let neighborhoods = compute_neighborhoods(&agents);
+    let problems: Vec<LinearProgram> = izip!(
+      0..agents.len(),
+      agents.get_positions(),
+      desired_velocities(&agents),
+      agents.get_radii(),
+      agents.get_maximum_speeds(),
+      &neighborhoods
+    )
+    .map(
+      |(index, &position, desired_velocity, &radius, &maximum_speed, neighborhood)| {
+        (
+          desired_velocity.normalize(),
+          maximum_speed,
+          compute_constraints(
+            index,
+            position,
+            desired_velocity,
+            radius,
+            neighborhood,
+            DEFAULT_TIME_HORIZON,
+            DT,
+          )
+          .get_constraints()
+          .to_vec(),
+        )
+      },
+    )
+    .collect();
+    group.throughput(Throughput::Elements(problems.len() as u64));
+    group.bench_function(BenchmarkId::from_parameter(agents_count), |b| {
+      b.iter(|| {
+        problems
+          .iter()
+          .filter_map(|(direction, maximum_speed, half_planes)| {
+            solve_linear_program(direction, *maximum_speed, half_planes, false).ok()
+          })
+          .count()
+      })
+    });
+  });
+  group.finish();
+}
+
+fn bench_navmesh(c: &mut Criterion) {
+  let mut group = c.benchmark_group("navmesh");
+  [10, 30].iter().for_each(|&size| {
+    group.bench_function(BenchmarkId::new("build", size), |b| {
+      b.iter(|| build_grid_navmesh(size))
+    });
+  });
+  [10, 30].iter().for_each(|&size| {
+    let navmesh = build_grid_navmesh(size);
+    let mut random = Random::new(SEED);
+    let positions: Vec<Vec2> = (0..100)
+      .map(|_| {
+        Vec2::new(
+          random.uniform(0., size as f64),
+          random.uniform(0., size as f64),
+        )
+      })
+      .collect();
+    group.throughput(Throughput::Elements(positions.len() as u64));
+    group.bench_function(BenchmarkId::new("locate", size), |b| {
+      b.iter(|| {
+        positions
+          .iter()
+          .filter_map(|position| navmesh.locate(position, None))
+          .count()
+      })
+    });
+  });
+  group.finish();
+}
+
+fn bench_update(c: &mut Criterion) {
+  let mut group = c.benchmark_group("update");
+  group.sample_size(10);
+  AGENTS_COUNTS.iter().for_each(|&agents_count| {
+    scenarios(agents_count)
+      .into_iter()
+      .for_each(|(name, scenario)| {
+        let universe = warmed_up_universe(&scenario);
+        group.throughput(Throughput::Elements(universe.count_agents() as u64));
+        group.bench_function(BenchmarkId::new(name, agents_count), |b| {
+          // Every iteration steps from the same state.
+          b.iter_batched_ref(
+            || {
+              let mut universe_copy = Universe::new();
+              universe_copy.restore(universe.snapshot());
+              universe_copy
+            },
+            |universe| universe.try_update(DT).unwrap(),
+            criterion::BatchSize::LargeInput,
+          )
+        });
+      });
+  });
+  group.finish();
+}
+
+criterion_group!(
+  benches,
+  bench_neighborhood,
+  bench_constraints,
+  bench_linear_program,
+  bench_navmesh,
+  bench_update
+);
+criterion_main!(benches);
@@ -1,14 +1,42 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::vec::Vec;
 
+use super::queue::DEFAULT_SERVICE_TIME;
+use super::trajectory::Trajectory;
 use super::vec2::Vec2;
 
 pub const DEFAULT_DESIRED_SPEED: f64 = 2.1;
 pub const DEFAULT_MAXIMUM_SPEED: f64 = 3.0;
 pub const DEFAULT_MAXIMUM_ACCELERATION: f64 = 3.0;
 pub const DEFAULT_RADIUS: f64 = 0.35;
+pub const DEFAULT_ARRIVAL_RADIUS: f64 = 0.2;
+pub const DEFAULT_MAXIMUM_ANGULAR_SPEED: f64 = std::f64::consts::PI;
+pub const DEFAULT_SMOOTHING_TIME: f64 = 0.3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AgentKind {
+  // Navigates toward its target while avoiding others.
+  Navigating,
+  // Follows a prescribed trajectory regardless of others.
+  Scripted,
+}
+
+// What an agent does once it has arrived at its target.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ArrivalPolicy {
+  // Stays where it arrived.
+  Stop,
+  // Leaves the simulation.
+  Despawn,
+  // Heads to its next waypoint, if any, or stops.
+  NextTarget,
+  // Heads to a new target nearby.
+  Wander,
+}
 
-#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub struct Agent {
+  pub kind: AgentKind,
   pub position: Vec2,
   pub velocity: Vec2,
   pub direction: Vec2,
@@ -17,11 +45,35 @@ pub struct Agent {
   pub maximum_speed: f64,
   pub maximum_acceleration: f64,
   pub radius: f64,
+  // Maximum rotation speed of the body direction, in radians per second.
+  pub maximum_angular_speed: f64,
+  // Time over which the body direction catches up with the walking direction,
+  // in seconds.
+  pub smoothing_time: f64,
+  // When defined, maximum speed at which the agent can walk backward, relative
+  // to its body direction.
+  pub maximum_backward_speed: Option<f64>,
+  // Distance to the target under which the agent is considered arrived.
+  pub arrival_radius: f64,
+  pub arrival_policy: ArrivalPolicy,
+  // Targets to reach after the current one, when using the `NextTarget` policy.
+  pub waypoints: Vec<Vec2>,
+  pub arrived: bool,
+  // Time at which the agent started its travel, from its initial position.
+  pub departure_time: f64,
+  pub group: Option<usize>,
+  // Queue the agent needs to go through before reaching its target.
+  pub queue: Option<usize>,
+  // Duration of the agent's service once at the head of its queue.
+  pub service_time: f64,
+  #[serde(skip)]
+  pub trajectory: Option<Trajectory>,
 }
 
 impl Agent {
   pub fn new() -> Self {
     Agent {
+      kind: AgentKind::Navigating,
       position: Vec2::new(0., 0.),
       velocity: Vec2::new(0., 0.),
       direction: Vec2::new(1., 0.),
@@ -30,6 +82,18 @@ impl Agent {
       maximum_speed: DEFAULT_MAXIMUM_SPEED,
       maximum_acceleration: DEFAULT_MAXIMUM_ACCELERATION,
       radius: DEFAULT_RADIUS,
+      maximum_angular_speed: DEFAULT_MAXIMUM_ANGULAR_SPEED,
+      smoothing_time: DEFAULT_SMOOTHING_TIME,
+      maximum_backward_speed: None,
+      arrival_radius: DEFAULT_ARRIVAL_RADIUS,
+      arrival_policy: ArrivalPolicy::Stop,
+      waypoints: Vec::new(),
+      arrived: false,
+      departure_time: 0.,
+      group: None,
+      queue: None,
+      service_time: DEFAULT_SERVICE_TIME,
+      trajectory: None,
     }
   }
 
@@ -78,6 +142,63 @@ impl Agent {
     self.radius = r;
     self
   }
+
+  pub fn maximum_angular_speed(mut self, s: f64) -> Self {
+    self.maximum_angular_speed = s;
+    self
+  }
+
+  pub fn smoothing_time(mut self, t: f64) -> Self {
+    self.smoothing_time = t;
+    self
+  }
+
+  pub fn maximum_backward_speed(mut self, s: f64) -> Self {
+    self.maximum_backward_speed = Some(s);
+    self
+  }
+
+  #[allow(dead_code)]
+  pub fn arrival_radius(mut self, r: f64) -> Self {
+    self.arrival_radius = r;
+    self
+  }
+
+  pub fn arrival_policy(mut self, arrival_policy: ArrivalPolicy) -> Self {
+    self.arrival_policy = arrival_policy;
+    self
+  }
+
+  pub fn waypoint(mut self, x: f64, y: f64) -> Self {
+    self.waypoints.push(Vec2::new(x, y));
+    self
+  }
+
+  pub fn group(mut self, group: usize) -> Self {
+    self.group = Some(group);
+    self
+  }
+
+  pub fn queue(mut self, queue: usize) -> Self {
+    self.queue = Some(queue);
+    self
+  }
+
+  pub fn service_time(mut self, service_time: f64) -> Self {
+    self.service_time = service_time;
+    self
+  }
+
+  pub fn departure_time(mut self, departure_time: f64) -> Self {
+    self.departure_time = departure_time;
+    self
+  }
+
+  pub fn trajectory(mut self, trajectory: Trajectory) -> Self {
+    self.kind = AgentKind::Scripted;
+    self.trajectory = Some(trajectory);
+    self
+  }
 }
 
 impl Default for Agent {
@@ -103,4 +224,12 @@ mod tests {
       Vec2::new(3.0, 2.0).normalize()
     );
   }
+
+  #[test]
+  fn test_given_trajectory() {
+    let agent = Agent::new().trajectory(Trajectory::new(&[(0., Vec2::new(1.0, 2.0))]));
+    assert_eq!(agent.kind, AgentKind::Scripted);
+    assert_eq!(agent.trajectory.unwrap().len(), 1);
+    assert_eq!(Agent::new().kind, AgentKind::Navigating);
+  }
 }
@@ -1,4 +1,5 @@
 use super::agent::Agent;
+use super::collisions::{AgentsOverlap, WallPenetration};
 use super::vec2::Vec2;
 use serde::Serialize;
 use std::vec::Vec;
@@ -7,6 +8,8 @@ use std::vec::Vec;
 pub struct AgentDebugInfo {
   agent: Agent,
   orca_constraints: Vec<(Vec2, Vec2)>,
+  agents_overlaps: Vec<AgentsOverlap>,
+  wall_penetration: Option<WallPenetration>,
 }
 
 impl AgentDebugInfo {
@@ -14,6 +17,8 @@ impl AgentDebugInfo {
     AgentDebugInfo {
       agent: Agent::default(),
       orca_constraints: Vec::new(),
+      agents_overlaps: Vec::new(),
+      wall_penetration: None,
     }
   }
 
@@ -27,6 +32,16 @@ impl AgentDebugInfo {
     self
   }
 
+  pub fn collisions(
+    mut self,
+    agents_overlaps: &[AgentsOverlap],
+    wall_penetration: Option<WallPenetration>,
+  ) -> Self {
+    self.agents_overlaps = agents_overlaps.to_vec();
+    self.wall_penetration = wall_penetration;
+    self
+  }
+
   pub fn render(&self) -> String {
     serde_json::to_string(self).unwrap_or_else(|error| format!("{{\"error\": \"{}\"}}", error))
   }
@@ -1,9 +1,19 @@
-use super::agent::Agent;
+use super::agent::{Agent, AgentKind, ArrivalPolicy};
+use super::group::Group;
+use super::queue::Queue;
+use super::trajectory::Trajectory;
 use super::vec2::Vec2;
+use serde::{Deserialize, Serialize};
+use std::mem;
 use std::vec::Vec;
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Agents {
+  // Stable identifiers, unlike indices they are not reused when agents are
+  // removed.
+  ids: Vec<usize>,
+  next_id: usize,
+  kinds: Vec<AgentKind>,
   positions: Vec<Vec2>,
   directions: Vec<Vec2>,
   velocities: Vec<Vec2>,
@@ -12,11 +22,34 @@ pub struct Agents {
   maximum_speeds: Vec<f64>,
   maximum_accelerations: Vec<f64>,
   radii: Vec<f64>,
+  maximum_angular_speeds: Vec<f64>,
+  smoothing_times: Vec<f64>,
+  maximum_backward_speeds: Vec<Option<f64>>,
+  arrival_radii: Vec<f64>,
+  arrival_policies: Vec<ArrivalPolicy>,
+  waypoints: Vec<Vec<Vec2>>,
+  arrived: Vec<bool>,
+  // Navmesh cell of each agent as of its last step, if it was in the navmesh.
+  cells: Vec<Option<usize>>,
+  // Travel tracking.
+  departure_times: Vec<f64>,
+  departure_positions: Vec<Vec2>,
+  path_lengths: Vec<f64>,
+  arrival_times: Vec<Option<f64>>,
+  group_ids: Vec<Option<usize>>,
+  queue_ids: Vec<Option<usize>>,
+  service_times: Vec<f64>,
+  trajectories: Vec<Option<Trajectory>>,
+  groups: Vec<Group>,
+  queues: Vec<Queue>,
 }
 
 impl Agents {
   pub fn new() -> Self {
     Agents {
+      ids: Vec::new(),
+      next_id: 0,
+      kinds: Vec::new(),
       positions: Vec::new(),
       directions: Vec::new(),
       velocities: Vec::new(),
@@ -25,65 +58,182 @@ impl Agents {
       maximum_speeds: Vec::new(),
       maximum_accelerations: Vec::new(),
       radii: Vec::new(),
+      maximum_angular_speeds: Vec::new(),
+      smoothing_times: Vec::new(),
+      maximum_backward_speeds: Vec::new(),
+      arrival_radii: Vec::new(),
+      arrival_policies: Vec::new(),
+      waypoints: Vec::new(),
+      arrived: Vec::new(),
+      cells: Vec::new(),
+      departure_times: Vec::new(),
+      departure_positions: Vec::new(),
+      path_lengths: Vec::new(),
+      arrival_times: Vec::new(),
+      group_ids: Vec::new(),
+      queue_ids: Vec::new(),
+      service_times: Vec::new(),
+      trajectories: Vec::new(),
+      groups: Vec::new(),
+      queues: Vec::new(),
     }
   }
+  pub fn get_ids(&self) -> &[usize] {
+    &self.ids
+  }
+  pub fn get_kinds(&self) -> &[AgentKind] {
+    &self.kinds
+  }
   pub fn get_positions(&self) -> &[Vec2] {
     &self.positions
   }
+  #[cfg(test)]
   pub fn set_positions(&mut self, new_positions: &[Vec2]) {
-    self.positions = new_positions.to_vec();
+    copy_column(&mut self.positions, new_positions);
+  }
+  pub fn swap_positions(&mut self, new_positions: &mut Vec<Vec2>) {
+    swap_column(&mut self.positions, new_positions);
   }
   pub fn get_directions(&self) -> &[Vec2] {
     &self.directions
   }
-  pub fn set_directions(&mut self, new_directions: &[Vec2]) {
-    self.directions = new_directions.to_vec();
+  pub fn swap_directions(&mut self, new_directions: &mut Vec<Vec2>) {
+    swap_column(&mut self.directions, new_directions);
   }
   pub fn get_velocities(&self) -> &[Vec2] {
     &self.velocities
   }
-  pub fn set_velocities(&mut self, new_velocities: &[Vec2]) {
-    self.velocities = new_velocities.to_vec();
+  pub fn swap_velocities(&mut self, new_velocities: &mut Vec<Vec2>) {
+    swap_column(&mut self.velocities, new_velocities);
   }
   pub fn get_targets(&self) -> &[Vec2] {
     &self.targets
   }
-  #[allow(dead_code)]
-  pub fn set_targets(&mut self, new_targets: &[Vec2]) {
-    self.targets = new_targets.to_vec();
+  pub fn get_targets_mut(&mut self) -> &mut [Vec2] {
+    &mut self.targets
   }
   pub fn get_desired_speeds(&self) -> &[f64] {
     &self.desired_speeds
   }
-  #[allow(dead_code)]
-  pub fn set_desired_speeds(&mut self, new_desired_speeds: &[f64]) {
-    self.desired_speeds = new_desired_speeds.to_vec();
-  }
   pub fn get_maximum_speeds(&self) -> &[f64] {
     &self.maximum_speeds
   }
-  #[allow(dead_code)]
-  pub fn set_maximum_speeds(&mut self, new_maximum_speeds: &[f64]) {
-    self.maximum_speeds = new_maximum_speeds.to_vec();
-  }
   pub fn get_maximum_accelerations(&self) -> &[f64] {
     &self.maximum_accelerations
   }
-  #[allow(dead_code)]
-  pub fn set_maximum_accelerations(&mut self, new_maximum_accelerations: &[f64]) {
-    self.maximum_accelerations = new_maximum_accelerations.to_vec();
-  }
   pub fn get_radii(&self) -> &[f64] {
     &self.radii
   }
-  #[allow(dead_code)]
-  pub fn set_radii(&mut self, new_radii: &[f64]) {
-    self.radii = new_radii.to_vec();
+  pub fn get_maximum_angular_speeds(&self) -> &[f64] {
+    &self.maximum_angular_speeds
+  }
+  pub fn get_smoothing_times(&self) -> &[f64] {
+    &self.smoothing_times
+  }
+  pub fn get_maximum_backward_speeds(&self) -> &[Option<f64>] {
+    &self.maximum_backward_speeds
+  }
+  pub fn get_arrival_radii(&self) -> &[f64] {
+    &self.arrival_radii
+  }
+  pub fn get_arrival_policies(&self) -> &[ArrivalPolicy] {
+    &self.arrival_policies
+  }
+  pub fn get_waypoints(&self) -> &[Vec<Vec2>] {
+    &self.waypoints
+  }
+  pub fn get_waypoints_mut(&mut self) -> &mut [Vec<Vec2>] {
+    &mut self.waypoints
+  }
+  pub fn get_arrived(&self) -> &[bool] {
+    &self.arrived
+  }
+  #[cfg(test)]
+  pub fn set_arrived(&mut self, new_arrived: &[bool]) {
+    copy_column(&mut self.arrived, new_arrived);
+  }
+  pub fn swap_arrived(&mut self, new_arrived: &mut Vec<bool>) {
+    swap_column(&mut self.arrived, new_arrived);
+  }
+  pub fn get_cells(&self) -> &[Option<usize>] {
+    &self.cells
+  }
+  pub fn swap_cells(&mut self, new_cells: &mut Vec<Option<usize>>) {
+    swap_column(&mut self.cells, new_cells);
+  }
+  pub fn get_departure_times(&self) -> &[f64] {
+    &self.departure_times
+  }
+  pub fn get_departure_positions(&self) -> &[Vec2] {
+    &self.departure_positions
+  }
+  pub fn get_path_lengths(&self) -> &[f64] {
+    &self.path_lengths
+  }
+  pub fn swap_path_lengths(&mut self, new_path_lengths: &mut Vec<f64>) {
+    swap_column(&mut self.path_lengths, new_path_lengths);
+  }
+  pub fn get_arrival_times(&self) -> &[Option<f64>] {
+    &self.arrival_times
+  }
+  pub fn get_arrival_times_mut(&mut self) -> &mut [Option<f64>] {
+    &mut self.arrival_times
+  }
+  pub fn get_group_ids(&self) -> &[Option<usize>] {
+    &self.group_ids
+  }
+  pub fn get_groups(&self) -> &[Group] {
+    &self.groups
+  }
+  pub fn get_queue_ids(&self) -> &[Option<usize>] {
+    &self.queue_ids
+  }
+  pub fn swap_queue_ids(&mut self, new_queue_ids: &mut Vec<Option<usize>>) {
+    swap_column(&mut self.queue_ids, new_queue_ids);
+  }
+  pub fn get_service_times(&self) -> &[f64] {
+    &self.service_times
+  }
+  pub fn get_queues(&self) -> &[Queue] {
+    &self.queues
+  }
+  #[cfg(test)]
+  pub fn set_queues(&mut self, new_queues: &[Queue]) {
+    copy_column(&mut self.queues, new_queues);
+  }
+  pub fn swap_queues(&mut self, new_queues: &mut Vec<Queue>) {
+    swap_column(&mut self.queues, new_queues);
+  }
+  pub fn get_trajectories(&self) -> &[Option<Trajectory>] {
+    &self.trajectories
   }
   pub fn len(&self) -> usize {
     self.positions.len()
   }
+  #[allow(dead_code)]
+  pub fn is_empty(&self) -> bool {
+    self.positions.is_empty()
+  }
+  pub fn create_group(&mut self, group: Group) -> usize {
+    self.groups.push(group);
+    self.groups.len() - 1
+  }
+  pub fn create_queue(&mut self, queue: Queue) -> usize {
+    self.queues.push(queue);
+    self.queues.len() - 1
+  }
   pub fn create_agent(&mut self, agent: Agent) {
+    debug_assert!(
+      agent.group.is_none_or(|group| group < self.groups.len()),
+      "Can't add an agent to an unknown group."
+    );
+    debug_assert!(
+      agent.queue.is_none_or(|queue| queue < self.queues.len()),
+      "Can't send an agent to an unknown queue."
+    );
+    self.ids.push(self.next_id);
+    self.next_id += 1;
+    self.kinds.push(agent.kind);
     self.positions.push(agent.position);
     self.velocities.push(agent.velocity);
     self.directions.push(agent.direction);
@@ -92,23 +242,83 @@ impl Agents {
     self.maximum_speeds.push(agent.maximum_speed);
     self.maximum_accelerations.push(agent.maximum_acceleration);
     self.radii.push(agent.radius);
+    self
+      .maximum_angular_speeds
+      .push(agent.maximum_angular_speed);
+    self.smoothing_times.push(agent.smoothing_time);
+    self
+      .maximum_backward_speeds
+      .push(agent.maximum_backward_speed);
+    self.arrival_radii.push(agent.arrival_radius);
+    self.arrival_policies.push(agent.arrival_policy);
+    self.waypoints.push(agent.waypoints);
+    self.arrived.push(agent.arrived);
+    self.cells.push(None);
+    self.departure_times.push(agent.departure_time);
+    self.departure_positions.push(agent.position);
+    self.path_lengths.push(0.);
+    self.arrival_times.push(None);
+    self.group_ids.push(agent.group);
+    self.queue_ids.push(agent.queue);
+    self.service_times.push(agent.service_time);
+    self.trajectories.push(agent.trajectory);
   }
   pub fn retrieve_agent(&self, idx_agent: usize) -> Agent {
-    Agent::new()
-      .position(self.positions[idx_agent].x(), self.positions[idx_agent].y())
-      .direction(
-        self.directions[idx_agent].x(),
-        self.directions[idx_agent].y(),
-      )
-      .velocity(
-        self.velocities[idx_agent].x(),
-        self.velocities[idx_agent].y(),
-      )
-      .target(self.targets[idx_agent].x(), self.targets[idx_agent].y())
-      .desired_speed(self.desired_speeds[idx_agent])
-      .maximum_speed(self.maximum_speeds[idx_agent])
-      .maximum_acceleration(self.maximum_accelerations[idx_agent])
-      .radius(self.radii[idx_agent])
+    Agent {
+      kind: self.kinds[idx_agent],
+      position: self.positions[idx_agent],
+      velocity: self.velocities[idx_agent],
+      direction: self.directions[idx_agent],
+      target: self.targets[idx_agent],
+      desired_speed: self.desired_speeds[idx_agent],
+      maximum_speed: self.maximum_speeds[idx_agent],
+      maximum_acceleration: self.maximum_accelerations[idx_agent],
+      radius: self.radii[idx_agent],
+      maximum_angular_speed: self.maximum_angular_speeds[idx_agent],
+      smoothing_time: self.smoothing_times[idx_agent],
+      maximum_backward_speed: self.maximum_backward_speeds[idx_agent],
+      arrival_radius: self.arrival_radii[idx_agent],
+      arrival_policy: self.arrival_policies[idx_agent],
+      waypoints: self.waypoints[idx_agent].clone(),
+      arrived: self.arrived[idx_agent],
+      departure_time: self.departure_times[idx_agent],
+      group: self.group_ids[idx_agent],
+      queue: self.queue_ids[idx_agent],
+      service_time: self.service_times[idx_agent],
+      trajectory: self.trajectories[idx_agent].clone(),
+    }
+  }
+  pub fn remove_agent(&mut self, idx_agent: usize) {
+    self.ids.remove(idx_agent);
+    self.kinds.remove(idx_agent);
+    self.positions.remove(idx_agent);
+    self.velocities.remove(idx_agent);
+    self.directions.remove(idx_agent);
+    self.targets.remove(idx_agent);
+    self.desired_speeds.remove(idx_agent);
+    self.maximum_speeds.remove(idx_agent);
+    self.maximum_accelerations.remove(idx_agent);
+    self.radii.remove(idx_agent);
+    self.maximum_angular_speeds.remove(idx_agent);
+    self.smoothing_times.remove(idx_agent);
+    self.maximum_backward_speeds.remove(idx_agent);
+    self.arrival_radii.remove(idx_agent);
+    self.arrival_policies.remove(idx_agent);
+    self.waypoints.remove(idx_agent);
+    self.arrived.remove(idx_agent);
+    self.cells.remove(idx_agent);
+    self.departure_times.remove(idx_agent);
+    self.departure_positions.remove(idx_agent);
+    self.path_lengths.remove(idx_agent);
+    self.arrival_times.remove(idx_agent);
+    self.group_ids.remove(idx_agent);
+    self.queue_ids.remove(idx_agent);
+    self.service_times.remove(idx_agent);
+    self.trajectories.remove(idx_agent);
+    self
+      .queues
+      .iter_mut()
+      .for_each(|queue| queue.remove_agent(idx_agent));
   }
 }
 
@@ -118,6 +328,20 @@ impl Default for Agents {
   }
 }
 
+// Replace the content of a column, reusing its allocated buffer.
+#[cfg(test)]
+fn copy_column<T: Clone>(column: &mut Vec<T>, values: &[T]) {
+  column.clear();
+  column.extend_from_slice(values);
+}
+
+// Exchange a column with values computed elsewhere, without copying them,
+// the previous values are handed back.
+fn swap_column<T>(column: &mut Vec<T>, values: &mut Vec<T>) {
+  debug_assert_eq!(column.len(), values.len());
+  mem::swap(column, values);
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -132,10 +356,74 @@ mod tests {
   fn test_create_agent() {
     let mut agents = Agents::new();
     let created_agent = Agent::new().position(2.0, 3.0).velocity(0., 0.);
-    agents.create_agent(created_agent);
+    agents.create_agent(created_agent.clone());
     assert_eq!(agents.len(), 1);
     itertools::assert_equal(agents.get_positions(), &vec![Vec2::new(2.0, 3.0)]);
     itertools::assert_equal(agents.get_velocities(), &vec![Vec2::new(0., 0.)]);
     assert_eq!(agents.retrieve_agent(0), created_agent);
   }
+
+  #[test]
+  fn test_remove_agent() {
+    let mut agents = Agents::new();
+    let queue = agents.create_queue(Queue::new(2.0, 2.0));
+    agents.create_agent(Agent::new().position(0.0, 0.0).queue(queue));
+    agents.create_agent(Agent::new().position(1.0, 0.0).queue(queue));
+    agents.create_agent(Agent::new().position(2.0, 0.0).queue(queue));
+    let mut queues = agents.get_queues().to_vec();
+    queues[0].join(2);
+    queues[0].join(0);
+    agents.set_queues(&queues);
+
+    agents.remove_agent(1);
+    assert_eq!(agents.len(), 2);
+    itertools::assert_equal(agents.get_ids(), &vec![0, 2]);
+    itertools::assert_equal(
+      agents.get_positions(),
+      &vec![Vec2::new(0.0, 0.0), Vec2::new(2.0, 0.0)],
+    );
+    assert_eq!(agents.get_queues()[0].get_members(), &[1, 0]);
+
+    agents.remove_agent(0);
+    assert_eq!(agents.len(), 1);
+    itertools::assert_equal(agents.get_ids(), &vec![2]);
+    assert_eq!(agents.get_queues()[0].get_members(), &[0]);
+  }
+
+  #[test]
+  fn test_create_grouped_agents() {
+    let mut agents = Agents::new();
+    let group = agents.create_group(Group::new().spacing(1.0));
+    agents.create_agent(Agent::new().position(0.0, 0.0).group(group));
+    agents.create_agent(Agent::new().position(1.0, 0.0).group(group));
+    agents.create_agent(Agent::new().position(5.0, 0.0));
+    assert_eq!(agents.get_groups().len(), 1);
+    itertools::assert_equal(agents.get_group_ids(), &vec![Some(0), Some(0), None]);
+    assert_eq!(agents.retrieve_agent(1).group, Some(0));
+  }
+
+  #[test]
+  fn test_create_queuing_agent() {
+    let mut agents = Agents::new();
+    let queue = agents.create_queue(Queue::new(2.0, 2.0));
+    agents.create_agent(Agent::new().position(0.0, 0.0).queue(queue));
+    assert_eq!(agents.get_queues().len(), 1);
+    itertools::assert_equal(agents.get_queue_ids(), &vec![Some(0)]);
+    assert_eq!(agents.retrieve_agent(0).queue, Some(0));
+  }
+
+  #[test]
+  fn test_create_scripted_agent() {
+    let mut agents = Agents::new();
+    let created_agent = Agent::new()
+      .position(1.0, 0.0)
+      .trajectory(Trajectory::new(&[
+        (0.0, Vec2::new(1.0, 0.0)),
+        (1.0, Vec2::new(2.0, 0.0)),
+      ]));
+    agents.create_agent(created_agent.clone());
+    itertools::assert_equal(agents.get_kinds(), &vec![AgentKind::Scripted]);
+    assert!(agents.get_trajectories()[0].is_some());
+    assert_eq!(agents.retrieve_agent(0), created_agent);
+  }
 }
@@ -0,0 +1,188 @@
+use super::agent::AgentKind;
+use super::navmesh::Navmesh;
+use super::neighborhood::AgentNeighborhood;
+use super::random::Random;
+use super::vec2::Vec2;
+use itertools::izip;
+
+// Distance between a wandering agent and its successive targets.
+pub const WANDER_DISTANCE: f64 = 5.0;
+
+const WANDER_ATTEMPTS_COUNT: usize = 8;
+
+// Update the arrival state of the agents.
+//
+// An agent arrives when it gets within its arrival radius of its target, or
+// when its target is already taken by an arrived agent it is touching, to
+// avoid several agents sharing a target pushing each other forever. Agents
+// going through a queue and scripted agents never arrive.
+//
+// Touching agents being close, only the neighbors of each agent are checked.
+#[allow(clippy::too_many_arguments)]
+pub fn update_arrivals(
+  positions: &[Vec2],
+  targets: &[Vec2],
+  radii: &[f64],
+  arrival_radii: &[f64],
+  kinds: &[AgentKind],
+  queue_ids: &[Option<usize>],
+  arrived: &[bool],
+  neighborhoods: &[AgentNeighborhood],
+  updated_arrived: &mut [bool],
+) {
+  izip!(
+    positions,
+    targets,
+    radii,
+    arrival_radii,
+    kinds,
+    queue_ids,
+    arrived,
+    neighborhoods,
+    updated_arrived
+  )
+  .for_each(
+    |(
+      &position,
+      &target,
+      &radius,
+      &arrival_radius,
+      &kind,
+      queue_id,
+      &agent_arrived,
+      neighborhood,
+      updated_agent_arrived,
+    )| {
+      *updated_agent_arrived = if agent_arrived {
+        true
+      } else if kind == AgentKind::Scripted || queue_id.is_some() {
+        false
+      } else if (target - position).norm() <= arrival_radius {
+        true
+      } else {
+        neighborhood
+          .get_neighbors_indices()
+          .iter()
+          .any(|&idx_other| {
+            arrived[idx_other]
+              && (targets[idx_other] - target).norm() <= arrival_radius
+              && (positions[idx_other] - position).norm()
+                <= radius + radii[idx_other] + arrival_radius
+          })
+      }
+    },
+  );
+}
+
+// Pick a new target for a wandering agent in a random direction, picking
+// again as long as the target is outside the navmesh.
+//
+// Returns the agent's position when no navigable target could be found.
+pub fn wander_target(position: Vec2, navmesh: &Navmesh, random: &mut Random) -> Vec2 {
+  (0..WANDER_ATTEMPTS_COUNT)
+    .map(|_| {
+      let angle = random.uniform(-std::f64::consts::PI, std::f64::consts::PI);
+      position + Vec2::new(WANDER_DISTANCE, 0.).rotate(angle)
+    })
+    .find(|target| navmesh.locate(target, None).is_some())
+    .unwrap_or(position)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::navmesh::NavmeshBuilder;
+  use approx::assert_relative_eq;
+
+  #[test]
+  fn test_update_arrivals() {
+    let p = vec![
+      Vec2::new(0.1, 0.0),
+      Vec2::new(0.0, 0.8),
+      Vec2::new(5.0, 0.0),
+      Vec2::new(0.0, 0.0),
+      Vec2::new(3.0, 0.0),
+    ];
+    let t = vec![
+      Vec2::new(0.0, 0.0),
+      Vec2::new(0.0, 0.0),
+      Vec2::new(0.0, 0.0),
+      Vec2::new(0.0, 0.0),
+      Vec2::new(3.0, 0.0),
+    ];
+    let r = vec![0.35; 5];
+    let ar = vec![0.2; 5];
+    let k = vec![
+      AgentKind::Navigating,
+      AgentKind::Navigating,
+      AgentKind::Navigating,
+      AgentKind::Navigating,
+      AgentKind::Scripted,
+    ];
+    let qi = vec![None, None, None, Some(0), None];
+    let a = vec![false; 5];
+    let mut n = vec![AgentNeighborhood::default(); 5];
+    AgentNeighborhood::compute_agents_neighborhood(
+      &p,
+      &[Vec2::default(); 5],
+      &r,
+      &k,
+      &a,
+      &[None; 5],
+      &[],
+      &mut n,
+    );
+    let mut updated_a = vec![false; 5];
+
+    // The first agent is on its target.
+    update_arrivals(&p, &t, &r, &ar, &k, &qi, &a, &n, &mut updated_a);
+    itertools::assert_equal(&updated_a, &vec![true, false, false, false, false]);
+
+    // The second agent touches the first one, which took their shared target.
+    let a = updated_a.clone();
+    update_arrivals(&p, &t, &r, &ar, &k, &qi, &a, &n, &mut updated_a);
+    itertools::assert_equal(&updated_a, &vec![true, true, false, false, false]);
+
+    // Agents out of the neighborhoods aren't checked.
+    update_arrivals(
+      &p,
+      &t,
+      &r,
+      &ar,
+      &k,
+      &qi,
+      &[true, false, false, false, false],
+      &vec![AgentNeighborhood::default(); 5],
+      &mut updated_a,
+    );
+    itertools::assert_equal(&updated_a, &vec![true, false, false, false, false]);
+  }
+
+  #[test]
+  fn test_wander_target() {
+    let navmesh = NavmeshBuilder::new()
+      .add_cell(
+        Vec2::new(-10.0, -10.0),
+        Vec2::new(10.0, -10.0),
+        Vec2::new(10.0, 10.0),
+      )
+      .add_cell(
+        Vec2::new(-10.0, -10.0),
+        Vec2::new(-10.0, 10.0),
+        Vec2::new(10.0, 10.0),
+      )
+      .build()
+      .unwrap();
+    let mut random = Random::new(3);
+    let position = Vec2::new(8.0, 0.0);
+    let target = wander_target(position, &navmesh, &mut random);
+    assert_relative_eq!((target - position).norm(), WANDER_DISTANCE);
+    assert!(navmesh.locate(&target, None).is_some());
+
+    let empty_navmesh = NavmeshBuilder::new().build().unwrap();
+    assert_eq!(
+      wander_target(position, &empty_navmesh, &mut random),
+      position
+    );
+  }
+}
@@ -0,0 +1,314 @@
+use rusty_pedestrians_engine::metrics::MetricsConfig;
+use rusty_pedestrians_engine::sweep::{self, SweepConfig};
+use rusty_pedestrians_engine::Universe;
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process;
+
+const USAGE: &str = "\
+Usage: rusty_pedestrians <command> [options]
+
+Commands:
+  simulate <scenario.json>   Run a scenario and write its results
+  sweep <sweep.json>         Run a scenario over a parameters grid and write
+                             a summary table of the runs
+  help                       Print this message
+
+Simulate options:
+  --duration <seconds>         Simulated duration (default 60)
+  --timestep <seconds>         Duration of a simulation step (default 0.1)
+  --sampling-period <seconds>  Period of the written samples (default 0.5)
+  --metrics <config.json>      Metrics configuration (default metrics)
+  --output <directory>         Where the results are written (default .)
+  --quiet                      Don't print the progress
+
+Sweep options:
+  --threads <count>            Number of runs simulated in parallel (default 1)
+  --output <directory>         Where the summary table is written (default .)
+  --quiet                      Don't print the progress";
+
+const EXIT_FAILURE: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+
+#[derive(Clone, Debug, PartialEq)]
+struct SimulateOptions {
+  scenario_path: PathBuf,
+  duration: f64,
+  timestep: f64,
+  sampling_period: f64,
+  metrics_config_path: Option<PathBuf>,
+  output_path: PathBuf,
+  quiet: bool,
+}
+
+fn parse_simulate_options(args: &[String]) -> Result<SimulateOptions, String> {
+  let mut options = SimulateOptions {
+    scenario_path: PathBuf::new(),
+    duration: 60.,
+    timestep: 0.1,
+    sampling_period: 0.5,
+    metrics_config_path: None,
+    output_path: PathBuf::from("."),
+    quiet: false,
+  };
+  let mut scenario_path = None;
+  let mut args = args.iter();
+  while let Some(arg) = args.next() {
+    let mut value = |name: &str| {
+      args
+        .next()
+        .ok_or_else(|| format!("Missing value for {}", name))
+    };
+    let positive_number = |name: &str, value: &str| match value.parse::<f64>() {
+      Ok(number) if number > 0. && number.is_finite() => Ok(number),
+      _ => Err(format!("Invalid value \"{}\" for {}", value, name)),
+    };
+    match arg.as_str() {
+      "--duration" => options.duration = positive_number(arg, value(arg)?)?,
+      "--timestep" => options.timestep = positive_number(arg, value(arg)?)?,
+      "--sampling-period" => options.sampling_period = positive_number(arg, value(arg)?)?,
+      "--metrics" => options.metrics_config_path = Some(PathBuf::from(value(arg)?)),
+      "--output" => options.output_path = PathBuf::from(value(arg)?),
+      "--quiet" => options.quiet = true,
+      _ if arg.starts_with("--") => return Err(format!("Unknown option {}", arg)),
+      _ if scenario_path.is_none() => scenario_path = Some(PathBuf::from(arg)),
+      _ => return Err(format!("Unexpected argument {}", arg)),
+    }
+  }
+  options.scenario_path = scenario_path.ok_or("Missing scenario file")?;
+  Ok(options)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct SweepOptions {
+  config_path: PathBuf,
+  threads_count: usize,
+  output_path: PathBuf,
+  quiet: bool,
+}
+
+fn parse_sweep_options(args: &[String]) -> Result<SweepOptions, String> {
+  let mut options = SweepOptions {
+    config_path: PathBuf::new(),
+    threads_count: 1,
+    output_path: PathBuf::from("."),
+    quiet: false,
+  };
+  let mut config_path = None;
+  let mut args = args.iter();
+  while let Some(arg) = args.next() {
+    let mut value = |name: &str| {
+      args
+        .next()
+        .ok_or_else(|| format!("Missing value for {}", name))
+    };
+    match arg.as_str() {
+      "--threads" => {
+        let threads_count = value(arg)?;
+        options.threads_count = match threads_count.parse::<usize>() {
+          Ok(count) if count > 0 => count,
+          _ => return Err(format!("Invalid value \"{}\" for {}", threads_count, arg)),
+        }
+      }
+      "--output" => options.output_path = PathBuf::from(value(arg)?),
+      "--quiet" => options.quiet = true,
+      _ if arg.starts_with("--") => return Err(format!("Unknown option {}", arg)),
+      _ if config_path.is_none() => config_path = Some(PathBuf::from(arg)),
+      _ => return Err(format!("Unexpected argument {}", arg)),
+    }
+  }
+  options.config_path = config_path.ok_or("Missing sweep file")?;
+  Ok(options)
+}
+
+fn read_file(path: &PathBuf) -> Result<String, String> {
+  fs::read_to_string(path).map_err(|err| format!("Unable to read {}: {}", path.display(), err))
+}
+
+fn write_file(path: PathBuf, content: &str) -> Result<(), String> {
+  fs::write(&path, content).map_err(|err| format!("Unable to write {}: {}", path.display(), err))
+}
+
+fn simulate(options: &SimulateOptions) -> Result<(), String> {
+  let scenario_data = read_file(&options.scenario_path)?;
+  let metrics_config = match &options.metrics_config_path {
+    Some(path) => serde_json::from_str::<MetricsConfig>(&read_file(path)?)
+      .map_err(|err| format!("Invalid metrics config {}: {}", path.display(), err))?,
+    None => MetricsConfig::default(),
+  };
+  fs::create_dir_all(&options.output_path).map_err(|err| {
+    format!(
+      "Unable to create {}: {}",
+      options.output_path.display(),
+      err
+    )
+  })?;
+
+  let mut universe = Universe::new();
+  universe.try_set_timestep(options.timestep)?;
+  universe.try_start_recording(options.sampling_period, true)?;
+  universe.set_metrics(metrics_config)?;
+  universe
+    .try_load_scenario(&scenario_data)
+    .map_err(|err| format!("{}: {}", options.scenario_path.display(), err))?;
+
+  let steps_count = (options.duration / options.timestep).round() as usize;
+  let sampling_steps_count = ((options.sampling_period / options.timestep).round() as usize).max(1);
+  let mut metrics_lines = String::new();
+  let mut progress = None;
+  for step_index in 1..=steps_count {
+    universe.try_step()?;
+    if step_index % sampling_steps_count == 0 || step_index == steps_count {
+      metrics_lines.push_str(&universe.render_metrics());
+      metrics_lines.push('\n');
+    }
+    let step_progress = 100 * step_index / steps_count;
+    if !options.quiet && progress != Some(step_progress) {
+      progress = Some(step_progress);
+      eprint!(
+        "\rSimulating {}: {:>3}% ({} agents)",
+        options.scenario_path.display(),
+        step_progress,
+        universe.count_agents()
+      );
+      io::stderr().flush().ok();
+    }
+  }
+  if !options.quiet {
+    eprintln!();
+  }
+
+  write_file(
+    options.output_path.join("trajectories.csv"),
+    &universe.export_recording_to_csv().unwrap_or_default(),
+  )?;
+  write_file(options.output_path.join("metrics.jsonl"), &metrics_lines)?;
+  write_file(
+    options.output_path.join("travels.csv"),
+    &universe.export_travels_to_csv(),
+  )?;
+  write_file(
+    options.output_path.join("travels_summary.json"),
+    &universe.render_travels_summary(),
+  )?;
+  Ok(())
+}
+
+fn run_sweep(options: &SweepOptions) -> Result<(), String> {
+  let config = serde_json::from_str::<SweepConfig>(&read_file(&options.config_path)?)
+    .map_err(|err| format!("Invalid sweep {}: {}", options.config_path.display(), err))?;
+  fs::create_dir_all(&options.output_path).map_err(|err| {
+    format!(
+      "Unable to create {}: {}",
+      options.output_path.display(),
+      err
+    )
+  })?;
+  if !options.quiet {
+    eprintln!(
+      "Sweeping {}: {} runs on {} threads",
+      options.config_path.display(),
+      config.runs().len(),
+      options.threads_count
+    );
+  }
+  let summaries = sweep::run_sweep(&config, options.threads_count)?;
+  write_file(
+    options.output_path.join("sweep.csv"),
+    &sweep::to_csv(&config.parameters_names(), &summaries),
+  )
+}
+
+fn main() {
+  let args: Vec<String> = env::args().skip(1).collect();
+  let result = match args.first().map(String::as_str) {
+    Some("simulate") => match parse_simulate_options(&args[1..]) {
+      Ok(options) => simulate(&options),
+      Err(err) => {
+        eprintln!("{}\n\n{}", err, USAGE);
+        process::exit(EXIT_USAGE);
+      }
+    },
+    Some("sweep") => match parse_sweep_options(&args[1..]) {
+      Ok(options) => run_sweep(&options),
+      Err(err) => {
+        eprintln!("{}\n\n{}", err, USAGE);
+        process::exit(EXIT_USAGE);
+      }
+    },
+    Some("help") | Some("--help") | Some("-h") => {
+      println!("{}", USAGE);
+      Ok(())
+    }
+    _ => {
+      eprintln!("{}", USAGE);
+      process::exit(EXIT_USAGE);
+    }
+  };
+  if let Err(err) = result {
+    eprintln!("Error: {}", err);
+    process::exit(EXIT_FAILURE);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn to_args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+  }
+
+  #[test]
+  fn test_parse_simulate_options() {
+    let options = parse_simulate_options(&to_args(&[
+      "scenario.json",
+      "--duration",
+      "10",
+      "--timestep",
+      "0.05",
+      "--output",
+      "out",
+      "--quiet",
+    ]))
+    .unwrap();
+    assert_eq!(
+      options,
+      SimulateOptions {
+        scenario_path: PathBuf::from("scenario.json"),
+        duration: 10.,
+        timestep: 0.05,
+        sampling_period: 0.5,
+        metrics_config_path: None,
+        output_path: PathBuf::from("out"),
+        quiet: true,
+      }
+    );
+    assert!(parse_simulate_options(&to_args(&[])).is_err());
+    assert!(parse_simulate_options(&to_args(&["a.json", "b.json"])).is_err());
+    assert!(parse_simulate_options(&to_args(&["a.json", "--duration"])).is_err());
+    assert!(parse_simulate_options(&to_args(&["a.json", "--duration", "-1"])).is_err());
+    assert!(parse_simulate_options(&to_args(&["a.json", "--duration", "inf"])).is_err());
+    assert!(parse_simulate_options(&to_args(&["a.json", "--timestep", "NaN"])).is_err());
+    assert!(parse_simulate_options(&to_args(&["a.json", "--unknown"])).is_err());
+  }
+
+  #[test]
+  fn test_parse_sweep_options() {
+    let options =
+      parse_sweep_options(&to_args(&["sweep.json", "--threads", "4", "--quiet"])).unwrap();
+    assert_eq!(
+      options,
+      SweepOptions {
+        config_path: PathBuf::from("sweep.json"),
+        threads_count: 4,
+        output_path: PathBuf::from("."),
+        quiet: true,
+      }
+    );
+    assert!(parse_sweep_options(&to_args(&[])).is_err());
+    assert!(parse_sweep_options(&to_args(&["sweep.json", "--threads", "0"])).is_err());
+  }
+}
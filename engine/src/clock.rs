@@ -0,0 +1,136 @@
+use crate::error::{check_positive, Error};
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_TIMESTEP: f64 = 1. / 60.;
+pub const DEFAULT_MAXIMUM_SUBSTEPS_COUNT: usize = 8;
+
+// Simulated time, and conversion of the elapsed real time to a number of
+// fixed simulation steps.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Clock {
+  // Elapsed simulated time.
+  time: f64,
+  // Number of steps computed so far.
+  step_index: usize,
+  // Duration of the last step.
+  last_dt: f64,
+  // Duration of the fixed steps.
+  timestep: f64,
+  // Maximum number of fixed steps computed for one real time update, the
+  // remaining real time is dropped to let slow hosts catch up.
+  maximum_substeps_count: usize,
+  // Real time not yet simulated.
+  accumulator: f64,
+}
+
+impl Clock {
+  pub fn new() -> Self {
+    Clock {
+      time: 0.,
+      step_index: 0,
+      last_dt: 0.,
+      timestep: DEFAULT_TIMESTEP,
+      maximum_substeps_count: DEFAULT_MAXIMUM_SUBSTEPS_COUNT,
+      accumulator: 0.,
+    }
+  }
+
+  pub fn get_time(&self) -> f64 {
+    self.time
+  }
+
+  pub fn get_step_index(&self) -> usize {
+    self.step_index
+  }
+
+  pub fn get_last_dt(&self) -> f64 {
+    self.last_dt
+  }
+
+  pub fn get_timestep(&self) -> f64 {
+    self.timestep
+  }
+
+  pub fn set_timestep(&mut self, timestep: f64) -> Result<(), Error> {
+    check_positive("timestep", timestep)?;
+    self.timestep = timestep;
+    Ok(())
+  }
+
+  pub fn set_maximum_substeps_count(&mut self, maximum_substeps_count: usize) -> Result<(), Error> {
+    check_positive("maximum_substeps_count", maximum_substeps_count as f64)?;
+    self.maximum_substeps_count = maximum_substeps_count;
+    Ok(())
+  }
+
+  // Go back to the beginning of the simulation, keeping the configuration.
+  pub fn reset(&mut self) {
+    self.time = 0.;
+    self.step_index = 0;
+    self.last_dt = 0.;
+    self.accumulator = 0.;
+  }
+
+  // Account for a computed step.
+  pub fn tick(&mut self, dt: f64) {
+    self.time += dt;
+    self.step_index += 1;
+    self.last_dt = dt;
+  }
+
+  // Accumulate elapsed real time.
+  //
+  // Returns the number of fixed steps to compute.
+  pub fn accumulate(&mut self, real_dt: f64) -> usize {
+    self.accumulator += real_dt.max(0.);
+    let steps_count = (self.accumulator / self.timestep).floor() as usize;
+    if steps_count > self.maximum_substeps_count {
+      self.accumulator = 0.;
+      self.maximum_substeps_count
+    } else {
+      self.accumulator -= steps_count as f64 * self.timestep;
+      steps_count
+    }
+  }
+}
+
+impl Default for Clock {
+  fn default() -> Self {
+    Clock::new()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use approx::assert_relative_eq;
+
+  #[test]
+  fn test_tick() {
+    let mut clock = Clock::new();
+    clock.tick(0.5);
+    clock.tick(0.25);
+    assert_relative_eq!(clock.get_time(), 0.75);
+    assert_eq!(clock.get_step_index(), 2);
+    assert_relative_eq!(clock.get_last_dt(), 0.25);
+    clock.reset();
+    assert_relative_eq!(clock.get_time(), 0.);
+    assert_eq!(clock.get_step_index(), 0);
+  }
+
+  #[test]
+  fn test_accumulate() {
+    let mut clock = Clock::new();
+    clock.set_timestep(0.1).unwrap();
+    clock.set_maximum_substeps_count(3).unwrap();
+    assert!(clock.set_timestep(0.).is_err());
+    assert!(clock.set_timestep(f64::INFINITY).is_err());
+    assert!(clock.set_maximum_substeps_count(0).is_err());
+    assert_eq!(clock.accumulate(0.05), 0);
+    assert_eq!(clock.accumulate(0.1), 1);
+    assert_eq!(clock.accumulate(0.16), 2);
+    // Too much time elapsed, the backlog is dropped.
+    assert_eq!(clock.accumulate(1.), 3);
+    assert_eq!(clock.accumulate(0.05), 0);
+  }
+}
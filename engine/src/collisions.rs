@@ -0,0 +1,150 @@
+use super::navmesh::Navmesh;
+use super::neighborhood::AgentNeighborhood;
+use super::vec2::Vec2;
+use itertools::izip;
+use serde::Serialize;
+use std::vec::Vec;
+
+// Overlaps shallower than this are numerical noise of agents in contact.
+pub const OVERLAP_TOLERANCE: f64 = 1e-4;
+
+// Two agents whose discs overlap.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct AgentsOverlap {
+  pub agent: usize,
+  pub other_agent: usize,
+  pub depth: f64,
+}
+
+// An agent whose disc goes through the boundary of the navigable area.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct WallPenetration {
+  pub agent: usize,
+  pub depth: f64,
+}
+
+// Pairs of overlapping agents, each pair is listed once with `agent` being
+// the lowest index.
+//
+// Overlapping agents being close, only the neighbors of each agent are
+// checked.
+pub fn detect_agents_overlaps(
+  positions: &[Vec2],
+  radii: &[f64],
+  neighborhoods: &[AgentNeighborhood],
+) -> Vec<AgentsOverlap> {
+  let mut overlaps: Vec<AgentsOverlap> = izip!(positions, radii, neighborhoods)
+    .enumerate()
+    .flat_map(|(agent, (&position, &radius, neighborhood))| {
+      neighborhood
+        .get_neighbors_indices()
+        .iter()
+        .filter_map(move |&other_agent| {
+          let depth = radius + radii[other_agent] - (positions[other_agent] - position).norm();
+          if depth > OVERLAP_TOLERANCE {
+            Some(AgentsOverlap {
+              agent: agent.min(other_agent),
+              other_agent: agent.max(other_agent),
+              depth,
+            })
+          } else {
+            None
+          }
+        })
+    })
+    .collect();
+  // Agents are usually neighbors of each other, their overlap is found twice.
+  overlaps.sort_by_key(|overlap| (overlap.agent, overlap.other_agent));
+  overlaps.dedup_by_key(|overlap| (overlap.agent, overlap.other_agent));
+  overlaps
+}
+
+// Agents overlapping the navmesh boundary.
+//
+// The depth is how far the disc goes beyond the boundary, agents whose center
+// is out of the navmesh are penetrating by more than their radius.
+//
+// Agents are located from their known cells, only the boundary edges within
+// their radius are then checked.
+pub fn detect_walls_penetrations(
+  positions: &[Vec2],
+  radii: &[f64],
+  cells: &[Option<usize>],
+  navmesh: &Navmesh,
+) -> Vec<WallPenetration> {
+  izip!(positions, radii, cells)
+    .enumerate()
+    .filter_map(|(agent, (position, &radius, &cell))| {
+      let depth = match navmesh.locate(position, cell) {
+        Some(cell) => navmesh
+          .get_boundary_distance(position, Some(cell), radius)
+          .map(|distance| radius - distance),
+        None => navmesh
+          .get_boundary_distance(position, None, f64::INFINITY)
+          .map(|distance| radius + distance),
+      };
+      match depth {
+        Some(depth) if depth > OVERLAP_TOLERANCE => Some(WallPenetration { agent, depth }),
+        _ => None,
+      }
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::agent::AgentKind;
+  use crate::navmesh::NavmeshBuilder;
+  use approx::assert_relative_eq;
+
+  #[test]
+  fn test_detect_agents_overlaps() {
+    let positions = vec![
+      Vec2::new(0., 0.),
+      Vec2::new(0.5, 0.),
+      Vec2::new(1.2, 0.),
+      Vec2::new(0., 5.),
+    ];
+    let radii = vec![0.35, 0.35, 0.35, 0.35];
+    let mut neighborhoods = vec![AgentNeighborhood::default(); 4];
+    AgentNeighborhood::compute_agents_neighborhood(
+      &positions,
+      &[Vec2::default(); 4],
+      &radii,
+      &[AgentKind::Navigating; 4],
+      &[false; 4],
+      &[None; 4],
+      &[],
+      &mut neighborhoods,
+    );
+    let overlaps = detect_agents_overlaps(&positions, &radii, &neighborhoods);
+    assert_eq!(overlaps.len(), 1);
+    assert_eq!(overlaps[0].agent, 0);
+    assert_eq!(overlaps[0].other_agent, 1);
+    assert_relative_eq!(overlaps[0].depth, 0.2);
+  }
+
+  #[test]
+  fn test_detect_walls_penetrations() {
+    let navmesh = NavmeshBuilder::new()
+      .add_cell(Vec2::new(0., 0.), Vec2::new(4., 0.), Vec2::new(4., 4.))
+      .add_cell(Vec2::new(0., 0.), Vec2::new(4., 4.), Vec2::new(0., 4.))
+      .build()
+      .unwrap();
+    let positions = vec![
+      Vec2::new(2., 2.),
+      Vec2::new(0.25, 2.),
+      Vec2::new(2., 4.1),
+      Vec2::new(1.9, 1.9),
+    ];
+    let radii = vec![0.5, 0.5, 0.5, 0.5];
+    let cells = vec![Some(1), None, None, Some(7)];
+    let penetrations = detect_walls_penetrations(&positions, &radii, &cells, &navmesh);
+    assert_eq!(penetrations.len(), 2);
+    assert_eq!(penetrations[0].agent, 1);
+    assert_relative_eq!(penetrations[0].depth, 0.25);
+    assert_eq!(penetrations[1].agent, 2);
+    assert_relative_eq!(penetrations[1].depth, 0.6, epsilon = 1e-9);
+  }
+}
@@ -0,0 +1,384 @@
+use super::error::Error;
+use super::scenarii::{CustomAgent, CustomScenario};
+use super::trajectory::Trajectory;
+use super::vec2::Vec2;
+use std::collections::BTreeMap;
+use std::vec::Vec;
+
+// Margin added around the observed positions to build the navigable area.
+const BOUNDS_MARGIN: f64 = 2.0;
+
+// Trajectory of a real pedestrian, as recorded in a dataset.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObservedPedestrian {
+  pub id: usize,
+  pub trajectory: Trajectory,
+}
+
+// Parse the ETH/UCY `obsmat.txt` format.
+//
+// Each line is `frame id x z y vx vz vy`, positions in meters, the `z`
+// components being unused.
+pub fn parse_obsmat(data: &str, frame_rate: f64) -> Result<Vec<ObservedPedestrian>, Error> {
+  parse_columns(data, 8, |values| {
+    (
+      values[1],
+      values[0] / frame_rate,
+      Vec2::new(values[2], values[4]),
+    )
+  })
+}
+
+// Parse the Jülich PeTrack text format.
+//
+// Each line is `id frame x y [z]`, lines starting with `#` are comments.
+// Positions are multiplied by `unit_scale` to convert them to meters, e.g.
+// `0.01` for files exported in centimeters.
+pub fn parse_petrack(
+  data: &str,
+  frame_rate: f64,
+  unit_scale: f64,
+) -> Result<Vec<ObservedPedestrian>, Error> {
+  parse_columns(data, 4, |values| {
+    (
+      values[0],
+      values[1] / frame_rate,
+      unit_scale * Vec2::new(values[2], values[3]),
+    )
+  })
+}
+
+// Parse whitespace separated numeric columns into trajectories, each line
+// being converted to a `(id, time, position)` sample. Ids must be
+// non-negative integers, times and positions finite.
+fn parse_columns<F>(
+  data: &str,
+  minimum_columns_count: usize,
+  parse_line: F,
+) -> Result<Vec<ObservedPedestrian>, Error>
+where
+  F: Fn(&[f64]) -> (f64, f64, Vec2),
+{
+  let mut samples: BTreeMap<usize, Vec<(f64, Vec2)>> = BTreeMap::new();
+  for (idx_line, line) in data.lines().enumerate() {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+      continue;
+    }
+    let values = line
+      .split_whitespace()
+      .map(|value| value.parse::<f64>())
+      .collect::<Result<Vec<f64>, _>>()
+      .map_err(|_| Error::InvalidData(format!("Invalid number at line {}", idx_line + 1)))?;
+    if values.len() < minimum_columns_count {
+      return Err(Error::InvalidData(format!(
+        "Missing columns at line {}",
+        idx_line + 1
+      )));
+    }
+    let (id, time, position) = parse_line(&values);
+    if !(id >= 0. && id.fract() == 0. && id <= usize::MAX as f64) {
+      return Err(Error::InvalidData(format!(
+        "Invalid id {} at line {}",
+        id,
+        idx_line + 1
+      )));
+    }
+    if !(time.is_finite() && position.x().is_finite() && position.y().is_finite()) {
+      return Err(Error::InvalidData(format!(
+        "Non-finite sample at line {}",
+        idx_line + 1
+      )));
+    }
+    samples
+      .entry(id as usize)
+      .or_default()
+      .push((time, position));
+  }
+  Ok(
+    samples
+      .into_iter()
+      .map(|(id, samples)| ObservedPedestrian {
+        id,
+        trajectory: Trajectory::new(&samples),
+      })
+      .collect(),
+  )
+}
+
+// Initial conditions of a dataset, as a scenario.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObservedScenario {
+  pub scenario: CustomScenario,
+  // Observed pedestrians matching the scenario's agents, with their
+  // trajectories shifted so that the scenario starts at time 0.
+  pub pedestrians: Vec<ObservedPedestrian>,
+}
+
+// Build a scenario from the pedestrians observed at `start_time`.
+//
+// Each one becomes an agent walking from its position at `start_time` to its
+// last observed position at its observed average speed. Pedestrians entering
+// the scene later aren't simulated.
+pub fn to_scenario(pedestrians: &[ObservedPedestrian], start_time: f64) -> ObservedScenario {
+  let pedestrians: Vec<ObservedPedestrian> = pedestrians
+    .iter()
+    .filter(|pedestrian| {
+      pedestrian
+        .trajectory
+        .time_range()
+        .is_some_and(|(t_first, t_last)| t_first <= start_time && start_time < t_last)
+    })
+    .map(|pedestrian| {
+      let position = pedestrian.trajectory.position_at(start_time);
+      let samples: Vec<(f64, Vec2)> = std::iter::once((0., position))
+        .chain(
+          pedestrian
+            .trajectory
+            .get_samples()
+            .iter()
+            .filter(|&&(t, _)| t > start_time)
+            .map(|&(t, position)| (t - start_time, position)),
+        )
+        .collect();
+      ObservedPedestrian {
+        id: pedestrian.id,
+        trajectory: Trajectory::new(&samples),
+      }
+    })
+    .collect();
+
+  let positions = pedestrians
+    .iter()
+    .flat_map(|pedestrian| pedestrian.trajectory.get_samples().iter())
+    .map(|&(_, position)| position);
+  let bounds = positions.fold(
+    [f64::MAX, f64::MAX, f64::MIN, f64::MIN],
+    |[min_x, min_y, max_x, max_y], position| {
+      [
+        min_x.min(position.x()),
+        min_y.min(position.y()),
+        max_x.max(position.x()),
+        max_y.max(position.y()),
+      ]
+    },
+  );
+  let agents = pedestrians
+    .iter()
+    .map(|pedestrian| {
+      let samples = pedestrian.trajectory.get_samples();
+      let (_, position) = samples[0];
+      let (duration, target) = samples[samples.len() - 1];
+      let path_length: f64 = samples
+        .windows(2)
+        .map(|segment| (segment[1].1 - segment[0].1).norm())
+        .sum();
+      CustomAgent {
+        position: [position.x(), position.y()],
+        target: [target.x(), target.y()],
+        desired_speed: Some(path_length / duration),
+        ..CustomAgent::default()
+      }
+    })
+    .collect();
+  ObservedScenario {
+    scenario: CustomScenario {
+      bounds: if pedestrians.is_empty() {
+        CustomScenario::default().bounds
+      } else {
+        [
+          bounds[0] - BOUNDS_MARGIN,
+          bounds[1] - BOUNDS_MARGIN,
+          bounds[2] + BOUNDS_MARGIN,
+          bounds[3] + BOUNDS_MARGIN,
+        ]
+      },
+      agents,
+      ..CustomScenario::default()
+    },
+    pedestrians,
+  }
+}
+
+// Displacement errors between simulated and observed trajectories.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TrajectoriesComparison {
+  // Average displacement error, over every observed sample.
+  pub average_displacement_error: f64,
+  // Final displacement error, averaged over the pedestrians.
+  pub final_displacement_error: f64,
+}
+
+// Compare simulated trajectories to the observed ones, matched by index.
+pub fn compare_trajectories(
+  observed: &[Trajectory],
+  simulated: &[Trajectory],
+) -> Result<TrajectoriesComparison, Error> {
+  if observed.len() != simulated.len() {
+    return Err(Error::InvalidData(format!(
+      "{} observed trajectories for {} simulated ones",
+      observed.len(),
+      simulated.len()
+    )));
+  }
+  let (errors_sum, errors_count, final_errors_sum, pedestrians_count) = observed
+    .iter()
+    .zip(simulated)
+    .filter(|(observed, _)| !observed.is_empty())
+    .fold(
+      (0., 0, 0., 0),
+      |(errors_sum, errors_count, final_errors_sum, pedestrians_count), (observed, simulated)| {
+        let errors: Vec<f64> = observed
+          .get_samples()
+          .iter()
+          .map(|&(t, position)| (simulated.position_at(t) - position).norm())
+          .collect();
+        (
+          errors_sum + errors.iter().sum::<f64>(),
+          errors_count + errors.len(),
+          final_errors_sum + errors[errors.len() - 1],
+          pedestrians_count + 1,
+        )
+      },
+    );
+  Ok(TrajectoriesComparison {
+    average_displacement_error: if errors_count > 0 {
+      errors_sum / errors_count as f64
+    } else {
+      0.
+    },
+    final_displacement_error: if pedestrians_count > 0 {
+      final_errors_sum / pedestrians_count as f64
+    } else {
+      0.
+    },
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::Universe;
+  use approx::assert_relative_eq;
+
+  const OBSMAT: &str = "
+    0.0000000e+00   1.0000000e+00  -4.0000000e+00   0.0000000e+00   0.0000000e+00   1.0000000e+00   0.0000000e+00   0.0000000e+00
+    1.0000000e+01   1.0000000e+00  -3.0000000e+00   0.0000000e+00   0.0000000e+00   1.0000000e+00   0.0000000e+00   0.0000000e+00
+    1.0000000e+01   2.0000000e+00   4.0000000e+00   0.0000000e+00   3.0000000e+00  -1.0000000e+00   0.0000000e+00   0.0000000e+00
+    8.0000000e+01   1.0000000e+00   4.0000000e+00   0.0000000e+00   0.0000000e+00   1.0000000e+00   0.0000000e+00   0.0000000e+00
+    8.0000000e+01   2.0000000e+00  -3.0000000e+00   0.0000000e+00   3.0000000e+00  -1.0000000e+00   0.0000000e+00   0.0000000e+00
+  ";
+
+  #[test]
+  fn test_parse_obsmat() {
+    let pedestrians = parse_obsmat(OBSMAT, 10.).unwrap();
+    assert_eq!(pedestrians.len(), 2);
+    assert_eq!(pedestrians[0].id, 1);
+    assert_eq!(pedestrians[0].trajectory.time_range(), Some((0., 8.)));
+    assert_relative_eq!(pedestrians[1].trajectory.position_at(1.), Vec2::new(4., 3.));
+    assert!(parse_obsmat("1 2 3", 10.).is_err());
+    assert!(parse_obsmat("1 2 3 a 5 6 7 8", 10.).is_err());
+    assert!(matches!(
+      parse_obsmat("0 1.5 3 4 5 6 7 8", 10.),
+      Err(Error::InvalidData(_))
+    ));
+    assert!(matches!(
+      parse_obsmat("0 -1 3 4 5 6 7 8", 10.),
+      Err(Error::InvalidData(_))
+    ));
+    assert!(matches!(
+      parse_obsmat("NaN 1 3 4 5 6 7 8", 10.),
+      Err(Error::InvalidData(_))
+    ));
+    assert!(matches!(
+      parse_obsmat("0 1 inf 4 5 6 7 8", 10.),
+      Err(Error::InvalidData(_))
+    ));
+  }
+
+  #[test]
+  fn test_parse_petrack() {
+    let pedestrians = parse_petrack(
+      "# id frame x/cm y/cm z/cm\n\
+      1 0 100 200 170\n\
+      1 25 150 200 170\n\
+      2 0 -100 0 165\n",
+      25.,
+      0.01,
+    )
+    .unwrap();
+    assert_eq!(pedestrians.len(), 2);
+    assert_relative_eq!(
+      pedestrians[0].trajectory.position_at(1.),
+      Vec2::new(1.5, 2.)
+    );
+    assert_eq!(pedestrians[1].trajectory.len(), 1);
+  }
+
+  #[test]
+  fn test_to_scenario() {
+    let pedestrians = parse_obsmat(OBSMAT, 10.).unwrap();
+    let observed_scenario = to_scenario(&pedestrians, 0.5);
+    // The second pedestrian is only observed from 1s.
+    assert_eq!(observed_scenario.pedestrians.len(), 1);
+    assert_eq!(observed_scenario.scenario.agents.len(), 1);
+    let agent = &observed_scenario.scenario.agents[0];
+    assert_relative_eq!(agent.position[0], -3.5);
+    assert_relative_eq!(agent.target[0], 4.);
+    assert_relative_eq!(agent.desired_speed.unwrap(), 1.);
+    assert_eq!(
+      observed_scenario.pedestrians[0].trajectory.time_range(),
+      Some((0., 7.5))
+    );
+    assert_relative_eq!(observed_scenario.scenario.bounds[0], -5.5);
+  }
+
+  #[test]
+  fn test_compare_trajectories() {
+    let observed = vec![Trajectory::new(&[
+      (0., Vec2::new(0., 0.)),
+      (1., Vec2::new(1., 0.)),
+    ])];
+    let simulated = vec![Trajectory::new(&[
+      (0., Vec2::new(0., 0.)),
+      (1., Vec2::new(1., 1.)),
+    ])];
+    let comparison = compare_trajectories(&observed, &simulated).unwrap();
+    assert_relative_eq!(comparison.average_displacement_error, 0.5);
+    assert_relative_eq!(comparison.final_displacement_error, 1.);
+    assert!(matches!(
+      compare_trajectories(&observed, &[]),
+      Err(Error::InvalidData(_))
+    ));
+  }
+
+  #[test]
+  fn test_simulate_observed_scenario() {
+    let pedestrians = parse_obsmat(OBSMAT, 10.).unwrap();
+    let observed_scenario = to_scenario(&pedestrians, 0.);
+    let mut universe = Universe::new();
+    universe.try_set_timestep(0.1).unwrap();
+    universe.try_start_recording(0., false).unwrap();
+    universe
+      .set_scenario(Box::new(observed_scenario.scenario))
+      .unwrap();
+    (0..80).for_each(|_| universe.try_step().unwrap());
+    let simulated: Vec<Trajectory> = universe
+      .get_recorder()
+      .unwrap()
+      .to_trajectories()
+      .into_iter()
+      .map(|(_, trajectory)| trajectory)
+      .collect();
+    let observed: Vec<Trajectory> = observed_scenario
+      .pedestrians
+      .into_iter()
+      .map(|pedestrian| pedestrian.trajectory)
+      .collect();
+    let comparison = compare_trajectories(&observed, &simulated).unwrap();
+    // A lone pedestrian walking straight is closely reproduced, up to the
+    // acceleration phase.
+    assert!(comparison.average_displacement_error < 0.3);
+    assert!(comparison.final_displacement_error < 0.3);
+  }
+}
@@ -0,0 +1,355 @@
+use super::error::Error;
+use super::random::Random;
+use serde::Deserialize;
+use std::convert::TryFrom;
+use std::vec::Vec;
+
+const TRUNCATED_NORMAL_ATTEMPTS_COUNT: usize = 32;
+
+// Distribution of a scalar parameter, sampled from the seeded random numbers
+// generator.
+//
+// Deserializes from a plain number, for fixed values, or from an object tagged
+// by its `distribution` field, e.g.
+// `{ "distribution": "Uniform", "min": 1.2, "max": 1.6 }`.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "DistributionData")]
+pub enum Distribution {
+  Fixed(f64),
+  Uniform {
+    min: f64,
+    max: f64,
+  },
+  // Normal distribution truncated to [min, max].
+  Normal {
+    mean: f64,
+    standard_deviation: f64,
+    min: f64,
+    max: f64,
+  },
+  // Values drawn from a table, with the given relative weights or uniformly
+  // when no weights are provided.
+  Empirical {
+    values: Vec<f64>,
+    weights: Vec<f64>,
+  },
+}
+
+impl Distribution {
+  pub fn sample(&self, random: &mut Random) -> f64 {
+    match self {
+      Distribution::Fixed(value) => *value,
+      Distribution::Uniform { min, max } => random.uniform(*min, *max),
+      Distribution::Normal {
+        mean,
+        standard_deviation,
+        min,
+        max,
+      } => (0..TRUNCATED_NORMAL_ATTEMPTS_COUNT)
+        .map(|_| mean + standard_deviation * sample_standard_normal(random))
+        .find(|value| value >= min && value <= max)
+        // Extremely unlikely unless the bounds are far in the tails.
+        .unwrap_or_else(|| mean.max(*min).min(*max)),
+      Distribution::Empirical { values, weights } => {
+        if values.is_empty() {
+          return 0.;
+        }
+        if weights.is_empty() {
+          let idx = (random.next_f64() * values.len() as f64) as usize;
+          return values[idx.min(values.len() - 1)];
+        }
+        let total_weight: f64 = weights.iter().sum();
+        let mut threshold = random.next_f64() * total_weight;
+        values
+          .iter()
+          .zip(weights)
+          .find(|(_, &weight)| {
+            threshold -= weight;
+            threshold < 0.
+          })
+          .map_or(values[values.len() - 1], |(&value, _)| value)
+      }
+    }
+  }
+}
+
+// Box-Muller transform, relying on `libm` to stay bit-identical across targets.
+fn sample_standard_normal(random: &mut Random) -> f64 {
+  let u1 = 1. - random.next_f64();
+  let u2 = random.next_f64();
+  libm::sqrt(-2. * libm::log(u1)) * libm::cos(2. * std::f64::consts::PI * u2)
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DistributionData {
+  Fixed(f64),
+  Tagged(TaggedDistributionData),
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "distribution")]
+enum TaggedDistributionData {
+  Fixed {
+    value: f64,
+  },
+  Uniform {
+    min: f64,
+    max: f64,
+  },
+  Normal {
+    mean: f64,
+    standard_deviation: f64,
+    #[serde(default = "negative_infinity")]
+    min: f64,
+    #[serde(default = "positive_infinity")]
+    max: f64,
+  },
+  Empirical {
+    values: Vec<f64>,
+    #[serde(default)]
+    weights: Vec<f64>,
+  },
+}
+
+fn negative_infinity() -> f64 {
+  f64::NEG_INFINITY
+}
+
+fn positive_infinity() -> f64 {
+  f64::INFINITY
+}
+
+impl TryFrom<DistributionData> for Distribution {
+  type Error = Error;
+
+  fn try_from(data: DistributionData) -> Result<Self, Error> {
+    let distribution = match data {
+      DistributionData::Fixed(value) => Distribution::Fixed(value),
+      DistributionData::Tagged(TaggedDistributionData::Fixed { value }) => {
+        Distribution::Fixed(value)
+      }
+      DistributionData::Tagged(TaggedDistributionData::Uniform { min, max }) => {
+        Distribution::Uniform { min, max }
+      }
+      DistributionData::Tagged(TaggedDistributionData::Normal {
+        mean,
+        standard_deviation,
+        min,
+        max,
+      }) => Distribution::Normal {
+        mean,
+        standard_deviation,
+        min,
+        max,
+      },
+      DistributionData::Tagged(TaggedDistributionData::Empirical { values, weights }) => {
+        Distribution::Empirical { values, weights }
+      }
+    };
+    distribution.check()?;
+    Ok(distribution)
+  }
+}
+
+impl Distribution {
+  // Check the parameters describe a distribution that can be sampled.
+  fn check(&self) -> Result<(), Error> {
+    let invalid = |message: String| Err(Error::InvalidScenario(message));
+    match self {
+      Distribution::Fixed(value) if !value.is_finite() => {
+        invalid(format!("non finite fixed value {}", value))
+      }
+      Distribution::Uniform { min, max } if !min.is_finite() || !max.is_finite() || min > max => {
+        invalid(format!("uniform distribution over [{}, {}]", min, max))
+      }
+      Distribution::Normal {
+        mean,
+        standard_deviation,
+        ..
+      } if !mean.is_finite() || !standard_deviation.is_finite() || *standard_deviation < 0. => {
+        invalid(format!(
+          "normal distribution of mean {} and standard deviation {}",
+          mean, standard_deviation
+        ))
+      }
+      Distribution::Normal { min, max, .. } if min.is_nan() || max.is_nan() || min > max => {
+        invalid(format!(
+          "normal distribution truncated to [{}, {}]",
+          min, max
+        ))
+      }
+      Distribution::Empirical { values, .. } if values.is_empty() => {
+        invalid("empirical distribution without values".to_string())
+      }
+      Distribution::Empirical { values, .. } if values.iter().any(|value| !value.is_finite()) => {
+        invalid("empirical distribution with non finite values".to_string())
+      }
+      Distribution::Empirical { values, weights }
+        if !weights.is_empty() && weights.len() != values.len() =>
+      {
+        invalid(format!(
+          "empirical distribution with {} values and {} weights",
+          values.len(),
+          weights.len()
+        ))
+      }
+      Distribution::Empirical { weights, .. }
+        if weights
+          .iter()
+          .any(|weight| !weight.is_finite() || *weight < 0.)
+          || (!weights.is_empty() && weights.iter().sum::<f64>() <= 0.) =>
+      {
+        invalid("empirical distribution with invalid weights".to_string())
+      }
+      _ => Ok(()),
+    }
+  }
+
+  // Check the distribution of a strictly positive quantity, e.g. a radius:
+  // its fixed and empirical values are positive and random values can be.
+  // Only the tails of the random values then need clamping.
+  pub fn check_positive(&self, name: &str) -> Result<(), Error> {
+    let invalid = |message: String| Err(Error::InvalidScenario(message));
+    match self {
+      Distribution::Fixed(value) if *value <= 0. => {
+        invalid(format!("non positive fixed {} {}", name, value))
+      }
+      Distribution::Uniform { max, .. } | Distribution::Normal { max, .. } if *max <= 0. => {
+        invalid(format!("{} distribution without positive values", name))
+      }
+      Distribution::Empirical { values, .. } if values.iter().any(|value| *value <= 0.) => invalid(
+        format!("{} empirical distribution with non positive values", name),
+      ),
+      _ => Ok(()),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use approx::assert_relative_eq;
+
+  #[test]
+  fn test_deserialize() {
+    assert_eq!(
+      serde_json::from_str::<Distribution>("1.5").unwrap(),
+      Distribution::Fixed(1.5)
+    );
+    assert_eq!(
+      serde_json::from_str::<Distribution>(
+        "{ \"distribution\": \"Uniform\", \"min\": 1, \"max\": 2 }"
+      )
+      .unwrap(),
+      Distribution::Uniform { min: 1., max: 2. }
+    );
+    assert_eq!(
+      serde_json::from_str::<Distribution>(
+        "{ \"distribution\": \"Normal\", \"mean\": 1.3, \"standard_deviation\": 0.2, \"min\": 0.5 }"
+      )
+      .unwrap(),
+      Distribution::Normal {
+        mean: 1.3,
+        standard_deviation: 0.2,
+        min: 0.5,
+        max: f64::INFINITY
+      }
+    );
+    assert_eq!(
+      serde_json::from_str::<Distribution>(
+        "{ \"distribution\": \"Empirical\", \"values\": [1, 2], \"weights\": [3, 1] }"
+      )
+      .unwrap(),
+      Distribution::Empirical {
+        values: vec![1., 2.],
+        weights: vec![3., 1.]
+      }
+    );
+    assert!(serde_json::from_str::<Distribution>("{ \"distribution\": \"Unknown\" }").is_err());
+  }
+
+  #[test]
+  fn test_deserialize_invalid() {
+    [
+      "{ \"distribution\": \"Uniform\", \"min\": 2, \"max\": 1 }",
+      "{ \"distribution\": \"Normal\", \"mean\": 1.3, \"standard_deviation\": -0.2 }",
+      "{ \"distribution\": \"Normal\", \"mean\": 1.3, \"standard_deviation\": 0.2, \"min\": 2, \"max\": 1 }",
+      "{ \"distribution\": \"Empirical\", \"values\": [] }",
+      "{ \"distribution\": \"Empirical\", \"values\": [1, 2], \"weights\": [1] }",
+      "{ \"distribution\": \"Empirical\", \"values\": [1, 2], \"weights\": [1, -1] }",
+    ]
+    .iter()
+    .for_each(|data| {
+      assert!(
+        serde_json::from_str::<Distribution>(data).is_err(),
+        "{} should be rejected",
+        data
+      )
+    });
+    assert_eq!(
+      Distribution::try_from(DistributionData::Tagged(TaggedDistributionData::Uniform {
+        min: 2.,
+        max: 1.
+      })),
+      Err(Error::InvalidScenario(
+        "uniform distribution over [2, 1]".to_string()
+      ))
+    );
+  }
+
+  #[test]
+  fn test_check_positive() {
+    assert!(Distribution::Fixed(0.3).check_positive("radius").is_ok());
+    assert!(Distribution::Uniform { min: -1., max: 1. }
+      .check_positive("radius")
+      .is_ok());
+    assert_eq!(
+      Distribution::Fixed(-0.3).check_positive("radius"),
+      Err(Error::InvalidScenario(
+        "non positive fixed radius -0.3".to_string()
+      ))
+    );
+    assert!(Distribution::Fixed(0.).check_positive("radius").is_err());
+    assert!(Distribution::Uniform { min: -1., max: 0. }
+      .check_positive("radius")
+      .is_err());
+    assert!(Distribution::Empirical {
+      values: vec![0.3, 0.],
+      weights: Vec::new()
+    }
+    .check_positive("radius")
+    .is_err());
+  }
+
+  #[test]
+  fn test_sample_normal() {
+    let mut random = Random::new(5);
+    let distribution = Distribution::Normal {
+      mean: 1.3,
+      standard_deviation: 0.2,
+      min: 1.0,
+      max: 2.0,
+    };
+    let samples: Vec<f64> = (0..10000)
+      .map(|_| distribution.sample(&mut random))
+      .collect();
+    assert!(samples.iter().all(|&value| (1.0..=2.0).contains(&value)));
+    let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+    // The truncation shifts the mean up a little.
+    assert_relative_eq!(mean, 1.33, epsilon = 0.01);
+  }
+
+  #[test]
+  fn test_sample_empirical() {
+    let mut random = Random::new(5);
+    let distribution = Distribution::Empirical {
+      values: vec![1., 2.],
+      weights: vec![3., 1.],
+    };
+    let ones_count = (0..10000)
+      .filter(|_| distribution.sample(&mut random) == 1.)
+      .count();
+    assert_relative_eq!(ones_count as f64 / 10000., 0.75, epsilon = 0.02);
+  }
+}
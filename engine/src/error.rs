@@ -0,0 +1,130 @@
+use std::fmt;
+use wasm_bindgen::JsValue;
+
+// Errors of the engine public API.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+  // Scenario description that can't be parsed.
+  InvalidScenario(String),
+  // Cells that don't make a valid navmesh.
+  InvalidNavmesh(String),
+  // Reference to an agent that doesn't exist.
+  InvalidAgentIndex {
+    index: usize,
+    agents_count: usize,
+  },
+  // Numeric parameter out of its valid range.
+  InvalidParameter {
+    name: &'static str,
+    value: f64,
+  },
+  // Non finite value computed by a navigator for an agent.
+  NonFiniteValue {
+    agent_id: usize,
+    quantity: &'static str,
+    navigator: &'static str,
+  },
+  // Snapshot, recording, dataset or configuration that can't be parsed, the
+  // message describes what was being read.
+  InvalidData(String),
+  // Output that can't be written.
+  Output(String),
+}
+
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Error::InvalidScenario(message) => write!(f, "Invalid scenario: {}", message),
+      Error::InvalidNavmesh(message) => write!(f, "Invalid navmesh: {}", message),
+      Error::InvalidAgentIndex {
+        index,
+        agents_count,
+      } => write!(
+        f,
+        "Invalid agent index {}, there are {} agents",
+        index, agents_count
+      ),
+      Error::InvalidParameter { name, value } => {
+        write!(f, "Invalid value {} for {}", value, name)
+      }
+      Error::NonFiniteValue {
+        agent_id,
+        quantity,
+        navigator,
+      } => write!(
+        f,
+        "Non finite {} for agent {} after the {} navigator",
+        quantity, agent_id, navigator
+      ),
+      Error::InvalidData(message) => write!(f, "{}", message),
+      Error::Output(message) => write!(f, "Unable to write the output: {}", message),
+    }
+  }
+}
+
+impl std::error::Error for Error {}
+
+// Check a parameter is a finite and strictly positive number.
+pub fn check_positive(name: &'static str, value: f64) -> Result<(), Error> {
+  if value.is_finite() && value > 0. {
+    Ok(())
+  } else {
+    Err(Error::InvalidParameter { name, value })
+  }
+}
+
+// Check a parameter is a finite and positive or zero number.
+pub fn check_non_negative(name: &'static str, value: f64) -> Result<(), Error> {
+  if value.is_finite() && value >= 0. {
+    Ok(())
+  } else {
+    Err(Error::InvalidParameter { name, value })
+  }
+}
+
+// Thrown as JS errors by the wasm bindings.
+impl From<Error> for JsValue {
+  fn from(error: Error) -> Self {
+    JsValue::from_str(&error.to_string())
+  }
+}
+
+// Reported as messages by the command line tools.
+impl From<Error> for String {
+  fn from(error: Error) -> Self {
+    error.to_string()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_display() {
+    assert_eq!(
+      Error::InvalidAgentIndex {
+        index: 3,
+        agents_count: 2
+      }
+      .to_string(),
+      "Invalid agent index 3, there are 2 agents"
+    );
+    assert_eq!(
+      Error::NonFiniteValue {
+        agent_id: 1,
+        quantity: "target",
+        navigator: "queue"
+      }
+      .to_string(),
+      "Non finite target for agent 1 after the queue navigator"
+    );
+    assert_eq!(
+      String::from(Error::InvalidParameter {
+        name: "dt",
+        value: 0.
+      }),
+      "Invalid value 0 for dt"
+    );
+  }
+}
@@ -0,0 +1,46 @@
+use super::trajectory::Trajectory;
+use super::vec2::Vec2;
+use itertools::izip;
+
+// Override the velocity of scripted agents with the one bringing them to their
+// trajectory position at `time + dt`, other agents are left untouched.
+pub fn follow_trajectory_navigator(
+  positions: &[Vec2],
+  velocities: &[Vec2],
+  trajectories: &[Option<Trajectory>],
+  time: f64,
+  dt: f64,
+  updated_velocities: &mut [Vec2],
+) {
+  izip!(positions, velocities, trajectories, updated_velocities).for_each(
+    move |(&position, &velocity, trajectory, updated_velocity)| {
+      *updated_velocity = match trajectory {
+        Some(trajectory) => (trajectory.position_at(time + dt) - position) / dt,
+        None => velocity,
+      }
+    },
+  );
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use approx::assert_relative_eq;
+
+  #[test]
+  fn test_simple_follow_trajectory() {
+    let p = vec![Vec2::new(0.0, 0.0), Vec2::new(3.0, 4.0)];
+    let v = vec![Vec2::new(-1.0, 1.0), Vec2::new(1.0, -1.0)];
+    let t = vec![
+      Some(Trajectory::new(&[
+        (0.0, Vec2::new(0.0, 0.0)),
+        (2.0, Vec2::new(2.0, 0.0)),
+      ])),
+      None,
+    ];
+    let mut updated_v = vec![Vec2::default(); 2];
+    follow_trajectory_navigator(&p, &v, &t, 0.5, 0.5, &mut updated_v);
+    assert_relative_eq!(updated_v[0], Vec2::new(2.0, 0.0));
+    assert_relative_eq!(updated_v[1], v[1]);
+  }
+}
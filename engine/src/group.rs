@@ -0,0 +1,63 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Formation {
+  // Members only stay close to each other.
+  Free,
+  // Members walk abreast, perpendicular to the group walking direction.
+  SideBySide,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Group {
+  pub formation: Formation,
+  // Gain (in 1/s) of the velocity correction bringing members back to their
+  // place in the group.
+  pub cohesion: f64,
+  // Preferred distance between the centers of two neighbor members.
+  pub spacing: f64,
+  // Factor applied to the avoidance between members of the group.
+  pub avoidance_factor: f64,
+}
+
+impl Group {
+  pub fn new() -> Self {
+    Group {
+      formation: Formation::SideBySide,
+      cohesion: 1.0,
+      spacing: 0.9,
+      avoidance_factor: 0.5,
+    }
+  }
+
+  #[allow(dead_code)]
+  pub fn formation(mut self, formation: Formation) -> Self {
+    self.formation = formation;
+    self
+  }
+
+  #[allow(dead_code)]
+  pub fn cohesion(mut self, cohesion: f64) -> Self {
+    self.cohesion = cohesion;
+    self
+  }
+
+  #[allow(dead_code)]
+  pub fn spacing(mut self, spacing: f64) -> Self {
+    self.spacing = spacing;
+    self
+  }
+
+  #[allow(dead_code)]
+  pub fn avoidance_factor(mut self, avoidance_factor: f64) -> Self {
+    self.avoidance_factor = avoidance_factor;
+    self
+  }
+}
+
+impl Default for Group {
+  fn default() -> Self {
+    Group::new()
+  }
+}
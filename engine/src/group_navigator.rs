@@ -0,0 +1,161 @@
+use super::group::{Formation, Group};
+use super::vec2::Vec2;
+use std::vec::Vec;
+
+// Compute, for each agent belonging to a group, the position it should have
+// in the group formation and pass it to `on_slot` along with the group.
+//
+// `members` is scratch space, holding the members of all the groups, bucketed
+// by group.
+fn for_each_formation_slot<F>(
+  positions: &[Vec2],
+  desired_velocities: &[Vec2],
+  group_ids: &[Option<usize>],
+  groups: &[Group],
+  members: &mut Vec<usize>,
+  mut on_slot: F,
+) where
+  F: FnMut(usize, &Group, Vec2),
+{
+  members.clear();
+  members.extend(
+    group_ids
+      .iter()
+      .enumerate()
+      .filter(
+        |(_, agent_group_id)| matches!(agent_group_id, Some(group_id) if *group_id < groups.len()),
+      )
+      .map(|(idx_agent, _)| idx_agent),
+  );
+  members.sort_unstable_by_key(|&idx_agent| (group_ids[idx_agent], idx_agent));
+  members
+    .chunk_by_mut(|&a, &b| group_ids[a] == group_ids[b])
+    .for_each(|members| {
+      let group = &groups[group_ids[members[0]].unwrap()];
+      if members.len() < 2 {
+        return;
+      }
+      let inv_members_count = 1.0 / members.len() as f64;
+      let centroid = members.iter().fold(Vec2::default(), |sum, &idx_agent| {
+        sum + positions[idx_agent]
+      }) * inv_members_count;
+      let walking_velocity = members.iter().fold(Vec2::default(), |sum, &idx_agent| {
+        sum + desired_velocities[idx_agent]
+      }) * inv_members_count;
+      if walking_velocity.norm() < f64::EPSILON {
+        // The group isn't going anywhere, no formation to keep.
+        return;
+      }
+      match group.formation {
+        Formation::Free => {
+          // Members only need to stay within reach of the centroid.
+          let reach = group.spacing * (members.len() - 1) as f64 * 0.5;
+          members.iter().for_each(|&idx_agent| {
+            let offset = positions[idx_agent] - centroid;
+            on_slot(idx_agent, group, centroid + offset.cap_norm(reach));
+          });
+        }
+        Formation::SideBySide => {
+          // Members are lined up perpendicularly to the walking direction,
+          // keeping their current left to right order.
+          let walking_direction = walking_velocity.normalize();
+          let side_direction = Vec2::new(-walking_direction.y(), walking_direction.x());
+          members.sort_by(|&a, &b| {
+            ((positions[a] - centroid) * side_direction)
+              .total_cmp(&((positions[b] - centroid) * side_direction))
+          });
+          let middle_rank = (members.len() - 1) as f64 * 0.5;
+          members.iter().enumerate().for_each(|(rank, &idx_agent)| {
+            on_slot(
+              idx_agent,
+              group,
+              centroid + (rank as f64 - middle_rank) * group.spacing * side_direction,
+            );
+          });
+        }
+      }
+    });
+}
+
+pub fn group_navigator(
+  positions: &[Vec2],
+  desired_velocities: &[Vec2],
+  maximum_speeds: &[f64],
+  group_ids: &[Option<usize>],
+  groups: &[Group],
+  members: &mut Vec<usize>,
+  updated_desired_velocities: &mut [Vec2],
+) {
+  updated_desired_velocities.copy_from_slice(desired_velocities);
+  for_each_formation_slot(
+    positions,
+    desired_velocities,
+    group_ids,
+    groups,
+    members,
+    |idx_agent, group, slot| {
+      updated_desired_velocities[idx_agent] = (desired_velocities[idx_agent]
+        + group.cohesion * (slot - positions[idx_agent]))
+        .cap_norm(maximum_speeds[idx_agent]);
+    },
+  );
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use approx::assert_relative_eq;
+
+  #[test]
+  fn test_side_by_side() {
+    let p = vec![
+      Vec2::new(0.0, 0.0),
+      Vec2::new(-1.0, 1.0),
+      Vec2::new(0.0, 5.0),
+    ];
+    let dv = vec![
+      Vec2::new(1.0, 0.0),
+      Vec2::new(1.0, 0.0),
+      Vec2::new(1.0, 0.0),
+    ];
+    let ms = vec![3.0, 3.0, 3.0];
+    let g = vec![Some(0), Some(0), None];
+    let groups = vec![Group::new().spacing(1.0).cohesion(1.0)];
+    let mut updated_dv = vec![Vec2::default(); dv.len()];
+    group_navigator(&p, &dv, &ms, &g, &groups, &mut Vec::new(), &mut updated_dv);
+    // The agent ahead slows down, the one behind accelerates
+    assert_relative_eq!(updated_dv[0], Vec2::new(0.5, 0.0));
+    assert_relative_eq!(updated_dv[1], Vec2::new(1.5, 0.0));
+    // Agents outside of a group are untouched
+    assert_relative_eq!(updated_dv[2], dv[2]);
+  }
+
+  #[test]
+  fn test_free() {
+    let p = vec![Vec2::new(0.0, 0.0), Vec2::new(0.0, 3.0)];
+    let dv = vec![Vec2::new(1.0, 0.0), Vec2::new(1.0, 0.0)];
+    let ms = vec![3.0, 3.0];
+    let g = vec![Some(0), Some(0)];
+    let groups = vec![Group::new()
+      .formation(Formation::Free)
+      .spacing(1.0)
+      .cohesion(2.0)];
+    let mut updated_dv = vec![Vec2::default(); dv.len()];
+    group_navigator(&p, &dv, &ms, &g, &groups, &mut Vec::new(), &mut updated_dv);
+    // Agents get closer to each other
+    assert_relative_eq!(updated_dv[0], Vec2::new(1.0, 2.0));
+    assert_relative_eq!(updated_dv[1], Vec2::new(1.0, -2.0));
+  }
+
+  #[test]
+  fn test_not_walking() {
+    let p = vec![Vec2::new(0.0, 0.0), Vec2::new(0.0, 3.0)];
+    let dv = vec![Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0)];
+    let ms = vec![3.0, 3.0];
+    let g = vec![Some(0), Some(0)];
+    let groups = vec![Group::new()];
+    let mut updated_dv = vec![Vec2::default(); dv.len()];
+    group_navigator(&p, &dv, &ms, &g, &groups, &mut Vec::new(), &mut updated_dv);
+    itertools::assert_equal(updated_dv, dv);
+  }
+}
@@ -2,21 +2,69 @@ mod agent;
 mod agent_debug_info;
 mod agents;
 mod apply_velocity_navigator;
+mod arrival_navigator;
+mod clock;
+mod collisions;
+pub mod datasets;
+mod distribution;
+pub mod error;
+mod follow_trajectory_navigator;
+mod group;
+mod group_navigator;
 mod look_where_you_go_navigator;
+pub mod metrics;
 mod navmesh;
 mod neighborhood;
 mod orca;
+mod queue;
+mod queue_navigator;
+mod random;
 mod reach_target_navigator;
+mod recorder;
+mod render_buffers;
+mod replay;
 mod scenarii;
+mod snapshot;
+mod step_buffers;
+pub mod sweep;
+mod trajectory;
+mod travels;
 mod utils;
 mod vec2;
 
+use agent::ArrivalPolicy;
 use agent_debug_info::AgentDebugInfo;
 use agents::Agents;
+use clock::Clock;
+use error::{check_non_negative, check_positive};
+use metrics::{Metrics, MetricsConfig};
 use navmesh::Navmesh;
+use random::Random;
+use recorder::Recorder;
+use render_buffers::RenderBuffers;
 use scenarii::{load_scenario, EmptyScenario, Scenario};
+use snapshot::Snapshot;
+use step_buffers::StepBuffers;
+use travels::{TravelRecord, TravelsSummary};
 use vec2::Vec2;
 
+pub use error::Error;
+pub use replay::Replay;
+
+// Engine building blocks, only exposed for the benchmarks.
+#[doc(hidden)]
+pub mod internals {
+  pub use crate::agents::Agents;
+  pub use crate::navmesh::{Navmesh, NavmeshBuilder};
+  pub use crate::neighborhood::AgentNeighborhood;
+  pub use crate::orca::{
+    compute_constraints, solve_linear_program, LinearProgramFailure, DEFAULT_TIME_HORIZON,
+  };
+  pub use crate::random::Random;
+  pub use crate::scenarii::{load_scenario, Scenario};
+  pub use crate::vec2::Vec2;
+}
+
 use wasm_bindgen::prelude::*;
 
 use itertools::izip;
@@ -34,6 +82,12 @@ extern "C" {
   fn alert(s: &str);
 }
 
+// The wasm memory, for JS to read the render buffers without copying them.
+#[wasm_bindgen]
+pub fn memory() -> JsValue {
+  wasm_bindgen::memory()
+}
+
 #[wasm_bindgen]
 #[derive(Clone, Debug, PartialEq)]
 pub struct RenderedAgent {
@@ -45,7 +99,14 @@ pub struct Universe {
   agents: Agents,
   navmesh: Navmesh,
   scenario: Box<dyn Scenario>,
-  last_dt: f64,
+  random: Random,
+  clock: Clock,
+  recorder: Option<Recorder>,
+  metrics: Option<Metrics>,
+  travel_records: Vec<TravelRecord>,
+  time_horizon: f64,
+  buffers: StepBuffers,
+  render_buffers: RenderBuffers,
 }
 
 // Public methods w/ js bindings.
@@ -55,118 +116,635 @@ impl Universe {
     utils::set_panic_hook();
 
     let empty_scenario = EmptyScenario::new();
-    let (agents, navmesh) = empty_scenario.generate();
+    let mut random = Random::new(empty_scenario.seed());
+    let (agents, navmesh) = empty_scenario
+      .generate(&mut random)
+      .expect("The empty scenario is valid");
 
     Universe {
       agents,
       navmesh,
       scenario: Box::new(empty_scenario),
-      last_dt: 0.,
+      random,
+      clock: Clock::new(),
+      recorder: None,
+      metrics: None,
+      travel_records: Vec::new(),
+      time_horizon: orca::DEFAULT_TIME_HORIZON,
+      buffers: StepBuffers::new(),
+      render_buffers: RenderBuffers::new(),
     }
   }
-  pub fn load_scenario(&mut self, scenario_data: &str) {
-    self.scenario = Box::new(load_scenario(scenario_data));
-    let (agents, navmesh) = self.scenario.generate();
-    self.agents = agents;
-    self.navmesh = navmesh;
+  pub fn load_scenario(&mut self, scenario_data: &str) -> Result<(), JsValue> {
+    Ok(self.try_load_scenario(scenario_data)?)
+  }
+  // Run the fixed timestep simulation steps covering the given elapsed real
+  // time, returns the number of computed steps.
+  pub fn advance(&mut self, real_dt: f64) -> Result<usize, JsValue> {
+    let steps_count = self.clock.accumulate(real_dt);
+    let timestep = self.clock.get_timestep();
+    for _ in 0..steps_count {
+      self.update(timestep)?;
+    }
+    Ok(steps_count)
+  }
+  // Run a single fixed timestep simulation step.
+  pub fn step(&mut self) -> Result<(), JsValue> {
+    self.update(self.clock.get_timestep())
+  }
+  pub fn set_timestep(&mut self, timestep: f64) -> Result<(), JsValue> {
+    Ok(self.try_set_timestep(timestep)?)
+  }
+  pub fn set_maximum_substeps_count(
+    &mut self,
+    maximum_substeps_count: usize,
+  ) -> Result<(), JsValue> {
+    Ok(
+      self
+        .clock
+        .set_maximum_substeps_count(maximum_substeps_count)?,
+    )
+  }
+  pub fn set_time_horizon(&mut self, time_horizon: f64) -> Result<(), JsValue> {
+    Ok(self.try_set_time_horizon(time_horizon)?)
+  }
+  pub fn get_time(&self) -> f64 {
+    self.clock.get_time()
+  }
+  pub fn get_step_index(&self) -> usize {
+    self.clock.get_step_index()
+  }
+  pub fn update(&mut self, dt: f64) -> Result<(), JsValue> {
+    Ok(self.try_update(dt)?)
+  }
+  pub fn render_agents(&self) -> Box<[f64]> {
+    izip!(
+      self.agents.get_positions().iter(),
+      self.agents.get_directions().iter(),
+      self.agents.get_velocities().iter(),
+      self.agents.get_radii().iter()
+    )
+    .flat_map(|(p, d, v, &r)| vec![p.x(), p.y(), d.x(), d.y(), v.x(), v.y(), r])
+    .collect::<Vec<f64>>()
+    .into_boxed_slice()
+  }
+  // Write the agents state to the render buffers, to read through the
+  // `get_render_*_ptr` pointers, `count_agents` values long (twice as much for
+  // 2D vectors). The pointers are invalidated by the next call.
+  pub fn update_render_buffers(&mut self) {
+    self.render_buffers.update(&self.agents);
+  }
+  pub fn get_render_ids_ptr(&self) -> *const u32 {
+    self.render_buffers.get_ids().as_ptr()
+  }
+  pub fn get_render_positions_ptr(&self) -> *const f32 {
+    self.render_buffers.get_positions().as_ptr()
+  }
+  pub fn get_render_directions_ptr(&self) -> *const f32 {
+    self.render_buffers.get_directions().as_ptr()
+  }
+  pub fn get_render_velocities_ptr(&self) -> *const f32 {
+    self.render_buffers.get_velocities().as_ptr()
+  }
+  pub fn get_render_radii_ptr(&self) -> *const f32 {
+    self.render_buffers.get_radii().as_ptr()
+  }
+  pub fn render_navmesh(&self) -> Result<String, JsValue> {
+    Ok(self.try_render_navmesh()?)
+  }
+  pub fn render_debug_info(&self, idx_agent: usize) -> Result<String, JsValue> {
+    Ok(self.try_render_debug_info(idx_agent)?)
+  }
+  pub fn count_agents(&self) -> usize {
+    self.agents.len()
+  }
+  // Start recording the agents every `sampling_period` of simulated time,
+  // dropping any previous recording.
+  pub fn start_recording(
+    &mut self,
+    sampling_period: f64,
+    record_state: bool,
+  ) -> Result<(), JsValue> {
+    Ok(self.try_start_recording(sampling_period, record_state)?)
   }
-  pub fn update(&mut self, dt: f64) {
-    let neighborhoods = neighborhood::AgentNeighborhood::compute_agents_neighborhood(
+  pub fn stop_recording(&mut self) {
+    self.recorder = None;
+  }
+  pub fn export_recording_to_csv(&self) -> Option<String> {
+    self.recorder.as_ref().map(|recorder| recorder.to_csv())
+  }
+  pub fn export_recording_to_json_lines(&self) -> Option<String> {
+    self
+      .recorder
+      .as_ref()
+      .map(|recorder| recorder.to_json_lines())
+  }
+  // Start computing the crowd metrics after each step, configured by a JSON
+  // `MetricsConfig`.
+  pub fn start_metrics(&mut self, config_data: &str) -> Result<(), JsValue> {
+    let config = serde_json::from_str::<MetricsConfig>(config_data)
+      .map_err(|err| Error::InvalidData(format!("Invalid metrics config: {}", err)))?;
+    Ok(self.set_metrics(config)?)
+  }
+  pub fn stop_metrics(&mut self) {
+    self.metrics = None;
+  }
+  // Metrics of the last step as JSON, `null` if not available.
+  pub fn render_metrics(&self) -> String {
+    serde_json::to_string(
+      &self
+        .metrics
+        .as_ref()
+        .and_then(|metrics| metrics.get_last_step_metrics()),
+    )
+    .unwrap()
+  }
+  // Travels of the arrived agents as CSV.
+  pub fn export_travels_to_csv(&self) -> String {
+    travels::to_csv(&self.travel_records)
+  }
+  // Distributions of the travels of the arrived agents as JSON.
+  pub fn render_travels_summary(&self) -> String {
+    serde_json::to_string(&TravelsSummary::new(&self.travel_records)).unwrap()
+  }
+  pub fn snapshot_to_json(&self) -> String {
+    self.snapshot().to_json()
+  }
+  pub fn snapshot_to_binary(&self) -> Box<[u8]> {
+    self.snapshot().to_binary().into_boxed_slice()
+  }
+  pub fn restore_from_json(&mut self, data: &str) -> Result<(), JsValue> {
+    let snapshot = Snapshot::from_json(data)?;
+    self.restore(snapshot);
+    Ok(())
+  }
+  pub fn restore_from_binary(&mut self, data: &[u8]) -> Result<(), JsValue> {
+    let snapshot = Snapshot::from_binary(data)?;
+    self.restore(snapshot);
+    Ok(())
+  }
+}
+
+// Public methods, Rust only.
+impl Universe {
+  // Run a simulation step of `dt`, failing on invalid durations and on
+  // agents getting non finite positions, velocities or directions.
+  pub fn try_update(&mut self, dt: f64) -> Result<(), Error> {
+    if !dt.is_finite() || dt <= 0. {
+      return Err(Error::InvalidParameter {
+        name: "dt",
+        value: dt,
+      });
+    }
+    let time = self.clock.get_time();
+    // The navigators only write into the step buffers, the agents are updated
+    // once all the results are known to be finite, a failed step leaves the
+    // universe unchanged.
+    self.buffers.resize(self.agents.len());
+    queue_navigator::update_queues(
+      self.agents.get_queues(),
       self.agents.get_positions(),
-      self.agents.get_velocities(),
-      self.agents.get_radii(),
+      self.agents.get_queue_ids(),
+      self.agents.get_service_times(),
+      time,
+      &mut self.buffers.queues,
+      &mut self.buffers.queue_ids,
     );
-    self.agents.set_velocities(&orca::orca_navigator(
+    Self::compute_neighborhoods(&self.agents, &mut self.buffers.neighborhoods);
+    Self::compute_desired_velocities(&self.agents, dt, &mut self.buffers)?;
+    orca::orca_navigator(
       self.agents.get_positions(),
       self.agents.get_directions(),
-      &reach_target_navigator::reach_target_navigator(
-        self.agents.get_positions(),
-        self.agents.get_velocities(),
-        self.agents.get_targets(),
-        self.agents.get_desired_speeds(),
-        self.agents.get_maximum_accelerations(),
-        dt,
-      ),
+      &self.buffers.desired_velocities,
       self.agents.get_radii(),
       self.agents.get_maximum_speeds(),
-      &neighborhoods,
-      5.0,
+      self.agents.get_maximum_backward_speeds(),
+      &self.buffers.neighborhoods,
+      self.time_horizon,
       dt,
-    ));
+      &mut self.buffers.orca_velocities,
+    );
+    Self::check_finite(
+      &self.agents,
+      "orca",
+      "velocity",
+      self.buffers.orca_velocities.iter().map(Vec2::is_finite),
+    )?;
+    follow_trajectory_navigator::follow_trajectory_navigator(
+      self.agents.get_positions(),
+      &self.buffers.orca_velocities,
+      self.agents.get_trajectories(),
+      time,
+      dt,
+      &mut self.buffers.velocities,
+    );
+    Self::check_finite(
+      &self.agents,
+      "follow trajectory",
+      "velocity",
+      self.buffers.velocities.iter().map(Vec2::is_finite),
+    )?;
+    apply_velocity_navigator::apply_velocity_navigator(
+      self.agents.get_positions(),
+      &self.buffers.velocities,
+      dt,
+      &mut self.buffers.positions,
+    );
+    Self::check_finite(
+      &self.agents,
+      "apply velocity",
+      "position",
+      self.buffers.positions.iter().map(Vec2::is_finite),
+    )?;
+    Self::locate_agents(
+      &self.navmesh,
+      &self.buffers.positions,
+      self.agents.get_cells(),
+      &mut self.buffers.cells,
+    );
+    travels::update_path_lengths(
+      self.agents.get_path_lengths(),
+      &self.buffers.velocities,
+      dt,
+      &mut self.buffers.path_lengths,
+    );
+    look_where_you_go_navigator::look_where_you_go_navigator(
+      self.agents.get_directions(),
+      &self.buffers.velocities,
+      self.agents.get_maximum_angular_speeds(),
+      self.agents.get_smoothing_times(),
+      dt,
+      &mut self.buffers.directions,
+    );
+    Self::check_finite(
+      &self.agents,
+      "look where you go",
+      "direction",
+      self.buffers.directions.iter().map(Vec2::is_finite),
+    )?;
 
+    // The buffers get the previous values back, overwritten at the next step.
+    self.agents.swap_queues(&mut self.buffers.queues);
+    self.agents.swap_queue_ids(&mut self.buffers.queue_ids);
+    self.agents.swap_velocities(&mut self.buffers.velocities);
+    self.agents.swap_positions(&mut self.buffers.positions);
+    self.agents.swap_cells(&mut self.buffers.cells);
     self
       .agents
-      .set_positions(&apply_velocity_navigator::apply_velocity_navigator(
-        self.agents.get_positions(),
-        self.agents.get_velocities(),
-        dt,
-      ));
+      .swap_path_lengths(&mut self.buffers.path_lengths);
+    self.agents.swap_directions(&mut self.buffers.directions);
 
-    self
-      .agents
-      .set_directions(&look_where_you_go_navigator::look_where_you_go_navigator(
-        self.agents.get_directions(),
-        self.agents.get_velocities(),
-      ));
+    arrival_navigator::update_arrivals(
+      self.agents.get_positions(),
+      self.agents.get_targets(),
+      self.agents.get_radii(),
+      self.agents.get_arrival_radii(),
+      self.agents.get_kinds(),
+      self.agents.get_queue_ids(),
+      self.agents.get_arrived(),
+      &self.buffers.neighborhoods,
+      &mut self.buffers.arrived,
+    );
+    self.record_arrivals(time + dt);
+    self.apply_arrival_policies();
 
-    self.last_dt = dt;
+    self.clock.tick(dt);
+    if let Some(recorder) = &mut self.recorder {
+      recorder.record(self.clock.get_time(), &self.agents);
+    }
+    if let Some(metrics) = &mut self.metrics {
+      metrics.update(
+        self.clock.get_time(),
+        dt,
+        &self.agents,
+        &self.buffers.neighborhoods,
+        &self.navmesh,
+      );
+    }
+    Ok(())
   }
-  pub fn render_agents(&self) -> Box<[f64]> {
-    izip!(
-      self.agents.get_positions().iter(),
-      self.agents.get_directions().iter(),
-      self.agents.get_velocities().iter(),
-      self.agents.get_radii().iter()
+  // Run a single fixed timestep simulation step.
+  pub fn try_step(&mut self) -> Result<(), Error> {
+    self.try_update(self.clock.get_timestep())
+  }
+  // Render the debug information of the agent at the given index, JSON
+  // encoded.
+  pub fn try_render_debug_info(&self, idx_agent: usize) -> Result<String, Error> {
+    self.check_agent_index(idx_agent)?;
+    let mut debug_info = AgentDebugInfo::new().agent(self.agents.retrieve_agent(idx_agent));
+    let last_dt = self.clock.get_last_dt();
+    let mut buffers = StepBuffers::new();
+    buffers.resize(self.agents.len());
+    Self::compute_neighborhoods(&self.agents, &mut buffers.neighborhoods);
+    if last_dt > 0. {
+      buffers.queues = self.agents.get_queues().to_vec();
+      buffers
+        .queue_ids
+        .copy_from_slice(self.agents.get_queue_ids());
+      Self::compute_desired_velocities(&self.agents, last_dt, &mut buffers)?;
+      let constraints = orca::compute_constraints(
+        idx_agent,
+        self.agents.get_positions()[idx_agent],
+        buffers.desired_velocities[idx_agent],
+        self.agents.get_radii()[idx_agent],
+        &buffers.neighborhoods[idx_agent],
+        self.time_horizon,
+        last_dt,
+      );
+      debug_info = debug_info.constraints(constraints.get_constraints())
+    }
+
+    let agents_overlaps: Vec<collisions::AgentsOverlap> = collisions::detect_agents_overlaps(
+      self.agents.get_positions(),
+      self.agents.get_radii(),
+      &buffers.neighborhoods,
     )
-    .flat_map(|(p, d, v, &r)| vec![p.x(), p.y(), d.x(), d.y(), v.x(), v.y(), r])
-    .collect::<Vec<f64>>()
-    .into_boxed_slice()
+    .into_iter()
+    .filter(|overlap| overlap.agent == idx_agent || overlap.other_agent == idx_agent)
+    .collect();
+    let wall_penetration = collisions::detect_walls_penetrations(
+      &self.agents.get_positions()[idx_agent..idx_agent + 1],
+      &self.agents.get_radii()[idx_agent..idx_agent + 1],
+      &self.agents.get_cells()[idx_agent..idx_agent + 1],
+      &self.navmesh,
+    )
+    .first()
+    .map(|&penetration| collisions::WallPenetration {
+      agent: idx_agent,
+      ..penetration
+    });
+    Ok(
+      debug_info
+        .collisions(&agents_overlaps, wall_penetration)
+        .render(),
+    )
+  }
+  pub fn try_load_scenario(&mut self, scenario_data: &str) -> Result<(), Error> {
+    self.set_scenario(Box::new(load_scenario(scenario_data)?))
   }
-  pub fn render_navmesh(&self) -> String {
+  // Render the navmesh in the Wavefront OBJ format.
+  pub fn try_render_navmesh(&self) -> Result<String, Error> {
     let mut output = Vec::new();
     self
       .navmesh
       .render_to_obj(&mut Cursor::new(&mut output))
-      .unwrap();
-    String::from_utf8(output).unwrap()
+      .map_err(|err| Error::Output(err.to_string()))?;
+    String::from_utf8(output).map_err(|err| Error::Output(err.to_string()))
   }
-  pub fn render_debug_info(&self, idx_agent: usize) -> String {
-    let mut debug_info = AgentDebugInfo::new().agent(self.agents.retrieve_agent(idx_agent));
-    if self.last_dt > 0. {
-      let neighborhood_slice = &neighborhood::AgentNeighborhood::compute_agents_neighborhood(
-        &self.agents.get_positions(),
-        &self.agents.get_velocities(),
-        &self.agents.get_radii(),
-      )[idx_agent..idx_agent + 1];
-      let position_slice = &self.agents.get_positions()[idx_agent..idx_agent + 1];
-      let velocity_slice = &self.agents.get_velocities()[idx_agent..idx_agent + 1];
-      let radius_slice = &self.agents.get_radii()[idx_agent..idx_agent + 1];
-      let target_slice = &self.agents.get_targets()[idx_agent..idx_agent + 1];
-      let desired_speed_slice = &self.agents.get_desired_speeds()[idx_agent..idx_agent + 1];
-      let maximum_acceleration_slice =
-        &self.agents.get_maximum_accelerations()[idx_agent..idx_agent + 1];
-      let constraints = orca::compute_constraints(
-        position_slice,
-        &reach_target_navigator::reach_target_navigator(
-          position_slice,
-          velocity_slice,
-          target_slice,
-          desired_speed_slice,
-          maximum_acceleration_slice,
-          self.last_dt,
-        ),
-        radius_slice,
-        neighborhood_slice,
-        10.0,
-        self.last_dt,
-      );
-      debug_info = debug_info.constraints(&constraints[0])
+  // Generate the agents and the navmesh of the scenario and restart the
+  // simulation, the universe is unchanged if the generation fails.
+  pub fn set_scenario(&mut self, scenario: Box<dyn Scenario>) -> Result<(), Error> {
+    let mut random = Random::new(scenario.seed());
+    let (agents, navmesh) = scenario.generate(&mut random)?;
+    self.scenario = scenario;
+    self.random = random;
+    self.agents = agents;
+    self.navmesh = navmesh;
+    self.clock.reset();
+    if let Some(recorder) = &mut self.recorder {
+      recorder.reset(0.);
+      recorder.record(0., &self.agents);
     }
+    if let Some(metrics) = &mut self.metrics {
+      metrics.reset();
+    }
+    self.travel_records.clear();
+    Ok(())
+  }
+  // Start recording the agents every `sampling_period` of simulated time,
+  // dropping any previous recording.
+  pub fn try_start_recording(
+    &mut self,
+    sampling_period: f64,
+    record_state: bool,
+  ) -> Result<(), Error> {
+    check_non_negative("sampling_period", sampling_period)?;
+    let mut recorder = Recorder::new()
+      .sampling_period(sampling_period)
+      .record_state(record_state);
+    let time = self.clock.get_time();
+    recorder.reset(time);
+    recorder.record(time, &self.agents);
+    self.recorder = Some(recorder);
+    Ok(())
+  }
+  pub fn try_set_timestep(&mut self, timestep: f64) -> Result<(), Error> {
+    self.clock.set_timestep(timestep)
+  }
+  pub fn try_set_time_horizon(&mut self, time_horizon: f64) -> Result<(), Error> {
+    check_positive("time_horizon", time_horizon)?;
+    self.time_horizon = time_horizon;
+    Ok(())
+  }
+  pub fn set_metrics(&mut self, config: MetricsConfig) -> Result<(), Error> {
+    self.metrics = Some(Metrics::new(config)?);
+    Ok(())
+  }
+  pub fn get_metrics(&self) -> Option<&Metrics> {
+    self.metrics.as_ref()
+  }
+  pub fn get_render_buffers(&self) -> &RenderBuffers {
+    &self.render_buffers
+  }
+  pub fn get_travel_records(&self) -> &[TravelRecord] {
+    &self.travel_records
+  }
+  pub fn get_recorder(&self) -> Option<&Recorder> {
+    self.recorder.as_ref()
+  }
+  pub fn snapshot(&self) -> Snapshot {
+    Snapshot::new(
+      self.agents.clone(),
+      self.navmesh.clone(),
+      self.clock.clone(),
+      self.random.clone(),
+      self.travel_records.clone(),
+      self.time_horizon,
+    )
+  }
+  // Replace the whole simulation state, the loaded scenario is kept as is.
+  // The recording and the metrics restart from the restored time.
+  pub fn restore(&mut self, snapshot: Snapshot) {
+    self.agents = snapshot.agents;
+    self.navmesh = snapshot.navmesh;
+    self.clock = snapshot.clock;
+    self.random = snapshot.random;
+    self.travel_records = snapshot.travel_records;
+    self.time_horizon = snapshot.time_horizon;
+    let time = self.clock.get_time();
+    if let Some(recorder) = &mut self.recorder {
+      recorder.reset(time);
+      recorder.record(time, &self.agents);
+    }
+    if let Some(metrics) = &mut self.metrics {
+      metrics.reset();
+    }
+  }
+}
 
-    debug_info.render()
+// Private methods
+impl Universe {
+  fn compute_neighborhoods(agents: &Agents, neighborhoods: &mut [neighborhood::AgentNeighborhood]) {
+    neighborhood::AgentNeighborhood::compute_agents_neighborhood(
+      agents.get_positions(),
+      agents.get_velocities(),
+      agents.get_radii(),
+      agents.get_kinds(),
+      agents.get_arrived(),
+      agents.get_group_ids(),
+      agents.get_groups(),
+      neighborhoods,
+    );
   }
-  pub fn count_agents(&self) -> usize {
-    self.agents.len()
+  // Locate the agents in the navmesh, starting from their previous cells.
+  fn locate_agents(
+    navmesh: &Navmesh,
+    positions: &[Vec2],
+    cells: &[Option<usize>],
+    updated_cells: &mut [Option<usize>],
+  ) {
+    izip!(positions, cells, updated_cells).for_each(|(position, &cell, updated_cell)| {
+      *updated_cell = navmesh.locate(position, cell);
+    });
+  }
+  // Record the travel of the agents arriving for the first time.
+  fn record_arrivals(&mut self, time: f64) {
+    for idx_agent in 0..self.agents.len() {
+      if !self.buffers.arrived[idx_agent] || self.agents.get_arrival_times()[idx_agent].is_some() {
+        continue;
+      }
+      self.agents.get_arrival_times_mut()[idx_agent] = Some(time);
+      self.travel_records.push(TravelRecord::new(
+        self.agents.get_ids()[idx_agent],
+        self.agents.get_departure_times()[idx_agent],
+        time,
+        self.agents.get_departure_positions()[idx_agent],
+        self.agents.get_positions()[idx_agent],
+        self.agents.get_path_lengths()[idx_agent],
+      ));
+    }
+  }
+  // Apply the arrival policy of the agents arriving during this step, then
+  // store the updated arrival states.
+  fn apply_arrival_policies(&mut self) {
+    for idx_agent in 0..self.agents.len() {
+      if !self.buffers.arrived[idx_agent] || self.agents.get_arrived()[idx_agent] {
+        continue;
+      }
+      let position = self.agents.get_positions()[idx_agent];
+      match self.agents.get_arrival_policies()[idx_agent] {
+        ArrivalPolicy::Despawn => self.buffers.despawned.push(idx_agent),
+        ArrivalPolicy::NextTarget if !self.agents.get_waypoints()[idx_agent].is_empty() => {
+          let target = self.agents.get_waypoints_mut()[idx_agent].remove(0);
+          self.agents.get_targets_mut()[idx_agent] = target;
+          self.buffers.arrived[idx_agent] = false;
+        }
+        ArrivalPolicy::Wander => {
+          self.agents.get_targets_mut()[idx_agent] =
+            arrival_navigator::wander_target(position, &self.navmesh, &mut self.random);
+          self.buffers.arrived[idx_agent] = false;
+        }
+        _ => {
+          // Stop where the agent is when its target is taken by another one.
+          let arrival_radius = self.agents.get_arrival_radii()[idx_agent];
+          let target = &mut self.agents.get_targets_mut()[idx_agent];
+          if (*target - position).norm() > arrival_radius {
+            *target = position;
+          }
+        }
+      }
+    }
+    self.agents.swap_arrived(&mut self.buffers.arrived);
+    let agents = &mut self.agents;
+    self
+      .buffers
+      .despawned
+      .iter()
+      .rev()
+      .for_each(|&idx_agent| agents.remove_agent(idx_agent));
+    if !self.buffers.despawned.is_empty() {
+      // Keep the neighborhoods in line with the remaining agents' indices.
+      self.buffers.neighborhoods.truncate(self.agents.len());
+      Self::compute_neighborhoods(&self.agents, &mut self.buffers.neighborhoods);
+    }
+  }
+  // Compute the desired velocities, in `buffers.desired_velocities`, going
+  // through the queues of `buffers.queues`, the targets and the groups.
+  fn compute_desired_velocities(
+    agents: &Agents,
+    dt: f64,
+    buffers: &mut StepBuffers,
+  ) -> Result<(), Error> {
+    queue_navigator::queue_navigator(
+      agents.get_targets(),
+      &buffers.queue_ids,
+      &buffers.queues,
+      &mut buffers.targets,
+    );
+    Self::check_finite(
+      agents,
+      "queue",
+      "target",
+      buffers.targets.iter().map(Vec2::is_finite),
+    )?;
+    reach_target_navigator::reach_target_navigator(
+      agents.get_positions(),
+      agents.get_velocities(),
+      &buffers.targets,
+      agents.get_desired_speeds(),
+      agents.get_maximum_accelerations(),
+      dt,
+      &mut buffers.reach_target_velocities,
+    );
+    Self::check_finite(
+      agents,
+      "reach target",
+      "velocity",
+      buffers.reach_target_velocities.iter().map(Vec2::is_finite),
+    )?;
+    group_navigator::group_navigator(
+      agents.get_positions(),
+      &buffers.reach_target_velocities,
+      agents.get_maximum_speeds(),
+      agents.get_group_ids(),
+      agents.get_groups(),
+      &mut buffers.group_members,
+      &mut buffers.desired_velocities,
+    );
+    Self::check_finite(
+      agents,
+      "group",
+      "velocity",
+      buffers.desired_velocities.iter().map(Vec2::is_finite),
+    )
+  }
+  // Check the output of a navigator, naming the first agent getting a non
+  // finite value.
+  fn check_finite(
+    agents: &Agents,
+    navigator: &'static str,
+    quantity: &'static str,
+    finite: impl IntoIterator<Item = bool>,
+  ) -> Result<(), Error> {
+    match finite.into_iter().position(|finite| !finite) {
+      Some(idx_agent) => Err(Error::NonFiniteValue {
+        agent_id: agents.get_ids()[idx_agent],
+        quantity,
+        navigator,
+      }),
+      None => Ok(()),
+    }
+  }
+  // Check the agent index given by the host.
+  fn check_agent_index(&self, idx_agent: usize) -> Result<(), Error> {
+    if idx_agent < self.agents.len() {
+      Ok(())
+    } else {
+      Err(Error::InvalidAgentIndex {
+        index: idx_agent,
+        agents_count: self.agents.len(),
+      })
+    }
   }
 }
 
@@ -184,13 +762,15 @@ mod tests {
   #[test]
   pub fn test_simple_antipodal_scenario() {
     let mut universe = Universe::new();
-    universe.load_scenario(
-      "{
+    universe
+      .try_load_scenario(
+        "{
         \"scenario\": \"AntipodalCircle\",
         \"agents_count\": 4,
         \"radius\": 10.0
       }",
-    );
+      )
+      .unwrap();
     assert_eq!(universe.count_agents(), 4);
     universe
       .render_agents()
@@ -201,8 +781,8 @@ mod tests {
       ])
       .for_each(|(value, expected)| assert_relative_eq!(value, &expected, epsilon = 0.0001));
     (0..100).for_each(|_| {
-      universe.update(0.25);
-      universe.render_debug_info(0);
+      universe.try_update(0.25).unwrap();
+      universe.try_render_debug_info(0).unwrap();
     });
     let end_state = universe.render_agents();
     // All should have reached their target
@@ -216,7 +796,7 @@ mod tests {
     assert_relative_eq!(end_state[22], 10., epsilon = 0.0001);
 
     assert_eq!(
-      universe.render_navmesh(),
+      universe.try_render_navmesh().unwrap(),
       "v -15.000 -15.000 0.0\n\
       v 15.000 -15.000 0.0\n\
       v 15.000 15.000 0.0\n\
@@ -225,4 +805,644 @@ mod tests {
       f 1 3 4\n"
     );
   }
+
+  #[test]
+  pub fn test_scripted_agent_scenario() {
+    let mut universe = Universe::new();
+    universe
+      .try_load_scenario(
+        "{
+        \"scenario\": \"Custom\",
+        \"agents\": [
+          { \"position\": [-5, 0], \"target\": [5, 0] },
+          { \"trajectory\": [[0, 0, 5], [5, 0, -5]] }
+        ]
+      }",
+      )
+      .unwrap();
+    assert_eq!(universe.count_agents(), 2);
+    (0..20).for_each(|_| {
+      universe.try_update(0.25).unwrap();
+      let state = universe.render_agents();
+      // The scripted agent follows its trajectory
+      assert_relative_eq!(state[7], 0., epsilon = 0.0001);
+      assert_relative_eq!(state[8], 5. - 2. * universe.get_time(), epsilon = 0.0001);
+      // The other agent avoids it
+      let distance = Vec2::new(state[7] - state[0], state[8] - state[1]).norm();
+      assert!(distance > state[6] + state[13] - 0.01);
+    });
+  }
+
+  #[test]
+  pub fn test_group_scenario() {
+    let mut universe = Universe::new();
+    universe
+      .try_load_scenario(
+        "{
+        \"scenario\": \"Custom\",
+        \"groups\": [{ \"formation\": \"SideBySide\", \"spacing\": 1.0 }],
+        \"agents\": [
+          { \"position\": [-8, 0], \"target\": [8, 0], \"group\": 0 },
+          { \"position\": [-6, 1], \"target\": [8, 1], \"group\": 0 }
+        ]
+      }",
+      )
+      .unwrap();
+    (0..20).for_each(|_| {
+      universe.try_update(0.25).unwrap();
+    });
+    let state = universe.render_agents();
+    // Members are walking abreast
+    assert_relative_eq!(state[0], state[7], epsilon = 0.1);
+    assert_relative_eq!(state[8] - state[1], 1.0, epsilon = 0.1);
+  }
+
+  #[test]
+  pub fn test_queue_scenario() {
+    let mut universe = Universe::new();
+    universe
+      .try_load_scenario(
+        "{
+        \"scenario\": \"Custom\",
+        \"queues\": [{ \"position\": [0, 0], \"direction\": [-1, 0], \"service_time\": 1 }],
+        \"agents\": [
+          { \"position\": [-5, -1], \"target\": [5, -1], \"queue\": 0 },
+          { \"position\": [-6, 1], \"target\": [5, 0], \"queue\": 0 },
+          { \"position\": [-7, 0], \"target\": [5, 1], \"queue\": 0 }
+        ]
+      }",
+      )
+      .unwrap();
+    let mut served_order = Vec::new();
+    (0..200).for_each(|_| {
+      universe.try_update(0.1).unwrap();
+      universe
+        .agents
+        .get_queue_ids()
+        .iter()
+        .enumerate()
+        .for_each(|(idx_agent, queue_id)| {
+          if queue_id.is_none() && !served_order.contains(&idx_agent) {
+            served_order.push(idx_agent);
+          }
+        });
+    });
+    // Agents are served in their arrival order
+    assert_eq!(served_order, vec![0, 1, 2]);
+    assert!(universe.agents.get_queues()[0].get_members().is_empty());
+    // And then reach their own target
+    let end_state = universe.render_agents();
+    assert_relative_eq!(end_state[0], 5., epsilon = 0.01);
+    assert_relative_eq!(end_state[1], -1., epsilon = 0.01);
+    assert_relative_eq!(end_state[14], 5., epsilon = 0.01);
+    assert_relative_eq!(end_state[15], 1., epsilon = 0.01);
+  }
+
+  #[test]
+  pub fn test_arrival_scenario() {
+    let mut universe = Universe::new();
+    universe.try_load_scenario(
+      "{
+        \"scenario\": \"Custom\",
+        \"arrival_policy\": \"Despawn\",
+        \"agents\": [
+          { \"position\": [-5, 5], \"target\": [0, 5], \"arrival_policy\": \"NextTarget\", \"waypoints\": [[0, 8]] },
+          { \"position\": [-5, -5], \"target\": [0, -5], \"arrival_policy\": \"Stop\" },
+          { \"position\": [5, -5], \"target\": [0, -5], \"arrival_policy\": \"Stop\" },
+          { \"position\": [5, 0], \"target\": [8, 0] }
+        ]
+      }",
+    ).unwrap();
+    assert_eq!(universe.count_agents(), 4);
+    (0..200).for_each(|_| {
+      universe.try_update(0.1).unwrap();
+    });
+    // The last agent left once arrived
+    assert_eq!(universe.count_agents(), 3);
+    // The first agent reached its waypoint
+    let end_state = universe.render_agents();
+    assert_relative_eq!(end_state[0], 0., epsilon = 0.01);
+    assert_relative_eq!(end_state[1], 8., epsilon = 0.01);
+    // Agents sharing a target both arrived and stopped next to each other
+    assert!(universe.agents.get_arrived().iter().all(|&arrived| arrived));
+    assert_relative_eq!(end_state[11], 0., epsilon = 0.01);
+    assert_relative_eq!(end_state[18], 0., epsilon = 0.01);
+  }
+
+  #[test]
+  pub fn test_seeded_scenario() {
+    let scenario = |seed: u64| {
+      format!(
+        "{{
+          \"scenario\": \"AntipodalCircle\",
+          \"agents_count\": 6,
+          \"radius\": 5.0,
+          \"jitter\": 0.1,
+          \"arrival_policy\": \"Wander\",
+          \"seed\": {}
+        }}",
+        seed
+      )
+    };
+    let run = |seed: u64| {
+      let mut universe = Universe::new();
+      universe.try_load_scenario(&scenario(seed)).unwrap();
+      (0..100).for_each(|_| universe.try_update(0.1).unwrap());
+      universe.render_agents()
+    };
+    // Same seed, same results, bit for bit
+    assert_eq!(run(3), run(3));
+    assert_ne!(run(3), run(4));
+  }
+
+  #[test]
+  pub fn test_seeded_scenario_golden() {
+    // The expected values are shared by the native and wasm tests, checking that
+    // both targets produce bit-identical results.
+    let mut universe = Universe::new();
+    universe
+      .try_load_scenario(
+        "{
+        \"scenario\": \"AntipodalCircle\",
+        \"agents_count\": 3,
+        \"radius\": 4.0,
+        \"jitter\": 0.2,
+        \"seed\": 42
+      }",
+      )
+      .unwrap();
+    (0..40).for_each(|_| universe.try_update(0.1).unwrap());
+    assert_eq!(
+      universe.render_agents().to_vec(),
+      vec![
+        -3.721627043647701,
+        -0.018796720501117765,
+        -0.9977926844157325,
+        0.06640601573988747,
+        -1.4492133338962965,
+        0.09785597832738877,
+        0.35,
+        1.9999999999999991,
+        -3.464101615137755,
+        0.19116399526644268,
+        -0.9815581118373846,
+        0.0,
+        -0.0,
+        0.35,
+        1.9038455384278132,
+        3.223750400759977,
+        0.373551110616365,
+        0.9276095987845753,
+        0.5219733435327538,
+        1.3047436898883622,
+        0.35
+      ]
+    );
+  }
+
+  #[test]
+  pub fn test_advance() {
+    let mut universe = Universe::new();
+    universe
+      .try_load_scenario(
+        "{
+        \"scenario\": \"Corridor\"
+      }",
+      )
+      .unwrap();
+    universe.try_set_timestep(0.1).unwrap();
+    assert_eq!(universe.advance(0.25).unwrap(), 2);
+    assert_eq!(universe.advance(0.06).unwrap(), 1);
+    assert_eq!(universe.get_step_index(), 3);
+    assert_relative_eq!(universe.get_time(), 0.3);
+    universe.try_step().unwrap();
+    assert_eq!(universe.get_step_index(), 4);
+
+    // Same results whatever the real time updates
+    let mut other_universe = Universe::new();
+    other_universe
+      .try_load_scenario(
+        "{
+        \"scenario\": \"Corridor\"
+      }",
+      )
+      .unwrap();
+    other_universe.try_set_timestep(0.1).unwrap();
+    (0..4).for_each(|_| other_universe.try_update(0.1).unwrap());
+    assert_eq!(universe.render_agents(), other_universe.render_agents());
+
+    // Loading a scenario resets the clock
+    universe
+      .try_load_scenario(
+        "{
+        \"scenario\": \"Corridor\"
+      }",
+      )
+      .unwrap();
+    assert_eq!(universe.get_step_index(), 0);
+    assert_relative_eq!(universe.get_time(), 0.);
+  }
+  #[test]
+  pub fn test_render_buffers() {
+    let mut universe = Universe::new();
+    universe
+      .try_load_scenario(
+        "{
+        \"scenario\": \"Corridor\"
+      }",
+      )
+      .unwrap();
+    (0..3).for_each(|_| universe.try_update(0.1).unwrap());
+    universe.update_render_buffers();
+    let positions_ptr = universe.get_render_positions_ptr();
+    let render_buffers = universe.get_render_buffers();
+    assert_eq!(render_buffers.get_ids().len(), universe.count_agents());
+    // Same values as the boxed rendering, with single precision
+    let rendered_agents = universe.render_agents();
+    rendered_agents
+      .chunks(7)
+      .enumerate()
+      .for_each(|(idx_agent, agent)| {
+        assert_eq!(
+          &render_buffers.get_positions()[2 * idx_agent..2 * idx_agent + 2],
+          &[agent[0] as f32, agent[1] as f32]
+        );
+        assert_eq!(
+          &render_buffers.get_directions()[2 * idx_agent..2 * idx_agent + 2],
+          &[agent[2] as f32, agent[3] as f32]
+        );
+        assert_eq!(
+          &render_buffers.get_velocities()[2 * idx_agent..2 * idx_agent + 2],
+          &[agent[4] as f32, agent[5] as f32]
+        );
+        assert_eq!(render_buffers.get_radii()[idx_agent], agent[6] as f32);
+      });
+
+    // The buffers stay in place from one frame to the next
+    universe.try_update(0.1).unwrap();
+    universe.update_render_buffers();
+    assert_eq!(universe.get_render_positions_ptr(), positions_ptr);
+  }
+  #[test]
+  pub fn test_snapshot_round_trip() {
+    let mut universe = Universe::new();
+    universe
+      .try_load_scenario(
+        "{
+        \"scenario\": \"AntipodalCircle\",
+        \"agents_count\": 5,
+        \"radius\": 3.0,
+        \"jitter\": 0.2,
+        \"arrival_policy\": \"Wander\",
+        \"seed\": 7
+      }",
+      )
+      .unwrap();
+    universe.try_set_timestep(0.1).unwrap();
+    universe.try_set_time_horizon(4.).unwrap();
+    (0..30).for_each(|_| universe.try_step().unwrap());
+
+    let mut json_universe = Universe::new();
+    json_universe
+      .restore_from_json(&universe.snapshot_to_json())
+      .unwrap();
+    let mut binary_universe = Universe::new();
+    binary_universe
+      .restore_from_binary(&universe.snapshot_to_binary())
+      .unwrap();
+    assert_relative_eq!(json_universe.time_horizon, 4.);
+    assert_relative_eq!(binary_universe.time_horizon, 4.);
+
+    // Wandering agents keep drawing random targets, the random numbers generator
+    // state must have been restored as well.
+    (0..100).for_each(|_| {
+      universe.try_step().unwrap();
+      json_universe.try_step().unwrap();
+      binary_universe.try_step().unwrap();
+      assert_eq!(json_universe.render_agents(), universe.render_agents());
+      assert_eq!(binary_universe.render_agents(), universe.render_agents());
+    });
+    assert_eq!(json_universe.get_step_index(), universe.get_step_index());
+    assert_eq!(json_universe.get_time(), universe.get_time());
+
+    // Restoring an earlier state restarts the recording and the metrics.
+    let snapshot = universe.snapshot();
+    let time = universe.get_time();
+    universe.try_start_recording(1., false).unwrap();
+    universe.set_metrics(MetricsConfig::default()).unwrap();
+    (0..30).for_each(|_| universe.try_step().unwrap());
+    universe.restore(snapshot);
+    let samples = universe.get_recorder().unwrap().get_samples();
+    assert_eq!(samples.len(), universe.count_agents());
+    assert!(samples.iter().all(|sample| sample.time == time));
+    assert!(universe
+      .get_metrics()
+      .unwrap()
+      .get_last_step_metrics()
+      .is_none());
+    universe.try_step().unwrap();
+    assert_eq!(
+      universe.get_recorder().unwrap().get_samples().len(),
+      universe.count_agents()
+    );
+  }
+  #[test]
+  pub fn test_recording() {
+    let mut universe = Universe::new();
+    universe
+      .try_load_scenario(
+        "{
+        \"scenario\": \"Corridor\",
+        \"agents_per_side_count\": 2
+      }",
+      )
+      .unwrap();
+    assert_eq!(universe.export_recording_to_csv(), None);
+    universe.try_set_timestep(0.1).unwrap();
+    universe.try_start_recording(0.5, false).unwrap();
+    (0..10).for_each(|_| universe.try_step().unwrap());
+    let csv = universe.export_recording_to_csv().unwrap();
+    // Header, then 4 agents sampled at 0, 0.5 and 1.0.
+    assert_eq!(csv.lines().count(), 1 + 3 * 4);
+    assert_eq!(
+      universe
+        .export_recording_to_json_lines()
+        .unwrap()
+        .lines()
+        .count(),
+      3 * 4
+    );
+    let last_line: Vec<&str> = csv.lines().last().unwrap().split(',').collect();
+    assert_relative_eq!(last_line[0].parse::<f64>().unwrap(), 1.);
+    assert_eq!(last_line[1], "3");
+
+    // Loading a scenario restarts the recording.
+    universe
+      .try_load_scenario(
+        "{
+        \"scenario\": \"Corridor\",
+        \"agents_per_side_count\": 2
+      }",
+      )
+      .unwrap();
+    assert_eq!(
+      universe.export_recording_to_csv().unwrap().lines().count(),
+      1 + 4
+    );
+    universe.stop_recording();
+    assert_eq!(universe.export_recording_to_csv(), None);
+  }
+  #[test]
+  pub fn test_metrics() {
+    let mut universe = Universe::new();
+    universe
+      .try_load_scenario(
+        "{
+        \"scenario\": \"Corridor\",
+        \"agents_per_side_count\": 3,
+        \"length\": 10,
+        \"width\": 3.0
+      }",
+      )
+      .unwrap();
+    assert_eq!(universe.render_metrics(), "null");
+    universe
+      .set_metrics(MetricsConfig {
+        measurement_lines: vec![metrics::MeasurementLine {
+          start: [0., 2.],
+          end: [0., -2.],
+        }],
+        ..MetricsConfig::default()
+      })
+      .unwrap();
+    universe.try_set_timestep(0.1).unwrap();
+    (0..100).for_each(|_| universe.try_step().unwrap());
+    let step_metrics = universe
+      .get_metrics()
+      .unwrap()
+      .get_last_step_metrics()
+      .unwrap();
+    assert_eq!(step_metrics.agents_count, 6);
+    // Each agent crossed the middle of the corridor once, 3 in each direction.
+    let line_flow = step_metrics.line_flows[0];
+    assert_eq!(line_flow.total_forward_crossings_count, 3);
+    assert_eq!(line_flow.total_backward_crossings_count, 3);
+    assert!(universe
+      .render_metrics()
+      .contains("\"fundamental_diagram\""));
+  }
+  #[test]
+  pub fn test_travels() {
+    let mut universe = Universe::new();
+    universe
+      .try_load_scenario(
+        "{
+        \"scenario\": \"Custom\",
+        \"arrival_policy\": \"Despawn\",
+        \"agents\": [
+          { \"position\": [-5, 0], \"target\": [5, 0] },
+          { \"position\": [0, -5], \"target\": [0, 5] },
+          { \"position\": [-8, 8], \"target\": [-8, 7] }
+        ]
+      }",
+      )
+      .unwrap();
+    universe.try_set_timestep(0.1).unwrap();
+    (0..100).for_each(|_| universe.try_step().unwrap());
+    // Despawned agents are still accounted for.
+    assert_eq!(universe.count_agents(), 0);
+    let records = universe.get_travel_records();
+    assert_eq!(records.len(), 3);
+    // The closest agent arrives first.
+    assert_eq!(records[0].id, 2);
+    records.iter().for_each(|record| {
+      assert_relative_eq!(record.travel_time, record.arrival_time);
+      assert!(record.path_length >= record.straight_line_length);
+      assert!(record.path_length_ratio < 1.2);
+    });
+    let summary = TravelsSummary::new(records);
+    assert_eq!(summary.arrived_count, 3);
+    assert!(summary.travel_time.unwrap().max > 4.);
+    assert_eq!(universe.export_travels_to_csv().lines().count(), 4);
+
+    // An agent departing later, from where it is created.
+    universe.agents.create_agent(
+      agent::Agent::new()
+        .position(0., 0.)
+        .target(2., 0.)
+        .departure_time(universe.get_time()),
+    );
+    (0..30).for_each(|_| universe.try_step().unwrap());
+    let record = universe.get_travel_records()[3];
+    assert_eq!(record.id, 3);
+    assert_relative_eq!(
+      record.travel_time,
+      record.arrival_time - 10.,
+      epsilon = 1e-9
+    );
+    assert_relative_eq!(record.straight_line_length, 2., epsilon = 0.2);
+  }
+
+  #[test]
+  pub fn test_invalid_inputs() {
+    let mut universe = Universe::new();
+    universe
+      .try_load_scenario(
+        "{
+        \"scenario\": \"Custom\",
+        \"agents\": [{ \"position\": [0, 0], \"target\": [5, 0] }]
+      }",
+      )
+      .unwrap();
+    assert!(universe.try_update(0.).is_err());
+    assert!(universe.try_update(f64::NAN).is_err());
+    assert_eq!(
+      universe.try_set_timestep(-0.1),
+      Err(Error::InvalidParameter {
+        name: "timestep",
+        value: -0.1
+      })
+    );
+    assert!(universe.try_set_time_horizon(0.).is_err());
+    assert!(universe.try_start_recording(-1., false).is_err());
+    assert!(universe
+      .set_metrics(MetricsConfig {
+        cell_size: 0.,
+        ..MetricsConfig::default()
+      })
+      .is_err());
+    assert!(universe.get_metrics().is_none());
+    assert_eq!(
+      universe.try_render_debug_info(1),
+      Err(Error::InvalidAgentIndex {
+        index: 1,
+        agents_count: 1
+      })
+    );
+    // An invalid scenario leaves the universe unchanged.
+    assert!(matches!(
+      universe.try_load_scenario("{ \"scenario\": \"Unknown\" }"),
+      Err(Error::InvalidScenario(_))
+    ));
+    assert!(matches!(
+      universe.try_load_scenario(
+        "{ \"scenario\": \"Custom\", \"bounds\": [0, 0, 0, 10], \"agents\": [] }"
+      ),
+      Err(Error::InvalidNavmesh(_))
+    ));
+    assert_eq!(universe.count_agents(), 1);
+    universe.try_update(0.1).unwrap();
+    assert!(universe.try_render_debug_info(0).is_ok());
+
+    // A non finite target is reported with the agent and the navigator.
+    universe
+      .agents
+      .create_agent(agent::Agent::new().position(1., 1.).target(f64::NAN, 0.));
+    assert_eq!(
+      universe.try_update(0.1),
+      Err(Error::NonFiniteValue {
+        agent_id: 1,
+        quantity: "target",
+        navigator: "queue"
+      })
+    );
+    // The agents state is left untouched.
+    assert_relative_eq!(universe.get_time(), 0.1);
+    assert!(universe
+      .agents
+      .get_positions()
+      .iter()
+      .all(|position| position.is_finite()));
+  }
+
+  #[test]
+  pub fn test_failed_step() {
+    let mut universe = Universe::new();
+    universe
+      .try_load_scenario(
+        "{
+        \"scenario\": \"Custom\",
+        \"queues\": [{ \"position\": [0, 0], \"direction\": [1, 0] }],
+        \"agents\": [
+          { \"position\": [0.1, 0], \"target\": [-5, 0], \"queue\": 0 },
+          { \"position\": [3, 3], \"target\": [-5, 3] }
+        ]
+      }",
+      )
+      .unwrap();
+    // The first agent would join the queue and the second would move, but
+    // the step fails on the last agent's target.
+    universe.agents.create_agent(
+      agent::Agent::new()
+        .position(1., 1.)
+        .target(f64::INFINITY, 0.),
+    );
+    let agents = universe.agents.clone();
+    let time = universe.get_time();
+    assert!(universe.try_step().is_err());
+    assert_eq!(universe.agents, agents);
+    assert_relative_eq!(universe.get_time(), time);
+
+    universe.agents.remove_agent(2);
+    universe.try_step().unwrap();
+    assert_eq!(universe.agents.get_queues()[0].get_members(), &[0]);
+  }
+
+  #[test]
+  pub fn test_step_in_place() {
+    let mut universe = Universe::new();
+    universe
+      .try_load_scenario(
+        "{
+        \"scenario\": \"Custom\",
+        \"groups\": [{ \"formation\": \"SideBySide\" }],
+        \"queues\": [{ \"position\": [0, 0], \"direction\": [1, 0] }],
+        \"agents\": [
+          { \"position\": [0.1, 0], \"target\": [-5, 0], \"queue\": 0 },
+          { \"position\": [3, 3], \"target\": [-5, 3], \"group\": 0 },
+          { \"position\": [3, 4], \"target\": [-5, 4], \"group\": 0 }
+        ]
+      }",
+      )
+      .unwrap();
+    universe.try_step().unwrap();
+    let positions = universe.agents.get_positions().as_ptr();
+    let buffered_positions = universe.buffers.positions.as_ptr();
+    let members = universe.agents.get_queues()[0].get_members().as_ptr();
+    // The columns are exchanged with the step buffers, not reallocated.
+    universe.try_step().unwrap();
+    assert_eq!(universe.agents.get_positions().as_ptr(), buffered_positions);
+    assert_eq!(universe.buffers.positions.as_ptr(), positions);
+    universe.try_step().unwrap();
+    assert_eq!(universe.agents.get_positions().as_ptr(), positions);
+    assert_eq!(
+      universe.agents.get_queues()[0].get_members().as_ptr(),
+      members
+    );
+  }
+
+  #[test]
+  pub fn test_coincident_agents() {
+    let mut universe = Universe::new();
+    universe
+      .try_load_scenario(
+        "{
+        \"scenario\": \"Custom\",
+        \"agents\": [
+          { \"position\": [0, 0], \"target\": [5, 0] },
+          { \"position\": [0, 0], \"target\": [-5, 0] },
+          { \"position\": [0, 3], \"target\": [5, 3] },
+          { \"position\": [0, 3], \"target\": [5, 3] }
+        ]
+      }",
+      )
+      .unwrap();
+    (0..40).for_each(|_| universe.try_update(0.1).unwrap());
+    let state = universe.render_agents();
+    assert!(state.iter().all(|value| value.is_finite()));
+    // Agents heading in opposite directions get apart.
+    assert!(Vec2::new(state[7] - state[0], state[8] - state[1]).norm() > state[6] + state[13]);
+    // Agents heading to the same target get apart too.
+    assert!(Vec2::new(state[21] - state[14], state[22] - state[15]).norm() > state[20] + state[27]);
+  }
 }
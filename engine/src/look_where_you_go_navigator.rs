@@ -1,29 +1,75 @@
 use super::vec2::Vec2;
 use itertools::izip;
 
-pub fn look_where_you_go_navigator(directions: &[Vec2], velocities: &[Vec2]) -> Vec<Vec2> {
-  izip!(directions, velocities)
-    .map(move |(&direction, &velocity)| {
+// Progressively turn the agents toward their walking direction.
+//
+// The rotation closes the gap with the walking direction over the agent's
+// smoothing time, without exceeding the agent's maximum angular speed.
+pub fn look_where_you_go_navigator(
+  directions: &[Vec2],
+  velocities: &[Vec2],
+  maximum_angular_speeds: &[f64],
+  smoothing_times: &[f64],
+  dt: f64,
+  updated_directions: &mut [Vec2],
+) {
+  izip!(
+    directions,
+    velocities,
+    maximum_angular_speeds,
+    smoothing_times,
+    updated_directions
+  )
+  .for_each(
+    move |(&direction, &velocity, &maximum_angular_speed, &smoothing_time, updated_direction)| {
       let speed = velocity.norm();
-      if speed < f64::EPSILON {
+      *updated_direction = if speed < f64::EPSILON {
         direction
       } else {
-        velocity / speed
-      }
-    })
-    .collect()
+        let smoothing_factor = if smoothing_time > dt {
+          dt / smoothing_time
+        } else {
+          1.
+        };
+        let maximum_rotation = maximum_angular_speed * dt;
+        let rotation = (smoothing_factor * Vec2::angle(direction, velocity))
+          .max(-maximum_rotation)
+          .min(maximum_rotation);
+        direction.rotate(rotation)
+      };
+    },
+  );
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
+  use approx::assert_relative_eq;
 
   #[test]
   fn test_simple_look_where_you_go_navigator() {
     let d = vec![Vec2::new(1.0, 0.0), Vec2::new(1.0, 1.0).normalize()];
     let v = vec![Vec2::new(-1.0, 1.0), Vec2::new(0.0, 0.0)];
-    let updated_d: Vec<Vec2> = look_where_you_go_navigator(&d, &v);
-    assert_eq!(updated_d[0], v[0].normalize());
+    let mas = vec![10.0, 10.0];
+    let mut updated_d = vec![Vec2::default(); 2];
+    look_where_you_go_navigator(&d, &v, &mas, &[0., 0.], 0.5, &mut updated_d);
+    assert_relative_eq!(updated_d[0], v[0].normalize());
     assert_eq!(updated_d[1], d[1]);
   }
+
+  #[test]
+  fn test_look_where_you_go_navigator_turning_rate() {
+    let d = vec![Vec2::new(1.0, 0.0), Vec2::new(1.0, 0.0)];
+    let v = vec![Vec2::new(0.0, 2.0), Vec2::new(0.0, -2.0)];
+    let mas = vec![std::f64::consts::FRAC_PI_2, 10.0];
+    let mut updated_d = vec![Vec2::default(); 2];
+    look_where_you_go_navigator(&d, &v, &mas, &[1.0, 1.0], 0.5, &mut updated_d);
+    // Limited by the maximum angular speed
+    assert_relative_eq!(updated_d[0], Vec2::new(1.0, 1.0).normalize());
+    // Limited by the smoothing
+    assert_relative_eq!(updated_d[1], Vec2::new(1.0, -1.0).normalize());
+    // Each agent has its own smoothing
+    look_where_you_go_navigator(&d, &v, &mas, &[1.0, 0.25], 0.5, &mut updated_d);
+    assert_relative_eq!(updated_d[1], Vec2::new(0.0, -1.0));
+  }
 }
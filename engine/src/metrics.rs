@@ -0,0 +1,606 @@
+use super::agents::Agents;
+use super::collisions::{detect_agents_overlaps, detect_walls_penetrations};
+use super::error::{check_non_negative, check_positive, Error};
+use super::navmesh::Navmesh;
+use super::neighborhood::AgentNeighborhood;
+use super::vec2::Vec2;
+use itertools::izip;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::vec::Vec;
+
+pub const DEFAULT_CELL_SIZE: f64 = 1.0;
+// Cells of the density grid, beyond which the cells are enlarged.
+pub const MAXIMUM_CELLS_COUNT: usize = 1_000_000;
+
+// Oriented segment counting the agents crossing it, crossing from its right
+// side to its left side is counted as forward.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MeasurementLine {
+  pub start: [f64; 2],
+  pub end: [f64; 2],
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct MetricsConfig {
+  // Size of the density grid cells.
+  pub cell_size: f64,
+  // Area covered by the density grid, as [min_x, min_y, max_x, max_y], the
+  // navmesh bounds are used when not defined.
+  pub region: Option<[f64; 4]>,
+  pub measurement_lines: Vec<MeasurementLine>,
+}
+
+impl MetricsConfig {
+  // Check the config describes a valid grid and valid measurement lines.
+  pub fn check(&self) -> Result<(), Error> {
+    check_positive("cell_size", self.cell_size)?;
+    if let Some([min_x, min_y, max_x, max_y]) = self.region {
+      check_non_negative("region width", max_x - min_x)?;
+      check_non_negative("region height", max_y - min_y)?;
+      if cells_count([min_x, min_y, max_x, max_y], self.cell_size) > MAXIMUM_CELLS_COUNT as f64 {
+        return Err(Error::InvalidParameter {
+          name: "cell_size",
+          value: self.cell_size,
+        });
+      }
+    }
+    self
+      .measurement_lines
+      .iter()
+      .flat_map(|line| line.start.iter().chain(line.end.iter()))
+      .find(|coordinate| !coordinate.is_finite())
+      .map_or(Ok(()), |&value| {
+        Err(Error::InvalidParameter {
+          name: "measurement line",
+          value,
+        })
+      })
+  }
+}
+
+impl Default for MetricsConfig {
+  fn default() -> Self {
+    MetricsConfig {
+      cell_size: DEFAULT_CELL_SIZE,
+      region: None,
+      measurement_lines: Vec::new(),
+    }
+  }
+}
+
+// Number of agents per square meter in each cell of a regular grid.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DensityGrid {
+  // Corner of the first cell, with the smallest coordinates.
+  pub origin: [f64; 2],
+  pub cell_size: f64,
+  pub columns_count: usize,
+  pub rows_count: usize,
+  // Row major densities, starting from the origin.
+  pub densities: Vec<f64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct LineFlow {
+  // Crossings during the last step.
+  pub forward_crossings_count: usize,
+  pub backward_crossings_count: usize,
+  // Crossings since the metrics were started.
+  pub total_forward_crossings_count: usize,
+  pub total_backward_crossings_count: usize,
+  // Specific flow during the last step, in agents per second per meter,
+  // regardless of the direction.
+  pub flow: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct AgentCollisionsCount {
+  pub id: usize,
+  // Number of steps overlapping each other agent, summed over the agents.
+  pub agents_overlaps_count: usize,
+  // Number of steps penetrating the navmesh boundary.
+  pub walls_penetrations_count: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct CollisionsStats {
+  // Overlapping agents pairs and penetrating agents during the last step.
+  pub agents_overlaps_count: usize,
+  pub walls_penetrations_count: usize,
+  pub maximum_agents_overlap_depth: f64,
+  pub maximum_wall_penetration_depth: f64,
+  // Since the metrics were started.
+  pub total_agents_overlaps_count: usize,
+  pub total_walls_penetrations_count: usize,
+  pub total_maximum_agents_overlap_depth: f64,
+  pub total_maximum_wall_penetration_depth: f64,
+  // Agents involved in at least one collision, sorted by id.
+  pub agents: Vec<AgentCollisionsCount>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct StepMetrics {
+  pub time: f64,
+  pub agents_count: usize,
+  // Number of agents per square meter over the whole grid.
+  pub global_density: f64,
+  pub mean_speed: f64,
+  pub density_grid: DensityGrid,
+  pub line_flows: Vec<LineFlow>,
+  // Speed-density data points, as [density, mean speed] for each occupied
+  // grid cell.
+  pub fundamental_diagram: Vec<[f64; 2]>,
+  pub collisions: CollisionsStats,
+}
+
+// Crowd metrics computed after each simulation step.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Metrics {
+  config: MetricsConfig,
+  // Positions at the previous step, by agent id, to detect line crossings.
+  previous_positions: HashMap<usize, Vec2>,
+  total_crossings_counts: Vec<(usize, usize)>,
+  // Agents and walls collisions counts, by agent id.
+  collisions_counts: BTreeMap<usize, (usize, usize)>,
+  maximum_agents_overlap_depth: f64,
+  maximum_wall_penetration_depth: f64,
+  last_step_metrics: Option<StepMetrics>,
+  // Scratch space for the density grid, reused across steps.
+  cells_counts: Vec<usize>,
+  cells_speed_sums: Vec<f64>,
+}
+
+impl Metrics {
+  pub fn new(config: MetricsConfig) -> Result<Self, Error> {
+    config.check()?;
+    let total_crossings_counts = vec![(0, 0); config.measurement_lines.len()];
+    Ok(Metrics {
+      config,
+      previous_positions: HashMap::new(),
+      total_crossings_counts,
+      collisions_counts: BTreeMap::new(),
+      maximum_agents_overlap_depth: 0.,
+      maximum_wall_penetration_depth: 0.,
+      last_step_metrics: None,
+      cells_counts: Vec::new(),
+      cells_speed_sums: Vec::new(),
+    })
+  }
+
+  pub fn get_last_step_metrics(&self) -> Option<&StepMetrics> {
+    self.last_step_metrics.as_ref()
+  }
+
+  // Forget the previous steps, e.g. when a new scenario is loaded.
+  pub fn reset(&mut self) {
+    self.previous_positions.clear();
+    self
+      .total_crossings_counts
+      .iter_mut()
+      .for_each(|counts| *counts = (0, 0));
+    self.collisions_counts.clear();
+    self.maximum_agents_overlap_depth = 0.;
+    self.maximum_wall_penetration_depth = 0.;
+    self.last_step_metrics = None;
+  }
+
+  // `neighborhoods` must match the current indices of the agents.
+  pub fn update(
+    &mut self,
+    time: f64,
+    dt: f64,
+    agents: &Agents,
+    neighborhoods: &[AgentNeighborhood],
+    navmesh: &Navmesh,
+  ) {
+    let speeds: Vec<f64> = agents
+      .get_velocities()
+      .iter()
+      .map(|velocity| velocity.norm())
+      .collect();
+    let mean_speed = if speeds.is_empty() {
+      0.
+    } else {
+      speeds.iter().sum::<f64>() / speeds.len() as f64
+    };
+
+    let (density_grid, global_density, fundamental_diagram) =
+      self.compute_densities(agents.get_positions(), &speeds, navmesh);
+
+    let line_flows = self.compute_line_flows(agents, dt);
+    let collisions = self.compute_collisions(agents, neighborhoods, navmesh);
+    self.previous_positions = agents
+      .get_ids()
+      .iter()
+      .copied()
+      .zip(agents.get_positions().iter().copied())
+      .collect();
+
+    self.last_step_metrics = Some(StepMetrics {
+      time,
+      agents_count: agents.len(),
+      global_density,
+      mean_speed,
+      density_grid,
+      line_flows,
+      fundamental_diagram,
+      collisions,
+    });
+  }
+
+  fn compute_densities(
+    &mut self,
+    positions: &[Vec2],
+    speeds: &[f64],
+    navmesh: &Navmesh,
+  ) -> (DensityGrid, f64, Vec<[f64; 2]>) {
+    let region = self
+      .config
+      .region
+      .or_else(|| navmesh.get_bounds())
+      .unwrap_or([0., 0., 0., 0.]);
+    let [min_x, min_y, max_x, max_y] = region;
+    // The navmesh bounds aren't known when the config is checked, a grid
+    // covering them with too many cells gets coarser instead.
+    let mut cell_size = self.config.cell_size;
+    while cells_count(region, cell_size) > MAXIMUM_CELLS_COUNT as f64 {
+      cell_size *= 2.;
+    }
+    let columns_count = ((max_x - min_x) / cell_size).ceil().max(0.) as usize;
+    let rows_count = ((max_y - min_y) / cell_size).ceil().max(0.) as usize;
+    let counts = &mut self.cells_counts;
+    let speed_sums = &mut self.cells_speed_sums;
+    counts.clear();
+    counts.resize(columns_count * rows_count, 0);
+    speed_sums.clear();
+    speed_sums.resize(columns_count * rows_count, 0.);
+    izip!(positions, speeds)
+      .filter(|(position, _)| {
+        position.x() >= min_x
+          && position.x() < max_x
+          && position.y() >= min_y
+          && position.y() < max_y
+      })
+      .for_each(|(position, &speed)| {
+        let column = (((position.x() - min_x) / cell_size) as usize).min(columns_count - 1);
+        let row = (((position.y() - min_y) / cell_size) as usize).min(rows_count - 1);
+        counts[row * columns_count + column] += 1;
+        speed_sums[row * columns_count + column] += speed;
+      });
+    let cell_area = cell_size * cell_size;
+    let area = (max_x - min_x) * (max_y - min_y);
+    let global_density = if area > 0. {
+      counts.iter().sum::<usize>() as f64 / area
+    } else {
+      0.
+    };
+    let fundamental_diagram = izip!(counts.iter(), speed_sums.iter())
+      .filter(|(&count, _)| count > 0)
+      .map(|(&count, &speed_sum)| [count as f64 / cell_area, speed_sum / count as f64])
+      .collect();
+    // The previous step densities buffer is reused.
+    let mut densities = self
+      .last_step_metrics
+      .take()
+      .map(|step_metrics| step_metrics.density_grid.densities)
+      .unwrap_or_default();
+    densities.clear();
+    densities.extend(counts.iter().map(|&count| count as f64 / cell_area));
+    (
+      DensityGrid {
+        origin: [min_x, min_y],
+        cell_size,
+        columns_count,
+        rows_count,
+        densities,
+      },
+      global_density,
+      fundamental_diagram,
+    )
+  }
+
+  fn compute_collisions(
+    &mut self,
+    agents: &Agents,
+    neighborhoods: &[AgentNeighborhood],
+    navmesh: &Navmesh,
+  ) -> CollisionsStats {
+    let ids = agents.get_ids();
+    let agents_overlaps =
+      detect_agents_overlaps(agents.get_positions(), agents.get_radii(), neighborhoods);
+    let walls_penetrations = detect_walls_penetrations(
+      agents.get_positions(),
+      agents.get_radii(),
+      agents.get_cells(),
+      navmesh,
+    );
+    agents_overlaps.iter().for_each(|overlap| {
+      self
+        .collisions_counts
+        .entry(ids[overlap.agent])
+        .or_default()
+        .0 += 1;
+      self
+        .collisions_counts
+        .entry(ids[overlap.other_agent])
+        .or_default()
+        .0 += 1;
+    });
+    walls_penetrations.iter().for_each(|penetration| {
+      self
+        .collisions_counts
+        .entry(ids[penetration.agent])
+        .or_default()
+        .1 += 1;
+    });
+    let maximum_agents_overlap_depth = agents_overlaps
+      .iter()
+      .map(|overlap| overlap.depth)
+      .fold(0., f64::max);
+    let maximum_wall_penetration_depth = walls_penetrations
+      .iter()
+      .map(|penetration| penetration.depth)
+      .fold(0., f64::max);
+    self.maximum_agents_overlap_depth = self
+      .maximum_agents_overlap_depth
+      .max(maximum_agents_overlap_depth);
+    self.maximum_wall_penetration_depth = self
+      .maximum_wall_penetration_depth
+      .max(maximum_wall_penetration_depth);
+    let agents: Vec<AgentCollisionsCount> = self
+      .collisions_counts
+      .iter()
+      .map(
+        |(&id, &(agents_overlaps_count, walls_penetrations_count))| AgentCollisionsCount {
+          id,
+          agents_overlaps_count,
+          walls_penetrations_count,
+        },
+      )
+      .collect();
+    CollisionsStats {
+      agents_overlaps_count: agents_overlaps.len(),
+      walls_penetrations_count: walls_penetrations.len(),
+      maximum_agents_overlap_depth,
+      maximum_wall_penetration_depth,
+      // Each overlap is counted for both agents.
+      total_agents_overlaps_count: agents
+        .iter()
+        .map(|count| count.agents_overlaps_count)
+        .sum::<usize>()
+        / 2,
+      total_walls_penetrations_count: agents
+        .iter()
+        .map(|count| count.walls_penetrations_count)
+        .sum(),
+      total_maximum_agents_overlap_depth: self.maximum_agents_overlap_depth,
+      total_maximum_wall_penetration_depth: self.maximum_wall_penetration_depth,
+      agents,
+    }
+  }
+
+  fn compute_line_flows(&mut self, agents: &Agents, dt: f64) -> Vec<LineFlow> {
+    let previous_positions = &self.previous_positions;
+    let movements: Vec<(Vec2, Vec2)> = izip!(agents.get_ids(), agents.get_positions())
+      .filter_map(|(id, &position)| {
+        previous_positions
+          .get(id)
+          .map(|&previous_position| (previous_position, position))
+      })
+      .collect();
+    izip!(
+      &self.config.measurement_lines,
+      &mut self.total_crossings_counts
+    )
+    .map(|(line, total_crossings_counts)| {
+      let (forward_crossings_count, backward_crossings_count) =
+        movements
+          .iter()
+          .fold((0, 0), |(forward, backward), &(from, to)| {
+            match crossing(line, from, to) {
+              Some(true) => (forward + 1, backward),
+              Some(false) => (forward, backward + 1),
+              None => (forward, backward),
+            }
+          });
+      total_crossings_counts.0 += forward_crossings_count;
+      total_crossings_counts.1 += backward_crossings_count;
+      let length =
+        (Vec2::new(line.end[0], line.end[1]) - Vec2::new(line.start[0], line.start[1])).norm();
+      LineFlow {
+        forward_crossings_count,
+        backward_crossings_count,
+        total_forward_crossings_count: total_crossings_counts.0,
+        total_backward_crossings_count: total_crossings_counts.1,
+        flow: if dt > 0. && length > 0. {
+          (forward_crossings_count + backward_crossings_count) as f64 / (dt * length)
+        } else {
+          0.
+        },
+      }
+    })
+    .collect()
+  }
+}
+
+// Number of cells of a grid covering the region.
+fn cells_count([min_x, min_y, max_x, max_y]: [f64; 4], cell_size: f64) -> f64 {
+  ((max_x - min_x) / cell_size).ceil() * ((max_y - min_y) / cell_size).ceil()
+}
+
+// Whether the movement from `from` to `to` crosses the line, forward or
+// backward.
+fn crossing(line: &MeasurementLine, from: Vec2, to: Vec2) -> Option<bool> {
+  let start = Vec2::new(line.start[0], line.start[1]);
+  let direction = Vec2::new(line.end[0], line.end[1]) - start;
+  let from_side = Vec2::det(direction, from - start);
+  let to_side = Vec2::det(direction, to - start);
+  let forward = from_side < 0. && to_side >= 0.;
+  let backward = from_side >= 0. && to_side < 0.;
+  if !forward && !backward {
+    return None;
+  }
+  let crossing_point = from + (from_side / (from_side - to_side)) * (to - from);
+  let u = (crossing_point - start) * direction / direction.sqr_norm();
+  if (0. ..=1.).contains(&u) {
+    Some(forward)
+  } else {
+    None
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::agent::Agent;
+  use crate::navmesh::NavmeshBuilder;
+  use approx::assert_relative_eq;
+
+  fn neighborhoods(agents: &Agents) -> Vec<AgentNeighborhood> {
+    let mut neighborhoods = vec![AgentNeighborhood::default(); agents.len()];
+    AgentNeighborhood::compute_agents_neighborhood(
+      agents.get_positions(),
+      agents.get_velocities(),
+      agents.get_radii(),
+      agents.get_kinds(),
+      agents.get_arrived(),
+      agents.get_group_ids(),
+      agents.get_groups(),
+      &mut neighborhoods,
+    );
+    neighborhoods
+  }
+
+  #[test]
+  fn test_invalid_config() {
+    [0., -1., f64::NAN].iter().for_each(|&cell_size| {
+      assert_eq!(
+        Metrics::new(MetricsConfig {
+          cell_size,
+          ..MetricsConfig::default()
+        })
+        .err()
+        .map(|err| err.to_string()),
+        Some(format!("Invalid value {} for cell_size", cell_size))
+      )
+    });
+    assert!(Metrics::new(MetricsConfig {
+      region: Some([0., 0., -1., 1.]),
+      ..MetricsConfig::default()
+    })
+    .is_err());
+    assert_eq!(
+      Metrics::new(MetricsConfig {
+        cell_size: 0.001,
+        region: Some([0., 0., 10., 1000.]),
+        ..MetricsConfig::default()
+      })
+      .err()
+      .map(|err| err.to_string()),
+      Some("Invalid value 0.001 for cell_size".to_string())
+    );
+    assert!(Metrics::new(MetricsConfig {
+      measurement_lines: vec![MeasurementLine {
+        start: [0., f64::INFINITY],
+        end: [0., 1.]
+      }],
+      ..MetricsConfig::default()
+    })
+    .is_err());
+  }
+
+  #[test]
+  fn test_densities() {
+    let mut agents = Agents::new();
+    agents.create_agent(Agent::new().position(0.5, 0.5).velocity(1., 0.));
+    agents.create_agent(Agent::new().position(0.2, 0.8).velocity(0., 0.5));
+    agents.create_agent(Agent::new().position(1.5, 0.5).velocity(0., 0.));
+    agents.create_agent(Agent::new().position(5., 5.).velocity(3., 0.));
+    let navmesh = NavmeshBuilder::new()
+      .add_cell(Vec2::new(0., 0.), Vec2::new(2., 0.), Vec2::new(2., 2.))
+      .add_cell(Vec2::new(0., 0.), Vec2::new(2., 2.), Vec2::new(0., 2.))
+      .build()
+      .unwrap();
+    let mut metrics = Metrics::new(MetricsConfig::default()).unwrap();
+    metrics.update(0., 0.1, &agents, &neighborhoods(&agents), &navmesh);
+    let step_metrics = metrics.get_last_step_metrics().unwrap();
+    assert_eq!(step_metrics.agents_count, 4);
+    assert_relative_eq!(step_metrics.mean_speed, 1.125);
+    // The last agent is out of the navmesh bounds.
+    assert_relative_eq!(step_metrics.global_density, 0.75);
+    assert_eq!(step_metrics.density_grid.columns_count, 2);
+    assert_eq!(step_metrics.density_grid.rows_count, 2);
+    itertools::assert_equal(&step_metrics.density_grid.densities, &vec![2., 1., 0., 0.]);
+    itertools::assert_equal(
+      &step_metrics.fundamental_diagram,
+      &vec![[2., 0.75], [1., 0.]],
+    );
+    assert_eq!(step_metrics.collisions.agents_overlaps_count, 1);
+    // The last agent is out of the navmesh, the second one is close to its
+    // boundary.
+    assert_eq!(step_metrics.collisions.walls_penetrations_count, 2);
+
+    metrics.update(0.1, 0.1, &agents, &neighborhoods(&agents), &navmesh);
+    let collisions = &metrics.get_last_step_metrics().unwrap().collisions;
+    assert_eq!(collisions.total_agents_overlaps_count, 2);
+    assert_eq!(collisions.total_walls_penetrations_count, 4);
+    assert_eq!(
+      collisions.agents[1],
+      AgentCollisionsCount {
+        id: 1,
+        agents_overlaps_count: 2,
+        walls_penetrations_count: 2
+      }
+    );
+    assert_eq!(collisions.agents[2].id, 3);
+    assert_eq!(collisions.agents[2].agents_overlaps_count, 0);
+    // The grid covering the navmesh bounds gets coarser past the cells cap.
+    let mut metrics = Metrics::new(MetricsConfig {
+      cell_size: 0.001,
+      ..MetricsConfig::default()
+    })
+    .unwrap();
+    metrics.update(0., 0.1, &agents, &neighborhoods(&agents), &navmesh);
+    let density_grid = &metrics.get_last_step_metrics().unwrap().density_grid;
+    assert_relative_eq!(density_grid.cell_size, 0.002);
+    assert_eq!(density_grid.columns_count, 1000);
+    assert_eq!(density_grid.densities.len(), MAXIMUM_CELLS_COUNT);
+  }
+
+  #[test]
+  fn test_line_flows() {
+    let mut agents = Agents::new();
+    agents.create_agent(Agent::new().position(-0.5, 0.));
+    agents.create_agent(Agent::new().position(0.5, 0.5));
+    agents.create_agent(Agent::new().position(-0.5, 3.));
+    let navmesh = NavmeshBuilder::new().build().unwrap();
+    let mut metrics = Metrics::new(MetricsConfig {
+      measurement_lines: vec![MeasurementLine {
+        start: [0., 2.],
+        end: [0., -2.],
+      }],
+      ..MetricsConfig::default()
+    })
+    .unwrap();
+    metrics.update(0., 0.5, &agents, &neighborhoods(&agents), &navmesh);
+    assert_eq!(
+      metrics.get_last_step_metrics().unwrap().line_flows[0].total_forward_crossings_count,
+      0
+    );
+    // The first agent crosses forward, the second backward and the third
+    // passes beside the line.
+    agents.set_positions(&[Vec2::new(0.5, 0.), Vec2::new(-0.5, 0.5), Vec2::new(0.5, 3.)]);
+    metrics.update(0.5, 0.5, &agents, &neighborhoods(&agents), &navmesh);
+    let line_flow = metrics.get_last_step_metrics().unwrap().line_flows[0];
+    assert_eq!(line_flow.forward_crossings_count, 1);
+    assert_eq!(line_flow.backward_crossings_count, 1);
+    assert_relative_eq!(line_flow.flow, 1.);
+    metrics.update(1., 0.5, &agents, &neighborhoods(&agents), &navmesh);
+    let line_flow = metrics.get_last_step_metrics().unwrap().line_flows[0];
+    assert_eq!(line_flow.forward_crossings_count, 0);
+    assert_eq!(line_flow.total_forward_crossings_count, 1);
+    assert_eq!(line_flow.total_backward_crossings_count, 1);
+  }
+}
@@ -1,16 +1,18 @@
+use crate::error::Error;
 use crate::vec2::Vec2;
-use approx::{abs_diff_eq, abs_diff_ne};
+use approx::abs_diff_eq;
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
-use std::io::{Result, Write};
+use std::io::{self, Write};
 use std::vec::Vec;
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
 enum CellEdge {
   Direct(usize),
   Indirect(usize),
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Navmesh {
   vertices: Vec<Vec2>,
   edges_vertices: Vec<[usize; 2]>,
@@ -64,8 +66,14 @@ impl Navmesh {
       None => false,
     }
   }
-  fn _locate(&self, position: &Vec2, current_cell_index: usize) -> Option<usize> {
-    let (belongs_to_current_cell, mut candidate_cells_index) =
+  fn _locate(
+    &self,
+    position: &Vec2,
+    current_cell_index: usize,
+    visited_cells_index: &mut HashSet<usize>,
+  ) -> Option<usize> {
+    visited_cells_index.insert(current_cell_index);
+    let (belongs_to_current_cell, candidate_cells_index) =
       self.cells_edges[current_cell_index].iter().fold(
         (true, HashSet::new()),
         |(mut belongs_to_current_cell, mut candidates), &cell_edge| {
@@ -102,10 +110,15 @@ impl Navmesh {
     if belongs_to_current_cell {
       Some(current_cell_index)
     } else {
-      candidate_cells_index.remove(&current_cell_index);
-      candidate_cells_index
-        .iter()
-        .find_map(|&cell_index| self.locate(position, Some(cell_index)))
+      // Skipping the visited cells, otherwise positions out of the navmesh
+      // would bounce back and forth between neighbor cells.
+      candidate_cells_index.iter().find_map(|&cell_index| {
+        if visited_cells_index.contains(&cell_index) {
+          None
+        } else {
+          self._locate(position, cell_index, visited_cells_index)
+        }
+      })
     }
   }
 
@@ -113,20 +126,127 @@ impl Navmesh {
   pub fn locate(&self, position: &Vec2, origin_cell_index: Option<usize>) -> Option<usize> {
     if self.cells_edges.is_empty() {
       None
+    } else if let Some(cell_index) =
+      origin_cell_index.filter(|&cell_index| self.is_belonging_to_cell(cell_index, position))
+    {
+      // Most positions stay in the same cell from one step to the next.
+      Some(cell_index)
     } else {
-      self._locate(position, origin_cell_index.unwrap_or(0))
+      self._locate(
+        position,
+        origin_cell_index
+          .filter(|&cell_index| cell_index < self.cells_edges.len())
+          .unwrap_or(0),
+        &mut HashSet::new(),
+      )
     }
   }
 
-  pub fn render_to_obj<W: Write>(&self, mut output: W) -> Result<()> {
-    self.vertices.iter().try_for_each::<_, Result<()>>(|&v| {
-      writeln!(&mut output, "v {:.3} {:.3} 0.0", v.x(), v.y())?;
-      Ok(())
-    })?;
+  // Distance from a position to the closest boundary edge, if closer than
+  // `maximum_distance`.
+  //
+  // From a position in the given cell, only the cells within reach are walked
+  // through. Without a cell, every boundary edge is checked.
+  pub fn get_boundary_distance(
+    &self,
+    position: &Vec2,
+    cell_index: Option<usize>,
+    maximum_distance: f64,
+  ) -> Option<f64> {
+    let edge_distance = |edge_index: usize| {
+      let [v1_idx, v2_idx] = self.edges_vertices[edge_index];
+      distance_to_segment(*position, self.vertices[v1_idx], self.vertices[v2_idx])
+    };
+    let is_boundary = |edge_index: usize| {
+      let [left_cell, right_cell] = self.edges_cells[edge_index];
+      left_cell.is_none() || right_cell.is_none()
+    };
+    let closest = |closest: Option<f64>, distance: f64| {
+      Some(closest.map_or(distance, |closest| closest.min(distance)))
+    };
+    let cell_index = match cell_index {
+      Some(cell_index) => cell_index,
+      None => {
+        return (0..self.edges_vertices.len())
+          .filter(|&edge_index| is_boundary(edge_index))
+          .map(edge_distance)
+          .filter(|&distance| distance < maximum_distance)
+          .fold(None, closest)
+      }
+    };
+    let mut visited_cells_index = vec![cell_index];
+    let mut candidate_cells_index = vec![cell_index];
+    let mut boundary_distance = None;
+    while let Some(current_cell_index) = candidate_cells_index.pop() {
+      for &cell_edge in &self.cells_edges[current_cell_index] {
+        let edge_index = match cell_edge {
+          CellEdge::Direct(edge_index) => edge_index,
+          CellEdge::Indirect(edge_index) => edge_index,
+        };
+        let distance = edge_distance(edge_index);
+        if distance >= maximum_distance {
+          continue;
+        }
+        match self.edges_cells[edge_index] {
+          [Some(left_cell), Some(right_cell)] => {
+            let next_cell_index = if left_cell == current_cell_index {
+              right_cell
+            } else {
+              left_cell
+            };
+            if !visited_cells_index.contains(&next_cell_index) {
+              visited_cells_index.push(next_cell_index);
+              candidate_cells_index.push(next_cell_index);
+            }
+          }
+          _ => boundary_distance = closest(boundary_distance, distance),
+        }
+      }
+    }
+    boundary_distance
+  }
+
+  // Edges bordering a single cell, i.e. the walls of the navigable area.
+  pub fn get_boundary_edges(&self) -> Vec<(Vec2, Vec2)> {
+    self
+      .edges_vertices
+      .iter()
+      .zip(self.edges_cells.iter())
+      .filter(|(_, cells)| cells[0].is_none() || cells[1].is_none())
+      .map(|(&[v1, v2], _)| (self.vertices[v1], self.vertices[v2]))
+      .collect()
+  }
+
+  // Bounding box of the navigable area, as [min_x, min_y, max_x, max_y].
+  pub fn get_bounds(&self) -> Option<[f64; 4]> {
+    if self.vertices.is_empty() {
+      return None;
+    }
+    Some(self.vertices.iter().fold(
+      [f64::MAX, f64::MAX, f64::MIN, f64::MIN],
+      |[min_x, min_y, max_x, max_y], vertex| {
+        [
+          min_x.min(vertex.x()),
+          min_y.min(vertex.y()),
+          max_x.max(vertex.x()),
+          max_y.max(vertex.y()),
+        ]
+      },
+    ))
+  }
+
+  pub fn render_to_obj<W: Write>(&self, mut output: W) -> io::Result<()> {
+    self
+      .vertices
+      .iter()
+      .try_for_each::<_, io::Result<()>>(|&v| {
+        writeln!(&mut output, "v {:.3} {:.3} 0.0", v.x(), v.y())?;
+        Ok(())
+      })?;
     self
       .cells_edges
       .iter()
-      .try_for_each::<_, Result<()>>(|&c| {
+      .try_for_each::<_, io::Result<()>>(|&c| {
         let get_cell_vertex = |index| match c[index] {
           CellEdge::Direct(edge) => self.edges_vertices[edge][0],
           CellEdge::Indirect(edge) => self.edges_vertices[edge][1],
@@ -144,7 +264,18 @@ impl Navmesh {
   }
 }
 
-#[derive(Debug)]
+fn distance_to_segment(position: Vec2, v1: Vec2, v2: Vec2) -> f64 {
+  let edge = v2 - v1;
+  let sqr_length = edge.sqr_norm();
+  let u = if sqr_length > 0. {
+    ((position - v1) * edge / sqr_length).clamp(0., 1.)
+  } else {
+    0.
+  };
+  (position - (v1 + u * edge)).norm()
+}
+
+#[derive(Debug, Default)]
 pub struct NavmeshBuilder {
   cells: Vec<(Vec2, Vec2, Vec2)>,
 }
@@ -156,7 +287,6 @@ impl NavmeshBuilder {
 
   pub fn add_cell(mut self, p1: Vec2, p2: Vec2, p3: Vec2) -> Self {
     let det = Vec2::det(p2 - p1, p3 - p1);
-    debug_assert!(abs_diff_ne!(det, 0.), "Can't add a sliver triangle cell.");
     if det > 0. {
       self.cells.push((p1, p2, p3));
     } else {
@@ -165,7 +295,19 @@ impl NavmeshBuilder {
     self
   }
 
-  pub fn build(&self) -> Navmesh {
+  // Build the navmesh, failing on sliver cells and on overlapping cells.
+  pub fn build(&self) -> Result<Navmesh, Error> {
+    if let Some(cell_index) = self
+      .cells
+      .iter()
+      .position(|&(p1, p2, p3)| abs_diff_eq!(Vec2::det(p2 - p1, p3 - p1), 0.))
+    {
+      return Err(Error::InvalidNavmesh(format!(
+        "cell {} is a sliver triangle",
+        cell_index
+      )));
+    }
+
     let mut vertices = Vec::new();
 
     let mut add_vertex = |&position: &Vec2| match vertices
@@ -195,45 +337,50 @@ impl NavmeshBuilder {
           edges_vertices.push(edge);
           if cell_on_left {
             edges_cells.push([Some(cell), None]);
-            CellEdge::Direct(index)
+            Ok(CellEdge::Direct(index))
           } else {
             edges_cells.push([None, Some(cell)]);
-            CellEdge::Indirect(index)
+            Ok(CellEdge::Indirect(index))
           }
         }
         Some(index) => {
+          // An edge borders at most one cell on each side.
+          let side = if cell_on_left { 0 } else { 1 };
+          if let Some(other_cell) = edges_cells[index][side] {
+            return Err(Error::InvalidNavmesh(format!(
+              "cells {} and {} overlap",
+              other_cell, cell
+            )));
+          }
+          edges_cells[index][side] = Some(cell);
           if cell_on_left {
-            debug_assert!(edges_cells[index][0].is_none());
-            edges_cells[index][0] = Some(cell);
-            CellEdge::Direct(index)
+            Ok(CellEdge::Direct(index))
           } else {
-            debug_assert!(edges_cells[index][1].is_none());
-            edges_cells[index][1] = Some(cell);
-            CellEdge::Indirect(index)
+            Ok(CellEdge::Indirect(index))
           }
         }
       }
     };
 
     let mut cells_edges = Vec::new();
-    self.cells.iter().for_each(|(p1, p2, p3)| {
+    for (p1, p2, p3) in &self.cells {
       let v1 = add_vertex(p1);
       let v2 = add_vertex(p2);
       let v3 = add_vertex(p3);
       let cell_index = cells_edges.len();
       cells_edges.push([
-        add_edge(v1, v2, cell_index),
-        add_edge(v2, v3, cell_index),
-        add_edge(v3, v1, cell_index),
+        add_edge(v1, v2, cell_index)?,
+        add_edge(v2, v3, cell_index)?,
+        add_edge(v3, v1, cell_index)?,
       ]);
-    });
+    }
 
-    Navmesh {
+    Ok(Navmesh {
       vertices,
       edges_vertices,
       edges_cells,
       cells_edges,
-    }
+    })
   }
 }
 
@@ -248,9 +395,13 @@ mod tests {
     let navmesh = NavmeshBuilder::new()
       .add_cell(Vec2::new(0., 0.), Vec2::new(1., 0.), Vec2::new(1., 1.))
       .add_cell(Vec2::new(0., 0.), Vec2::new(1., 1.), Vec2::new(0., 1.))
-      .build();
+      .build()
+      .unwrap();
 
     assert_eq!(navmesh.vertices.len(), 4);
+    assert_eq!(navmesh.get_bounds(), Some([0., 0., 1., 1.]));
+    // Every edge but the diagonal.
+    assert_eq!(navmesh.get_boundary_edges().len(), 4);
     navmesh
       .vertices
       .iter()
@@ -322,7 +473,8 @@ mod tests {
     let navmesh = NavmeshBuilder::new()
       .add_cell(Vec2::new(0., 0.), Vec2::new(1., 0.), Vec2::new(1., 1.))
       .add_cell(Vec2::new(0., 0.), Vec2::new(0., 1.), Vec2::new(1., 1.))
-      .build();
+      .build()
+      .unwrap();
 
     assert!(navmesh.is_belonging_to_cell(0, &Vec2::new(0.5, 0.1)));
     assert!(!navmesh.is_belonging_to_cell(1, &Vec2::new(0.5, 0.1)));
@@ -345,7 +497,8 @@ mod tests {
     let navmesh = NavmeshBuilder::new()
       .add_cell(Vec2::new(-5., -5.), Vec2::new(5., -5.), Vec2::new(-5., 5.))
       .add_cell(Vec2::new(5., 5.), Vec2::new(5., -5.), Vec2::new(-5., 5.))
-      .build();
+      .build()
+      .unwrap();
 
     assert_eq!(navmesh.locate(&Vec2::new(-2., -2.), None), Some(0));
     assert_eq!(navmesh.locate(&Vec2::new(2., 2.), None), Some(1));
@@ -355,9 +508,54 @@ mod tests {
 
     assert_eq!(navmesh.locate(&Vec2::new(10., 0.), Some(0)), None);
     assert_eq!(navmesh.locate(&Vec2::new(10., 0.), Some(1)), None);
+    // Beyond the diagonal's ends, both cells are candidates of one another.
+    assert_eq!(navmesh.locate(&Vec2::new(-10., 10.), Some(0)), None);
+    assert_eq!(navmesh.locate(&Vec2::new(10., -10.), Some(1)), None);
 
     assert_eq!(navmesh.locate(&Vec2::new(-1., 1.), Some(0)), Some(0));
     assert_eq!(navmesh.locate(&Vec2::new(-1., 1.), Some(1)), Some(1));
+
+    // Unknown cells are ignored.
+    assert_eq!(navmesh.locate(&Vec2::new(2., 2.), Some(7)), Some(1));
+  }
+
+  #[test]
+  fn test_get_boundary_distance() {
+    let navmesh = NavmeshBuilder::new()
+      .add_cell(Vec2::new(0., 0.), Vec2::new(4., 0.), Vec2::new(4., 4.))
+      .add_cell(Vec2::new(0., 0.), Vec2::new(4., 4.), Vec2::new(0., 4.))
+      .build()
+      .unwrap();
+    let position = Vec2::new(2.5, 1.);
+    let cell = navmesh.locate(&position, None);
+    assert_eq!(cell, Some(0));
+    assert_relative_eq!(
+      navmesh.get_boundary_distance(&position, cell, 2.).unwrap(),
+      1.
+    );
+    assert_eq!(navmesh.get_boundary_distance(&position, cell, 0.5), None);
+    assert_relative_eq!(
+      navmesh
+        .get_boundary_distance(&Vec2::new(5., 2.), None, f64::INFINITY)
+        .unwrap(),
+      1.
+    );
+
+    let navmesh = NavmeshBuilder::new()
+      .add_cell(Vec2::new(0., 0.), Vec2::new(4., 0.), Vec2::new(4., 2.))
+      .add_cell(Vec2::new(0., 0.), Vec2::new(4., 2.), Vec2::new(0., 2.))
+      .build()
+      .unwrap();
+    let position = Vec2::new(3., 1.45);
+    assert_eq!(navmesh.locate(&position, None), Some(0));
+    // The closest boundary edge is reached through the other cell.
+    assert_relative_eq!(
+      navmesh
+        .get_boundary_distance(&position, Some(0), 1.2)
+        .unwrap(),
+      0.55,
+      epsilon = 1e-9
+    );
   }
 
   #[test]
@@ -367,7 +565,8 @@ mod tests {
       .add_cell(Vec2::new(0., 0.), Vec2::new(1., 1.), Vec2::new(0., 1.))
       .add_cell(Vec2::new(1., 0.), Vec2::new(2., 0.), Vec2::new(2., 1.))
       .add_cell(Vec2::new(1., 0.), Vec2::new(2., 1.), Vec2::new(1., 1.))
-      .build();
+      .build()
+      .unwrap();
 
     let mut output = Vec::new();
     assert!(navmesh.render_to_obj(&mut Cursor::new(&mut output)).is_ok());
@@ -385,4 +584,24 @@ mod tests {
       f 2 6 3\n"
     );
   }
+
+  #[test]
+  fn test_build_invalid() {
+    assert_eq!(
+      NavmeshBuilder::new()
+        .add_cell(Vec2::new(0., 0.), Vec2::new(1., 0.), Vec2::new(1., 1.))
+        .add_cell(Vec2::new(0., 0.), Vec2::new(1., 1.), Vec2::new(2., 2.))
+        .build(),
+      Err(Error::InvalidNavmesh(
+        "cell 1 is a sliver triangle".to_string()
+      ))
+    );
+    assert_eq!(
+      NavmeshBuilder::new()
+        .add_cell(Vec2::new(0., 0.), Vec2::new(1., 0.), Vec2::new(1., 1.))
+        .add_cell(Vec2::new(0., 0.), Vec2::new(1., 0.), Vec2::new(0.5, 2.))
+        .build(),
+      Err(Error::InvalidNavmesh("cells 0 and 1 overlap".to_string()))
+    );
+  }
 }
@@ -1,30 +1,47 @@
+use super::agent::AgentKind;
+use super::group::Group;
 use super::vec2::Vec2;
+use crate::par_izip;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 
 use itertools::izip;
 
+// Number of neighbors considered for the avoidance.
+pub const MAXIMUM_NEIGHBORS_COUNT: usize = 10;
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct AgentNeighborhood {
   neighbors_len: usize,
-  neighbors_positions: [Vec2; 10],
-  neighbors_velocities: [Vec2; 10],
-  neighbors_radii: [f64; 10],
-  neighbors_distances: [f64; 10],
+  neighbors_indices: [usize; MAXIMUM_NEIGHBORS_COUNT],
+  neighbors_positions: [Vec2; MAXIMUM_NEIGHBORS_COUNT],
+  neighbors_velocities: [Vec2; MAXIMUM_NEIGHBORS_COUNT],
+  neighbors_radii: [f64; MAXIMUM_NEIGHBORS_COUNT],
+  neighbors_distances: [f64; MAXIMUM_NEIGHBORS_COUNT],
+  neighbors_responsibilities: [f64; MAXIMUM_NEIGHBORS_COUNT],
 }
 
 impl AgentNeighborhood {
   fn new() -> Self {
     AgentNeighborhood {
       neighbors_len: 0,
-      neighbors_positions: [Vec2::new(0., 0.); 10],
-      neighbors_velocities: [Vec2::new(0., 0.); 10],
-      neighbors_radii: [super::agent::DEFAULT_RADIUS; 10],
-      neighbors_distances: [0.0; 10],
+      neighbors_indices: [0; MAXIMUM_NEIGHBORS_COUNT],
+      neighbors_positions: [Vec2::new(0., 0.); MAXIMUM_NEIGHBORS_COUNT],
+      neighbors_velocities: [Vec2::new(0., 0.); MAXIMUM_NEIGHBORS_COUNT],
+      neighbors_radii: [super::agent::DEFAULT_RADIUS; MAXIMUM_NEIGHBORS_COUNT],
+      neighbors_distances: [0.0; MAXIMUM_NEIGHBORS_COUNT],
+      neighbors_responsibilities: [0.5; MAXIMUM_NEIGHBORS_COUNT],
     }
   }
   #[allow(dead_code)]
   pub fn neighbors_len(&self) -> usize {
     self.neighbors_len
   }
+  // Indices of the neighbors among the agents the neighborhood was computed
+  // for.
+  pub fn get_neighbors_indices(&self) -> &[usize] {
+    &self.neighbors_indices[0..self.neighbors_len]
+  }
   pub fn get_neighbors_positions(&self) -> &[Vec2] {
     &self.neighbors_positions[0..self.neighbors_len]
   }
@@ -38,55 +55,128 @@ impl AgentNeighborhood {
   pub fn get_neighbors_distances(&self) -> &[f64] {
     &self.neighbors_distances[0..self.neighbors_len]
   }
+  // Share of the avoidance the agent is responsible for, for each neighbor.
+  pub fn get_neighbors_responsibilities(&self) -> &[f64] {
+    &self.neighbors_responsibilities[0..self.neighbors_len]
+  }
+  // Compute the neighborhood of every agent, its closest agents sorted by
+  // distance.
+  #[allow(clippy::too_many_arguments)]
   pub fn compute_agents_neighborhood(
     agent_positions: &[Vec2],
     agent_velocities: &[Vec2],
     agent_radii: &[f64],
-  ) -> Vec<Self> {
-    agent_positions
-      .iter()
-      .map(|&agent_position| {
-        let mut agent_neighborhood = AgentNeighborhood::new();
-
-        // Retrieve all the neighbot agents and compute their distance to the current agent
-        let mut neighbor_agents = izip!(
+    agent_kinds: &[AgentKind],
+    agent_arrived: &[bool],
+    agent_group_ids: &[Option<usize>],
+    groups: &[Group],
+    neighborhoods: &mut [Self],
+  ) {
+    par_izip!(
+      0..agent_positions.len(),
+      agent_positions,
+      agent_arrived,
+      agent_group_ids,
+      neighborhoods
+    )
+    .for_each(
+      |(idx_agent, &agent_position, &arrived, &agent_group_id, agent_neighborhood)| {
+        // Closest other agents, sorted by distance. Agents at the same distance
+        // are kept in their index order.
+        let mut closest_agents =
+          [(Vec2::default(), Vec2::default(), 0., 0., 0., 0); MAXIMUM_NEIGHBORS_COUNT];
+        let mut closest_agents_len = 0;
+
+        izip!(
+          0..agent_positions.len(),
           agent_positions.iter(),
           agent_velocities.iter(),
-          agent_radii.iter()
+          agent_radii.iter(),
+          agent_kinds.iter(),
+          agent_arrived.iter(),
+          agent_group_ids.iter()
         )
+        // Excluded by index rather than by position, agents can be coincident.
+        .filter(|neighbor| neighbor.0 != idx_agent)
         .map(
-          |(&neighbor_position, &neighbor_velocity, &neighbor_radius)| {
+          |(
+            idx_neighbor,
+            &neighbor_position,
+            &neighbor_velocity,
+            &neighbor_radius,
+            &neighbor_kind,
+            &neighbor_arrived,
+            &neighbor_group_id,
+          )| {
+            let responsibility = match (neighbor_kind, arrived, neighbor_arrived) {
+              // Arrived agents hold their spot, moving neighbors take care of all of it.
+              (AgentKind::Navigating, true, false) => 0.,
+              (AgentKind::Navigating, false, true) => 1.0,
+              // Reciprocal avoidance, each agent takes care of half of it.
+              (AgentKind::Navigating, _, _) => 0.5,
+              // Scripted agents don't avoid, the agent takes care of all of it.
+              (AgentKind::Scripted, _, _) => 1.0,
+            };
             (
               neighbor_position,
               neighbor_velocity,
               neighbor_radius,
               (neighbor_position - agent_position).norm(),
+              match (agent_group_id, neighbor_group_id) {
+                // Members of the same group are less inclined to avoid each other.
+                (Some(agent_group), Some(neighbor_group)) if agent_group == neighbor_group => {
+                  responsibility * groups[agent_group].avoidance_factor
+                }
+                _ => responsibility,
+              },
+              idx_neighbor,
             )
           },
         )
-        .collect::<Vec<(Vec2, Vec2, f64, f64)>>();
-
-        // Sort by distance
-        neighbor_agents.sort_by(|a, b| a.3.partial_cmp(&b.3).unwrap());
+        .for_each(|neighbor_agent| {
+          let idx_insert = closest_agents[..closest_agents_len]
+            .iter()
+            .position(|closest_agent| closest_agent.3 > neighbor_agent.3)
+            .unwrap_or(closest_agents_len);
+          if idx_insert < MAXIMUM_NEIGHBORS_COUNT {
+            // Shift the farther agents, dropping the last one when full.
+            let idx_last = closest_agents_len.min(MAXIMUM_NEIGHBORS_COUNT - 1);
+            closest_agents.copy_within(idx_insert..idx_last, idx_insert + 1);
+            closest_agents[idx_insert] = neighbor_agent;
+            closest_agents_len = (closest_agents_len + 1).min(MAXIMUM_NEIGHBORS_COUNT);
+          }
+        });
 
+        *agent_neighborhood = AgentNeighborhood::new();
         izip!(
           agent_neighborhood.neighbors_positions.iter_mut(),
           agent_neighborhood.neighbors_velocities.iter_mut(),
           agent_neighborhood.neighbors_radii.iter_mut(),
           agent_neighborhood.neighbors_distances.iter_mut(),
-          neighbor_agents.iter().skip(1).take(10)
+          agent_neighborhood.neighbors_responsibilities.iter_mut(),
+          agent_neighborhood.neighbors_indices.iter_mut(),
+          closest_agents[..closest_agents_len].iter()
         )
-        .for_each(|(position, velocity, radius, distance, &input)| {
-          *position = input.0;
-          *velocity = input.1;
-          *radius = input.2;
-          *distance = input.3;
-        });
+        .for_each(
+          |(position, velocity, radius, distance, responsibility, index, &input)| {
+            *position = input.0;
+            *velocity = input.1;
+            *radius = input.2;
+            *distance = input.3;
+            *responsibility = input.4;
+            *index = input.5;
+          },
+        );
 
-        agent_neighborhood.neighbors_len = std::cmp::min(neighbor_agents.len() - 1, 10);
-        agent_neighborhood
-      })
-      .collect()
+        agent_neighborhood.neighbors_len = closest_agents_len;
+      },
+    );
+  }
+}
+
+impl Default for AgentNeighborhood {
+  fn default() -> Self {
+    AgentNeighborhood::new()
   }
 }
 
@@ -94,9 +184,11 @@ impl AgentNeighborhood {
 mod tests {
   use super::super::agent::Agent;
   use super::super::agents::Agents;
+  use super::super::trajectory::Trajectory;
   use super::*;
 
   #[test]
+  #[allow(clippy::useless_vec)]
   fn test_compute_agents_neighborhood() {
     let mut agents = Agents::new();
     agents.create_agent(Agent::new().position(1.0, 1.0));
@@ -105,10 +197,16 @@ mod tests {
     agents.create_agent(Agent::new().position(4.0, -4.0));
     assert_eq!(agents.len(), 4);
 
-    let agents_neighborhood = AgentNeighborhood::compute_agents_neighborhood(
+    let mut agents_neighborhood = vec![AgentNeighborhood::default(); agents.len()];
+    AgentNeighborhood::compute_agents_neighborhood(
       agents.get_positions(),
       agents.get_velocities(),
       agents.get_radii(),
+      agents.get_kinds(),
+      agents.get_arrived(),
+      agents.get_group_ids(),
+      agents.get_groups(),
+      &mut agents_neighborhood,
     );
     assert_eq!(agents_neighborhood[0].neighbors_len(), 3);
     assert_eq!(agents_neighborhood[1].neighbors_len(), 3);
@@ -128,5 +226,144 @@ mod tests {
       agents_neighborhood[0].get_neighbors_radii().iter(),
       vec![0.35, 0.35, 0.35].iter(),
     );
+    itertools::assert_equal(
+      agents_neighborhood[0].get_neighbors_indices().iter(),
+      [1, 2, 3].iter(),
+    );
+    itertools::assert_equal(
+      agents_neighborhood[0]
+        .get_neighbors_responsibilities()
+        .iter(),
+      [0.5, 0.5, 0.5].iter(),
+    );
+  }
+
+  #[test]
+  fn test_compute_agents_neighborhood_grouped() {
+    let mut agents = Agents::new();
+    let group = agents.create_group(Group::new().avoidance_factor(0.2));
+    agents.create_agent(Agent::new().position(0.0, 0.0).group(group));
+    agents.create_agent(Agent::new().position(1.0, 0.0).group(group));
+    agents.create_agent(Agent::new().position(0.0, 2.0));
+
+    let mut agents_neighborhood = vec![AgentNeighborhood::default(); agents.len()];
+    AgentNeighborhood::compute_agents_neighborhood(
+      agents.get_positions(),
+      agents.get_velocities(),
+      agents.get_radii(),
+      agents.get_kinds(),
+      agents.get_arrived(),
+      agents.get_group_ids(),
+      agents.get_groups(),
+      &mut agents_neighborhood,
+    );
+    itertools::assert_equal(
+      agents_neighborhood[0]
+        .get_neighbors_responsibilities()
+        .iter(),
+      [0.1, 0.5].iter(),
+    );
+    itertools::assert_equal(
+      agents_neighborhood[2]
+        .get_neighbors_responsibilities()
+        .iter(),
+      [0.5, 0.5].iter(),
+    );
+  }
+
+  #[test]
+  fn test_compute_agents_neighborhood_scripted() {
+    let mut agents = Agents::new();
+    agents.create_agent(Agent::new().position(1.0, 1.0));
+    agents.create_agent(
+      Agent::new()
+        .position(-2.0, 2.0)
+        .trajectory(Trajectory::new(&[(0., Vec2::new(-2.0, 2.0))])),
+    );
+    agents.create_agent(Agent::new().position(-3.0, -3.0));
+
+    let mut agents_neighborhood = vec![AgentNeighborhood::default(); agents.len()];
+    AgentNeighborhood::compute_agents_neighborhood(
+      agents.get_positions(),
+      agents.get_velocities(),
+      agents.get_radii(),
+      agents.get_kinds(),
+      agents.get_arrived(),
+      agents.get_group_ids(),
+      agents.get_groups(),
+      &mut agents_neighborhood,
+    );
+    itertools::assert_equal(
+      agents_neighborhood[0]
+        .get_neighbors_responsibilities()
+        .iter(),
+      [1.0, 0.5].iter(),
+    );
+  }
+
+  #[test]
+  fn test_compute_agents_neighborhood_arrived() {
+    let mut agents = Agents::new();
+    agents.create_agent(Agent::new().position(1.0, 1.0));
+    agents.create_agent(Agent::new().position(-2.0, 2.0));
+    agents.create_agent(Agent::new().position(-3.0, -3.0));
+    agents.set_arrived(&[false, true, true]);
+
+    let mut agents_neighborhood = vec![AgentNeighborhood::default(); agents.len()];
+    AgentNeighborhood::compute_agents_neighborhood(
+      agents.get_positions(),
+      agents.get_velocities(),
+      agents.get_radii(),
+      agents.get_kinds(),
+      agents.get_arrived(),
+      agents.get_group_ids(),
+      agents.get_groups(),
+      &mut agents_neighborhood,
+    );
+    itertools::assert_equal(
+      agents_neighborhood[0]
+        .get_neighbors_responsibilities()
+        .iter(),
+      [1.0, 1.0].iter(),
+    );
+    itertools::assert_equal(
+      agents_neighborhood[1]
+        .get_neighbors_responsibilities()
+        .iter(),
+      [0.0, 0.5].iter(),
+    );
+  }
+
+  #[test]
+  fn test_coincident_agents_neighborhood() {
+    let mut agents = Agents::new();
+    agents.create_agent(Agent::new().position(1.0, 1.0));
+    agents.create_agent(Agent::new().position(1.0, 1.0).velocity(1.0, 0.0));
+    agents.create_agent(Agent::new().position(2.0, 1.0));
+
+    let mut agents_neighborhood = vec![AgentNeighborhood::default(); agents.len()];
+    AgentNeighborhood::compute_agents_neighborhood(
+      agents.get_positions(),
+      agents.get_velocities(),
+      agents.get_radii(),
+      agents.get_kinds(),
+      agents.get_arrived(),
+      agents.get_group_ids(),
+      agents.get_groups(),
+      &mut agents_neighborhood,
+    );
+    // Each agent gets the other one, not itself.
+    itertools::assert_equal(
+      agents_neighborhood[0].get_neighbors_velocities().iter(),
+      [Vec2::new(1.0, 0.0), Vec2::new(0.0, 0.0)].iter(),
+    );
+    itertools::assert_equal(
+      agents_neighborhood[1].get_neighbors_velocities().iter(),
+      [Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0)].iter(),
+    );
+    itertools::assert_equal(
+      agents_neighborhood[1].get_neighbors_distances().iter(),
+      [0.0, 1.0].iter(),
+    );
   }
 }
@@ -1,91 +1,148 @@
-use crate::neighborhood::AgentNeighborhood;
+use crate::neighborhood::{AgentNeighborhood, MAXIMUM_NEIGHBORS_COUNT};
 use crate::vec2::Vec2;
 
 use itertools::izip;
 
+// Maximum number of constraints of an agent, one per neighbor and one for its
+// maximum backward speed.
+pub const MAXIMUM_CONSTRAINTS_COUNT: usize = MAXIMUM_NEIGHBORS_COUNT + 1;
+
+// ORCA half planes, as `(origin, direction)`, an agent's velocity must belong
+// to. They are stored inline, like the neighborhood, to avoid allocating.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AgentConstraints {
+  constraints_len: usize,
+  constraints: [(Vec2, Vec2); MAXIMUM_CONSTRAINTS_COUNT],
+}
+
+impl AgentConstraints {
+  pub fn new() -> Self {
+    AgentConstraints {
+      constraints_len: 0,
+      constraints: [(Vec2::default(), Vec2::default()); MAXIMUM_CONSTRAINTS_COUNT],
+    }
+  }
+  #[allow(dead_code)]
+  pub fn constraints_len(&self) -> usize {
+    self.constraints_len
+  }
+  pub fn push(&mut self, constraint: (Vec2, Vec2)) {
+    debug_assert!(self.constraints_len < MAXIMUM_CONSTRAINTS_COUNT);
+    self.constraints[self.constraints_len] = constraint;
+    self.constraints_len += 1;
+  }
+  pub fn get_constraints(&self) -> &[(Vec2, Vec2)] {
+    &self.constraints[0..self.constraints_len]
+  }
+}
+
+impl Default for AgentConstraints {
+  fn default() -> Self {
+    AgentConstraints::new()
+  }
+}
+
+// Compute the ORCA constraints of an agent, one per neighbor.
+//
+// The agent index breaks the symmetry between coincident agents.
+#[allow(clippy::too_many_arguments)]
 pub fn compute_constraints(
-  positions: &[Vec2],
-  desired_velocities: &[Vec2],
-  radii: &[f64],
-  neighborhoods: &[AgentNeighborhood],
+  index: usize,
+  position: Vec2,
+  desired_velocity: Vec2,
+  radius: f64,
+  neighborhood: &AgentNeighborhood,
   time_horizon: f64,
   dt: f64,
-) -> Vec<Vec<(Vec2, Vec2)>> {
+) -> AgentConstraints {
   let inv_time_horizon = 1.0 / time_horizon;
   let inv_dt = 1.0 / dt;
-  izip!(positions, desired_velocities, radii, neighborhoods)
-    .map(
-      move |(&position, &desired_velocity, &radius, &neighborhood)| {
-        izip!(
-          neighborhood.get_neighbors_positions(),
-          neighborhood.get_neighbors_velocities(),
-          neighborhood.get_neighbors_radii()
-        )
-        .map(
-          move |(&neighbor_position, &neighbor_velocity, &neighbor_radius)| {
-            let relative_position = neighbor_position - position;
-            let relative_velocity = neighbor_velocity - desired_velocity;
-            let sqr_dist = relative_position.sqr_norm();
-            let combined_radii = radius + neighbor_radius;
-            let sqr_combined_radii = combined_radii.powi(2);
-            let constraint_dir;
-            let u;
-
-            if sqr_dist <= sqr_combined_radii {
-              // In collision, find u such as desired_velocity + u brings us out of the neighbor
-              let w = -inv_dt * relative_position - relative_velocity;
-              let w_norm = w.norm();
-              let unit_w = w / w_norm;
-
-              constraint_dir = Vec2::new(unit_w.y(), -unit_w.x());
-              u = (combined_radii * inv_dt - w_norm) * unit_w;
-            } else {
-              // No collision, find u such as desired_velocity + u brings us out of the neighbor's velocity obstable
-
-              // Compute the vector from cutoff center to relative velocity
-              let w = -inv_time_horizon * relative_position - relative_velocity;
-              let w_sqr_norm = w.sqr_norm();
-              let dot_product_1 = w * relative_position;
-
-              if dot_product_1 < 0. && dot_product_1.powi(2) > sqr_combined_radii * w_sqr_norm {
-                // Project on cut-off circle.
-                let w_norm = w_sqr_norm.sqrt();
-                let unit_w = w / w_norm;
-
-                constraint_dir = Vec2::new(unit_w.y(), -unit_w.x());
-                u = (combined_radii * inv_time_horizon - w_norm) * unit_w;
-              } else {
-                /* Project on legs. */
-                let leg = (sqr_dist - sqr_combined_radii).sqrt();
-
-                if Vec2::det(relative_position, w) > 0.0 {
-                  /* Project on left leg. */
-                  constraint_dir = Vec2::new(
-                    relative_position.x() * leg - relative_position.y() * combined_radii,
-                    relative_position.x() * combined_radii + relative_position.y() * leg,
-                  ) / sqr_dist;
-                } else {
-                  /* Project on right leg. */
-                  constraint_dir = -Vec2::new(
-                    relative_position.x() * leg + relative_position.y() * combined_radii,
-                    -relative_position.x() * combined_radii + relative_position.y() * leg,
-                  ) / sqr_dist;
-                }
-
-                let dot_product_2 = relative_velocity * constraint_dir;
-                u = dot_product_2 * constraint_dir + relative_velocity;
-              }
-            }
-            (
-              desired_velocity + 0.5 * u, // Take responsability for half of the avoidance.
-              constraint_dir,
-            )
-          },
-        )
-        .collect()
-      },
-    )
-    .collect()
+  let mut constraints = AgentConstraints::new();
+  izip!(
+    neighborhood.get_neighbors_indices(),
+    neighborhood.get_neighbors_positions(),
+    neighborhood.get_neighbors_velocities(),
+    neighborhood.get_neighbors_radii(),
+    neighborhood.get_neighbors_responsibilities()
+  )
+  .for_each(
+    |(
+      &neighbor_index,
+      &neighbor_position,
+      &neighbor_velocity,
+      &neighbor_radius,
+      &responsibility,
+    )| {
+      let relative_position = neighbor_position - position;
+      let relative_velocity = neighbor_velocity - desired_velocity;
+      let sqr_dist = relative_position.sqr_norm();
+      let combined_radii = radius + neighbor_radius;
+      let sqr_combined_radii = combined_radii.powi(2);
+      let constraint_dir;
+      let u;
+
+      if sqr_dist <= sqr_combined_radii {
+        // In collision, find u such as desired_velocity + u brings us out of the neighbor
+        let w = -inv_dt * relative_position - relative_velocity;
+        let w_norm = w.norm();
+        let unit_w = if sqr_dist > f64::EPSILON && w_norm > f64::EPSILON {
+          w / w_norm
+        } else {
+          // Coincident agents, get out in opposite directions, the lower
+          // index along the y axis and the higher one against it.
+          if index < neighbor_index {
+            Vec2::new(0., 1.)
+          } else {
+            Vec2::new(0., -1.)
+          }
+        };
+
+        constraint_dir = Vec2::new(unit_w.y(), -unit_w.x());
+        u = (combined_radii * inv_dt - w * unit_w) * unit_w;
+      } else {
+        // No collision, find u such as desired_velocity + u brings us out of the neighbor's velocity obstable
+
+        // Compute the vector from cutoff center to relative velocity
+        let w = -inv_time_horizon * relative_position - relative_velocity;
+        let w_sqr_norm = w.sqr_norm();
+        let dot_product_1 = w * relative_position;
+
+        if dot_product_1 < 0. && dot_product_1.powi(2) > sqr_combined_radii * w_sqr_norm {
+          // Project on cut-off circle.
+          let w_norm = w_sqr_norm.sqrt();
+          let unit_w = w / w_norm;
+
+          constraint_dir = Vec2::new(unit_w.y(), -unit_w.x());
+          u = (combined_radii * inv_time_horizon - w_norm) * unit_w;
+        } else {
+          /* Project on legs. */
+          let leg = (sqr_dist - sqr_combined_radii).sqrt();
+
+          if Vec2::det(relative_position, w) > 0.0 {
+            /* Project on left leg. */
+            constraint_dir = Vec2::new(
+              relative_position.x() * leg - relative_position.y() * combined_radii,
+              relative_position.x() * combined_radii + relative_position.y() * leg,
+            ) / sqr_dist;
+          } else {
+            /* Project on right leg. */
+            constraint_dir = -Vec2::new(
+              relative_position.x() * leg + relative_position.y() * combined_radii,
+              -relative_position.x() * combined_radii + relative_position.y() * leg,
+            ) / sqr_dist;
+          }
+
+          let dot_product_2 = relative_velocity * constraint_dir;
+          u = dot_product_2 * constraint_dir + relative_velocity;
+        }
+      }
+      constraints.push((
+        desired_velocity + responsibility * u, // Take responsability for our share of the avoidance.
+        constraint_dir,
+      ));
+    },
+  );
+  constraints
 }
 
 #[cfg(test)]
@@ -109,28 +166,81 @@ mod tests {
         .velocity(2.0, 2.0)
         .radius(0.5),
     );
-    let agents_neighborhood = AgentNeighborhood::compute_agents_neighborhood(
+    let mut agents_neighborhood = vec![AgentNeighborhood::default(); agents.len()];
+    AgentNeighborhood::compute_agents_neighborhood(
       agents.get_positions(),
       agents.get_velocities(),
       agents.get_radii(),
+      agents.get_kinds(),
+      agents.get_arrived(),
+      agents.get_group_ids(),
+      agents.get_groups(),
+      &mut agents_neighborhood,
     );
 
-    let constraints = compute_constraints(
+    let constraints: Vec<AgentConstraints> = (0..agents.len())
+      .map(|idx_agent| {
+        compute_constraints(
+          idx_agent,
+          agents.get_positions()[idx_agent],
+          agents.get_velocities()[idx_agent],
+          agents.get_radii()[idx_agent],
+          &agents_neighborhood[idx_agent],
+          1.,
+          0.1,
+        )
+      })
+      .collect();
+
+    assert_eq!(constraints[0].constraints_len(), 1);
+    assert_eq!(constraints[1].constraints_len(), 1);
+
+    let (_orca_0_1_ori, orca_0_1_dir) = constraints[0].get_constraints()[0];
+    let (_orca_1_0_ori, orca_1_0_dir) = constraints[1].get_constraints()[0];
+
+    assert_relative_eq!(orca_0_1_dir, -orca_1_0_dir);
+  }
+
+  #[test]
+  fn coincident_agents() {
+    let mut agents = Agents::new();
+    agents.create_agent(Agent::new().position(1.0, 1.0).velocity(1.0, 0.0));
+    agents.create_agent(Agent::new().position(1.0, 1.0).velocity(1.0, 0.0));
+    let mut agents_neighborhood = vec![AgentNeighborhood::default(); agents.len()];
+    AgentNeighborhood::compute_agents_neighborhood(
       agents.get_positions(),
       agents.get_velocities(),
       agents.get_radii(),
-      &agents_neighborhood,
-      1.,
-      0.1,
+      agents.get_kinds(),
+      agents.get_arrived(),
+      agents.get_group_ids(),
+      agents.get_groups(),
+      &mut agents_neighborhood,
     );
 
-    assert_eq!(constraints.len(), agents.len());
-    assert_eq!(constraints[0].len(), 1);
-    assert_eq!(constraints[1].len(), 1);
-
-    let (_orca_0_1_ori, orca_0_1_dir) = constraints[0][0];
-    let (_orca_1_0_ori, orca_1_0_dir) = constraints[1][0];
-
-    assert_relative_eq!(orca_0_1_dir, -orca_1_0_dir);
+    let constraints: Vec<AgentConstraints> = (0..agents.len())
+      .map(|idx_agent| {
+        compute_constraints(
+          idx_agent,
+          agents.get_positions()[idx_agent],
+          agents.get_velocities()[idx_agent],
+          agents.get_radii()[idx_agent],
+          &agents_neighborhood[idx_agent],
+          1.,
+          0.1,
+        )
+      })
+      .collect();
+    assert_eq!(constraints[0].constraints_len(), 1);
+    assert_eq!(constraints[1].constraints_len(), 1);
+    let (origin_0, direction_0) = constraints[0].get_constraints()[0];
+    let (origin_1, direction_1) = constraints[1].get_constraints()[0];
+    // Getting out sideways, in opposite directions.
+    assert_relative_eq!(direction_0, Vec2::new(1.0, 0.0));
+    assert_relative_eq!(direction_1, Vec2::new(-1.0, 0.0));
+    assert_relative_eq!(origin_0.x(), 1.0);
+    assert!(origin_0.y() > 0.0);
+    assert_relative_eq!(origin_1.x(), 1.0);
+    assert!(origin_1.y() < 0.0);
   }
 }
@@ -1,7 +1,12 @@
+use super::compute_constraints::MAXIMUM_CONSTRAINTS_COUNT;
 use crate::vec2::Vec2;
 
 const EPSILON: f64 = 0.000001;
 
+// Number of bisection steps searching for the smallest relaxation of the half
+// planes.
+const RELAXATION_STEPS: usize = 20;
+
 fn is_vector_belonging_to_half_plane(v: &Vec2, (origin, direction): &(Vec2, Vec2)) -> bool {
   Vec2::det(*direction, *v - *origin) >= 0.
 }
@@ -78,7 +83,9 @@ pub fn solve_linear_program_step(
 
       if t_denominator.abs() <= EPSILON {
         //println!("** Hk // Hi");
-        if t_numerator > 0. {
+        // Hk and Hi boundaries can be the same line, don't let rounding errors
+        // make it look like Hk excludes Hi.
+        if t_numerator > EPSILON {
           //println!("*** Hk on the right side of Hi => No solution");
           None
         } else {
@@ -94,7 +101,9 @@ pub fn solve_linear_program_step(
           t_left = t_left.max(t);
         }
         //println!("*** Vi range updated to [{}, {}]", t_left, t_right);
-        if t_left > t_right {
+        // The valid segment can be reduced to a single point, don't let
+        // rounding errors make it empty.
+        if t_left > t_right + EPSILON {
           //println!("*** No valid segment belonging to Hi => No solution");
           None
         } else {
@@ -113,11 +122,14 @@ pub fn solve_linear_program_step(
         let t_h_i_numerator = Vec2::det(*obj_dir, -*h_i_ori);
         let t_h_i_denominator = Vec2::det(*obj_dir, *h_i_dir);
         if t_h_i_denominator.abs() <= EPSILON {
-          // println!(
-          //   "** Vi // Hi, taking the rightmost valid intersection at {}",
-          //   t_h_i_right
-          // );
-          t_h_i_right
+          // Taking the valid intersection the farthest along the objective
+          if *obj_dir * *h_i_dir >= 0. {
+            //println!("** Vi // Hi, taking the rightmost valid intersection at {}", t_h_i_right);
+            t_h_i_right
+          } else {
+            //println!("** Vi // Hi, taking the leftmost valid intersection at {}", t_h_i_left);
+            t_h_i_left
+          }
         } else {
           let t_h_i = t_h_i_numerator / t_h_i_denominator;
           if maximize_norm {
@@ -143,50 +155,104 @@ pub fn solve_linear_program_step(
       };
       let candidate = *h_i_ori + t_h_i * *h_i_dir;
       //println!("** Candidate Vi = {}", candidate);
-      if candidate * *obj_dir < 0.0 {
-        //println!("*£ Candidate Vi goes backward");
-        None
-      } else {
-        //println!("* Candidate Vi validated");
-        Some(candidate)
-      }
+      Some(candidate)
     }
     None => None,
   }
 }
 
+// Why `solve_linear_program` found no solution.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LinearProgramFailure {
+  // No vector satisfies all the constraints within the maximum norm.
+  Infeasible,
+  // An intermediate solution goes backward, against the objective direction,
+  // the search stops there even though the problem may be feasible.
+  Backward,
+}
+
 pub fn solve_linear_program(
   obj_dir: &Vec2,
   obj_max_norm: f64,
   half_planes: &[(Vec2, Vec2)],
   maximize_norm: bool,
-) -> Option<Vec2> {
+) -> Result<Vec2, LinearProgramFailure> {
   half_planes
     .iter()
     .enumerate()
     .try_fold(*obj_dir * obj_max_norm, |v_im1, (i, h_i)| {
       if is_vector_belonging_to_half_plane(&v_im1, h_i) {
         //println!("Vi-1={} ∈ Hi=({}, {}) => Vi = Vi-1", v_im1, h_i.0, h_i.1);
-        Some(v_im1)
+        Ok(v_im1)
       } else {
         //println!("Vi-1={} ∉ Hi=({}, {})", v_im1, h_i.0, h_i.1);
-        solve_linear_program_step(
+        let v_i = solve_linear_program_step(
           obj_dir,
           obj_max_norm,
           h_i,
           &half_planes[0..i],
           maximize_norm,
         )
+        .ok_or(LinearProgramFailure::Infeasible)?;
+        // Don't let rounding errors make a solution orthogonal to the
+        // objective look backward.
+        if v_i * *obj_dir >= -EPSILON {
+          Ok(v_i)
+        } else {
+          Err(LinearProgramFailure::Backward)
+        }
       }
     })
 }
 
+// When the linear program has no solution, relax all the half planes by the
+// smallest distance making it feasible, found by bisection. The solution then
+// penetrates the half planes as little as possible.
+pub fn solve_relaxed_linear_program(
+  obj_dir: &Vec2,
+  obj_max_norm: f64,
+  half_planes: &[(Vec2, Vec2)],
+) -> Vec2 {
+  debug_assert!(half_planes.len() <= MAXIMUM_CONSTRAINTS_COUNT);
+  let mut relaxed_half_planes = [(Vec2::default(), Vec2::default()); MAXIMUM_CONSTRAINTS_COUNT];
+  let relaxed_half_planes = &mut relaxed_half_planes[0..half_planes.len()];
+  // The objective at the maximum norm satisfies the half planes relaxed by
+  // the largest distance from the origin to them plus the maximum norm.
+  let mut relaxation_min = 0.;
+  let mut relaxation_max = obj_max_norm
+    + half_planes
+      .iter()
+      .map(|(origin, direction)| *origin * Vec2::new(-direction.y(), direction.x()))
+      .fold(0., f64::max);
+  let mut solution = *obj_dir * obj_max_norm;
+  (0..RELAXATION_STEPS).for_each(|_| {
+    let relaxation = 0.5 * (relaxation_min + relaxation_max);
+    relaxed_half_planes.iter_mut().zip(half_planes).for_each(
+      |(relaxed_half_plane, &(origin, direction))| {
+        *relaxed_half_plane = (
+          origin - relaxation * Vec2::new(-direction.y(), direction.x()),
+          direction,
+        );
+      },
+    );
+    match solve_linear_program(obj_dir, obj_max_norm, relaxed_half_planes, false) {
+      Ok(relaxed_solution) => {
+        relaxation_max = relaxation;
+        solution = relaxed_solution;
+      }
+      Err(_) => relaxation_min = relaxation,
+    }
+  });
+  solution
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
   use approx::assert_relative_eq;
 
   #[test]
+  #[allow(clippy::bool_assert_comparison)]
   fn test_is_vector_belonging_to_half_plane() {
     assert_eq!(
       is_vector_belonging_to_half_plane(
@@ -219,7 +285,8 @@ mod tests {
     maximize_norm: bool,
     expected_solution: Option<Vec2>,
   ) -> Option<Vec2> {
-    let solution = solve_linear_program(&obj.normalize(), obj.norm(), &half_planes, maximize_norm);
+    let solution =
+      solve_linear_program(&obj.normalize(), obj.norm(), half_planes, maximize_norm).ok();
     match expected_solution {
       Some(expected_valid_solution) => {
         let valid_solution = solution.unwrap();
@@ -246,6 +313,10 @@ mod tests {
     let half_planes = vec![(Vec2::new(2.0, 0.0), Vec2::new(0.0, -1.0))];
     // (1.0, 0.0) can't be extended to belong to the half plane
     check_solve_linear_program(&Vec2::new(1.0, 0.0), &half_planes, false, None);
+    assert_eq!(
+      solve_linear_program(&Vec2::new(1.0, 0.0), 1.0, &half_planes, false),
+      Err(LinearProgramFailure::Infeasible)
+    );
     // (3.0, 0.0) already belongs to the half plane it should be returned as-is
     check_solve_linear_program(
       &Vec2::new(3.0, 0.0),
@@ -326,6 +397,11 @@ mod tests {
     );
     // (-3.0, 0.0) would need to be reversed to belong to the halfplane
     check_solve_linear_program(&Vec2::new(-3.0, 0.0), &half_planes, true, None);
+    // Even though (1.0, 0.0) would satisfy it
+    assert_eq!(
+      solve_linear_program(&Vec2::new(-1.0, 0.0), 3.0, &half_planes, true),
+      Err(LinearProgramFailure::Backward)
+    );
     // (-1.0, -2.0) would need to be reversed to belong to the halfplane
     check_solve_linear_program(
       &Vec2::new(-1.0, -2.0),
@@ -430,4 +506,166 @@ mod tests {
       Some(Vec2::new(1., 1.)),
     );
   }
+  #[test]
+  fn linear_program_opposite_half_planes() {
+    // Both half planes share the same boundary, the solution is on it
+    let half_planes = vec![
+      (Vec2::new(1., 1.), Vec2::new(1., 1.).normalize()),
+      (Vec2::new(-1., -1.), Vec2::new(-1., -1.).normalize()),
+    ];
+    check_solve_linear_program(
+      &Vec2::new(0.1, 0.),
+      &half_planes,
+      false,
+      Some(Vec2::new(0., 0.)),
+    );
+    // The objective is parallel to the boundary, going as far as possible along it
+    let half_planes = vec![
+      (Vec2::new(0., -2.), Vec2::new(1., 0.)),
+      (Vec2::new(-2., -2.), Vec2::new(-1., 0.)),
+    ];
+    check_solve_linear_program(
+      &Vec2::new(3., 0.),
+      &half_planes,
+      false,
+      Some(Vec2::new(5f64.sqrt(), -2.)),
+    );
+    // The valid region is reduced to a single point
+    let mut half_planes = half_planes;
+    half_planes.push((Vec2::new(1., -2.), Vec2::new(1., 1.).normalize()));
+    check_solve_linear_program(
+      &Vec2::new(3., 0.),
+      &half_planes,
+      false,
+      Some(Vec2::new(1., -2.)),
+    );
+  }
+
+  #[test]
+  fn relaxed_linear_program() {
+    // Feasible, the solution is the linear program's
+    let half_planes = vec![(Vec2::new(0., 1.), Vec2::new(-1., 0.))];
+    assert_relative_eq!(
+      solve_relaxed_linear_program(&Vec2::new(1., 0.), 2., &half_planes),
+      solve_linear_program(&Vec2::new(1., 0.), 2., &half_planes, false).unwrap(),
+      epsilon = 0.0001
+    );
+    // Contradictory half planes, y <= 1 and y >= 3, are penetrated evenly
+    let half_planes = vec![
+      (Vec2::new(0., 1.), Vec2::new(-1., 0.)),
+      (Vec2::new(0., 3.), Vec2::new(1., 0.)),
+    ];
+    assert_eq!(
+      solve_linear_program(&Vec2::new(1., 0.), 3., &half_planes, false),
+      Err(LinearProgramFailure::Infeasible)
+    );
+    let solution = solve_relaxed_linear_program(&Vec2::new(1., 0.), 3., &half_planes);
+    assert_relative_eq!(solution.y(), 2., epsilon = 0.0001);
+    assert!(solution.norm() <= 3. + 0.0001);
+  }
+}
+
+// Properties checked on random problems, against a brute force feasibility
+// check.
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod properties {
+  use super::*;
+  use proptest::prelude::*;
+
+  // Tolerance on the constraints satisfaction.
+  const TOLERANCE: f64 = 0.00001;
+
+  fn is_satisfying_constraints(v: &Vec2, obj_max_norm: f64, half_planes: &[(Vec2, Vec2)]) -> bool {
+    v.norm() <= obj_max_norm + TOLERANCE
+      && half_planes
+        .iter()
+        .all(|(origin, direction)| Vec2::det(*direction, *v - *origin) >= -TOLERANCE)
+  }
+
+  // The feasible region is a convex set bounded by the maximum norm circle,
+  // it is empty unless one of its vertices candidates, the intersections of
+  // the constraints boundaries two by two, is feasible.
+  fn is_feasible(obj_dir: &Vec2, obj_max_norm: f64, half_planes: &[(Vec2, Vec2)]) -> bool {
+    let mut candidates = vec![*obj_dir * obj_max_norm];
+    half_planes
+      .iter()
+      .enumerate()
+      .for_each(|(i, (h_i_ori, h_i_dir))| {
+        // With the maximum norm circle.
+        let b = *h_i_ori * *h_i_dir;
+        let det = b.powi(2) - (h_i_ori.sqr_norm() - obj_max_norm.powi(2));
+        if det >= 0. {
+          candidates.push(*h_i_ori + (-b - det.sqrt()) * *h_i_dir);
+          candidates.push(*h_i_ori + (-b + det.sqrt()) * *h_i_dir);
+        }
+        // With the other half planes boundaries.
+        half_planes[i + 1..].iter().for_each(|(h_k_ori, h_k_dir)| {
+          let denominator = Vec2::det(*h_k_dir, *h_i_dir);
+          if denominator.abs() > EPSILON {
+            let t = Vec2::det(*h_k_dir, *h_k_ori - *h_i_ori) / denominator;
+            candidates.push(*h_i_ori + t * *h_i_dir);
+          }
+        });
+      });
+    candidates
+      .iter()
+      .any(|v| is_satisfying_constraints(v, obj_max_norm, half_planes))
+  }
+
+  fn direction() -> impl Strategy<Value = Vec2> {
+    prop_oneof![
+      0.0..2.0 * std::f64::consts::PI,
+      // Few distinct directions, for parallel boundaries.
+      (0..8).prop_map(|i| f64::from(i) * std::f64::consts::FRAC_PI_4),
+    ]
+    .prop_map(|angle| Vec2::new(libm::cos(angle), libm::sin(angle)))
+  }
+
+  fn origin() -> impl Strategy<Value = Vec2> {
+    prop_oneof![
+      (-5.0..5.0, -5.0..5.0),
+      // Few distinct origins, for overlapping boundaries.
+      (-2..3, -2..3).prop_map(|(x, y)| (f64::from(x), f64::from(y))),
+    ]
+    .prop_map(|(x, y)| Vec2::new(x, y))
+  }
+
+  fn half_planes() -> impl Strategy<Value = Vec<(Vec2, Vec2)>> {
+    prop::collection::vec((origin(), direction()), 1..8)
+  }
+
+  proptest! {
+    #[test]
+    fn solutions_satisfy_constraints(
+      obj_dir in direction(),
+      obj_max_norm in 0.1..5.0,
+      half_planes in half_planes(),
+      maximize_norm in any::<bool>(),
+    ) {
+      if let Ok(v) = solve_linear_program(&obj_dir, obj_max_norm, &half_planes, maximize_norm) {
+        prop_assert!(
+          is_satisfying_constraints(&v, obj_max_norm, &half_planes),
+          "Solution {} doesn't satisfy the constraints",
+          v
+        );
+        prop_assert!(v * obj_dir >= -TOLERANCE, "Solution {} goes backward", v);
+      }
+    }
+
+    #[test]
+    fn no_solution_only_when_infeasible(
+      obj_dir in direction(),
+      obj_max_norm in 0.1..5.0,
+      half_planes in half_planes(),
+      maximize_norm in any::<bool>(),
+    ) {
+      let solution = solve_linear_program(&obj_dir, obj_max_norm, &half_planes, maximize_norm);
+      if solution == Err(LinearProgramFailure::Infeasible) {
+        prop_assert!(
+          !is_feasible(&obj_dir, obj_max_norm, &half_planes),
+          "No solution found for a feasible problem"
+        );
+      }
+    }
+  }
 }
@@ -2,11 +2,19 @@ mod compute_constraints;
 mod linear_program;
 
 pub use compute_constraints::compute_constraints;
+pub use linear_program::{
+  solve_linear_program, solve_relaxed_linear_program, LinearProgramFailure,
+};
 
 //use crate::log;
 use crate::neighborhood::AgentNeighborhood;
+use crate::par_izip;
 use crate::vec2::Vec2;
-use itertools::izip;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+// How far ahead, in seconds, collisions with the neighbors are avoided.
+pub const DEFAULT_TIME_HORIZON: f64 = 5.0;
 
 #[allow(clippy::too_many_arguments)]
 pub fn orca_navigator(
@@ -15,25 +23,52 @@ pub fn orca_navigator(
   desired_velocities: &[Vec2],
   radii: &[f64],
   maximum_speeds: &[f64],
+  maximum_backward_speeds: &[Option<f64>],
   neighborhoods: &[AgentNeighborhood],
   time_horizon: f64,
   dt: f64,
-) -> Vec<Vec2> {
-  izip!(
-    compute_constraints(
-      positions,
-      desired_velocities,
-      radii,
-      neighborhoods,
-      time_horizon,
-      dt
-    ),
+  updated_velocities: &mut [Vec2],
+) {
+  par_izip!(
+    0..positions.len(),
+    positions,
     directions,
     desired_velocities,
+    radii,
     maximum_speeds,
+    maximum_backward_speeds,
+    neighborhoods,
+    updated_velocities
   )
-  .map(
-    |(orca_constraints, &direction, &desired_velocity, &maximum_speed)| {
+  .for_each(
+    |(
+      index,
+      &position,
+      &direction,
+      &desired_velocity,
+      &radius,
+      &maximum_speed,
+      &maximum_backward_speed,
+      neighborhood,
+      updated_velocity,
+    )| {
+      let mut orca_constraints = compute_constraints(
+        index,
+        position,
+        desired_velocity,
+        radius,
+        neighborhood,
+        time_horizon,
+        dt,
+      );
+      if let Some(maximum_backward_speed) = maximum_backward_speed {
+        // Keep the velocity component along the body direction above `-maximum_backward_speed`.
+        orca_constraints.push((
+          -maximum_backward_speed * direction,
+          Vec2::new(direction.y(), -direction.x()),
+        ));
+      }
+
       let desired_speed = desired_velocity.norm();
       let desired_direction = if desired_speed < f64::EPSILON {
         direction
@@ -47,39 +82,71 @@ pub fn orca_navigator(
       //   orca_constraints
       // );
 
-      match linear_program::solve_linear_program(
+      *updated_velocity = match solve_linear_program(
         &desired_direction,
         desired_speed,
-        &orca_constraints,
+        orca_constraints.get_constraints(),
         true,
       ) {
-        Some(corrected_velocity) => {
+        Ok(corrected_velocity) => {
           //log!("$$ 1st solve worked -> {}", corrected_velocity);
           corrected_velocity
         }
         // No solution, let's try to accelerate
-        None => {
-          match linear_program::solve_linear_program(
+        Err(_) => {
+          match solve_linear_program(
             &desired_direction,
             maximum_speed,
-            &orca_constraints,
+            orca_constraints.get_constraints(),
             false,
           ) {
-            Some(corrected_velocity) => {
+            Ok(corrected_velocity) => {
               //log!("$$ 2nd solve worked -> {}", corrected_velocity);
               corrected_velocity
             }
-            // No solution, let's continue on our merry way
-            None => {
+            // No solution at all, let's penetrate the constraints as little
+            // as possible
+            Err(LinearProgramFailure::Infeasible) => {
               //log!("$$ No solution found");
-              desired_direction.normalize_to(0.9 * desired_speed)
+              cap_backward_speed(
+                solve_relaxed_linear_program(
+                  &desired_direction,
+                  desired_speed,
+                  orca_constraints.get_constraints(),
+                ),
+                direction,
+                maximum_backward_speed,
+              )
+            }
+            // No solution going forward, let's continue on our merry way
+            Err(LinearProgramFailure::Backward) => {
+              //log!("$$ No forward solution found");
+              cap_backward_speed(
+                desired_direction.normalize_to(0.9 * desired_speed),
+                direction,
+                maximum_backward_speed,
+              )
             }
           }
         }
-      }
+      };
     },
-  )
-  .collect()
+  );
+}
+
+// Remove the part of the velocity going backward, along the body direction,
+// faster than `maximum_backward_speed`.
+fn cap_backward_speed(
+  velocity: Vec2,
+  direction: Vec2,
+  maximum_backward_speed: Option<f64>,
+) -> Vec2 {
+  match maximum_backward_speed {
+    Some(maximum_backward_speed) if velocity * direction < -maximum_backward_speed => {
+      velocity - (velocity * direction + maximum_backward_speed) * direction
+    }
+    _ => velocity,
+  }
 }
 
 #[cfg(test)]
@@ -88,26 +155,41 @@ mod tests {
   use super::super::agents::Agents;
   use super::*;
 
-  #[test]
-  fn test_orca_navigator_no_movement() {
-    let mut agents = Agents::new();
-    agents.create_agent(Agent::new().position(1.0, 0.0));
-    agents.create_agent(Agent::new().position(2.0, 0.0));
-
-    let updated_velocities: Vec<Vec2> = orca_navigator(
+  fn compute_orca_velocities(agents: &Agents, desired_velocities: &[Vec2]) -> Vec<Vec2> {
+    let mut neighborhoods = vec![AgentNeighborhood::default(); agents.len()];
+    AgentNeighborhood::compute_agents_neighborhood(
       agents.get_positions(),
-      agents.get_directions(),
       agents.get_velocities(),
       agents.get_radii(),
+      agents.get_kinds(),
+      agents.get_arrived(),
+      agents.get_group_ids(),
+      agents.get_groups(),
+      &mut neighborhoods,
+    );
+    let mut updated_velocities = vec![Vec2::default(); agents.len()];
+    orca_navigator(
+      agents.get_positions(),
+      agents.get_directions(),
+      desired_velocities,
+      agents.get_radii(),
       agents.get_maximum_speeds(),
-      &AgentNeighborhood::compute_agents_neighborhood(
-        agents.get_positions(),
-        agents.get_velocities(),
-        agents.get_radii(),
-      ),
+      agents.get_maximum_backward_speeds(),
+      &neighborhoods,
       10.,
       0.5,
+      &mut updated_velocities,
     );
+    updated_velocities
+  }
+
+  #[test]
+  fn test_orca_navigator_no_movement() {
+    let mut agents = Agents::new();
+    agents.create_agent(Agent::new().position(1.0, 0.0));
+    agents.create_agent(Agent::new().position(2.0, 0.0));
+
+    let updated_velocities = compute_orca_velocities(&agents, agents.get_velocities());
     itertools::assert_equal(
       updated_velocities,
       vec![Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0)],
@@ -120,20 +202,7 @@ mod tests {
     agents.create_agent(Agent::new().position(1.0, 0.0).velocity(0.0, -1.0));
     agents.create_agent(Agent::new().position(2.0, 0.0).velocity(0.0, 1.0));
 
-    let updated_velocities: Vec<Vec2> = orca_navigator(
-      agents.get_positions(),
-      agents.get_directions(),
-      agents.get_velocities(),
-      agents.get_radii(),
-      agents.get_maximum_speeds(),
-      &AgentNeighborhood::compute_agents_neighborhood(
-        agents.get_positions(),
-        agents.get_velocities(),
-        agents.get_radii(),
-      ),
-      10.,
-      0.5,
-    );
+    let updated_velocities = compute_orca_velocities(&agents, agents.get_velocities());
     itertools::assert_equal(
       updated_velocities,
       vec![Vec2::new(0.0, -1.0), Vec2::new(0.0, 1.0)],
@@ -146,21 +215,40 @@ mod tests {
     agents.create_agent(Agent::new().position(0.0, 0.0).velocity(1.0, 0.0));
     agents.create_agent(Agent::new().position(1.0, 0.0).velocity(-1.0, 0.0));
 
-    orca_navigator(
-      agents.get_positions(),
-      agents.get_directions(),
-      agents.get_velocities(),
-      agents.get_radii(),
-      agents.get_maximum_speeds(),
-      &AgentNeighborhood::compute_agents_neighborhood(
-        agents.get_positions(),
-        agents.get_velocities(),
-        agents.get_radii(),
-      ),
-      10.,
-      0.5,
-    );
+    compute_orca_velocities(&agents, agents.get_velocities());
 
     // No assertion, let's just make sure everything works fine
   }
+
+  #[test]
+  fn test_orca_navigator_maximum_backward_speed() {
+    let mut agents = Agents::new();
+    agents.create_agent(
+      Agent::new()
+        .position(0.0, 0.0)
+        .direction(1.0, 0.0)
+        .maximum_backward_speed(0.5),
+    );
+
+    let updated_velocities = compute_orca_velocities(&agents, &[Vec2::new(-2.0, 0.0)]);
+    // The agent doesn't walk backward faster than allowed
+    assert!(updated_velocities[0] * agents.get_directions()[0] >= -0.5 - 1e-6);
+  }
+
+  #[test]
+  fn test_cap_backward_speed() {
+    let direction = Vec2::new(1.0, 0.0);
+    assert_eq!(
+      cap_backward_speed(Vec2::new(-2.0, 1.0), direction, Some(0.5)),
+      Vec2::new(-0.5, 1.0)
+    );
+    assert_eq!(
+      cap_backward_speed(Vec2::new(-0.2, 1.0), direction, Some(0.5)),
+      Vec2::new(-0.2, 1.0)
+    );
+    assert_eq!(
+      cap_backward_speed(Vec2::new(-2.0, 1.0), direction, None),
+      Vec2::new(-2.0, 1.0)
+    );
+  }
 }
@@ -0,0 +1,161 @@
+use super::vec2::Vec2;
+use serde::{Deserialize, Serialize};
+use std::vec::Vec;
+
+pub const DEFAULT_QUEUE_SPACING: f64 = 0.8;
+pub const DEFAULT_SERVICE_TIME: f64 = 2.0;
+
+// A service point where agents line up before being served.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Queue {
+  // Where the agent being served stands.
+  pub position: Vec2,
+  // Unit vector along which the queue line extends, from the service point.
+  pub direction: Vec2,
+  // Distance between two consecutive agents in the queue.
+  pub spacing: f64,
+  // Indices of the queued agents, the first one is the head.
+  members: Vec<usize>,
+  // Time at which the service of the head started, if it has.
+  service_start_time: Option<f64>,
+}
+
+// Queues are copied at every step, reuse the memory of the members.
+impl Clone for Queue {
+  fn clone(&self) -> Self {
+    Queue {
+      position: self.position,
+      direction: self.direction,
+      spacing: self.spacing,
+      members: self.members.clone(),
+      service_start_time: self.service_start_time,
+    }
+  }
+  fn clone_from(&mut self, source: &Self) {
+    self.position = source.position;
+    self.direction = source.direction;
+    self.spacing = source.spacing;
+    self.members.clone_from(&source.members);
+    self.service_start_time = source.service_start_time;
+  }
+}
+
+impl Queue {
+  pub fn new(x: f64, y: f64) -> Self {
+    Queue {
+      position: Vec2::new(x, y),
+      direction: Vec2::new(1., 0.),
+      spacing: DEFAULT_QUEUE_SPACING,
+      members: Vec::new(),
+      service_start_time: None,
+    }
+  }
+
+  pub fn direction(mut self, x: f64, y: f64) -> Self {
+    self.direction = Vec2::new(x, y);
+    let norm = self.direction.norm();
+    if norm < f64::EPSILON {
+      self.direction = Vec2::new(1., 0.);
+    } else {
+      self.direction /= norm
+    }
+    self
+  }
+
+  pub fn spacing(mut self, spacing: f64) -> Self {
+    self.spacing = spacing;
+    self
+  }
+
+  pub fn get_members(&self) -> &[usize] {
+    &self.members
+  }
+
+  // Position of the given rank in the queue line, 0 being the head.
+  pub fn slot(&self, rank: usize) -> Vec2 {
+    self.position + (rank as f64 * self.spacing) * self.direction
+  }
+
+  // Position where arriving agents join the queue.
+  pub fn tail(&self) -> Vec2 {
+    self.slot(self.members.len())
+  }
+
+  pub fn rank(&self, idx_agent: usize) -> Option<usize> {
+    self.members.iter().position(|&member| member == idx_agent)
+  }
+
+  pub fn join(&mut self, idx_agent: usize) {
+    debug_assert!(self.rank(idx_agent).is_none());
+    self.members.push(idx_agent);
+  }
+
+  // Forget about a removed agent, shifting the indices of the following ones.
+  pub fn remove_agent(&mut self, idx_agent: usize) {
+    if self.rank(idx_agent) == Some(0) {
+      self.service_start_time = None;
+    }
+    self.members.retain(|&member| member != idx_agent);
+    self.members.iter_mut().for_each(|member| {
+      if *member > idx_agent {
+        *member -= 1;
+      }
+    });
+  }
+
+  // Start the service of the head, if needed, and release it once served
+  // after its own service time.
+  //
+  // Returns the index of the released agent.
+  pub fn serve(&mut self, head_position: Vec2, head_service_time: f64, time: f64) -> Option<usize> {
+    match self.service_start_time {
+      None => {
+        if !self.members.is_empty() && (head_position - self.position).norm() < 0.5 * self.spacing {
+          self.service_start_time = Some(time);
+        }
+        None
+      }
+      Some(service_start_time) => {
+        if time - service_start_time >= head_service_time {
+          self.service_start_time = None;
+          Some(self.members.remove(0))
+        } else {
+          None
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use approx::assert_relative_eq;
+
+  #[test]
+  fn test_slots() {
+    let mut queue = Queue::new(1.0, 2.0).direction(0.0, -2.0).spacing(0.5);
+    assert_relative_eq!(queue.tail(), Vec2::new(1.0, 2.0));
+    queue.join(3);
+    queue.join(1);
+    assert_relative_eq!(queue.slot(1), Vec2::new(1.0, 1.5));
+    assert_relative_eq!(queue.tail(), Vec2::new(1.0, 1.0));
+    assert_eq!(queue.rank(1), Some(1));
+    assert_eq!(queue.rank(2), None);
+  }
+
+  #[test]
+  fn test_serve() {
+    let mut queue = Queue::new(0.0, 0.0);
+    queue.join(3);
+    queue.join(1);
+    // The head is not at the service point yet.
+    assert_eq!(queue.serve(Vec2::new(2.0, 0.0), 2.0, 0.0), None);
+    // The head reaches the service point.
+    assert_eq!(queue.serve(Vec2::new(0.1, 0.0), 2.0, 1.0), None);
+    assert_eq!(queue.serve(Vec2::new(0.0, 0.0), 2.0, 2.5), None);
+    // The head is served and leaves the queue.
+    assert_eq!(queue.serve(Vec2::new(0.0, 0.0), 2.0, 3.0), Some(3));
+    assert_eq!(queue.get_members(), &[1]);
+  }
+}
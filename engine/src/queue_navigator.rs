@@ -0,0 +1,148 @@
+use super::queue::Queue;
+use super::vec2::Vec2;
+use itertools::izip;
+use std::vec::Vec;
+
+// Update the queues: agents reaching the tail of the queue they are heading
+// to join it, heads are served and released.
+pub fn update_queues(
+  queues: &[Queue],
+  positions: &[Vec2],
+  queue_ids: &[Option<usize>],
+  service_times: &[f64],
+  time: f64,
+  updated_queues: &mut Vec<Queue>,
+  updated_queue_ids: &mut [Option<usize>],
+) {
+  // Reuses the memory of the previously updated queues.
+  queues.clone_into(updated_queues);
+  updated_queue_ids.copy_from_slice(queue_ids);
+  izip!(positions, queue_ids)
+    .enumerate()
+    .for_each(|(idx_agent, (&position, &queue_id))| {
+      if let Some(queue_id) = queue_id {
+        let queue = &mut updated_queues[queue_id];
+        if queue.rank(idx_agent).is_none() && (position - queue.tail()).norm() < queue.spacing {
+          queue.join(idx_agent);
+        }
+      }
+    });
+  updated_queues.iter_mut().for_each(|queue| {
+    let (head_position, head_service_time) = queue
+      .get_members()
+      .first()
+      .map_or((queue.position, 0.), |&idx_head| {
+        (positions[idx_head], service_times[idx_head])
+      });
+    if let Some(idx_served) = queue.serve(head_position, head_service_time, time) {
+      updated_queue_ids[idx_served] = None;
+    }
+  });
+}
+
+// Compute the target of each agent, agents heading to a queue target their
+// place in it, others keep their own target.
+pub fn queue_navigator(
+  targets: &[Vec2],
+  queue_ids: &[Option<usize>],
+  queues: &[Queue],
+  updated_targets: &mut [Vec2],
+) {
+  izip!(targets, queue_ids, updated_targets)
+    .enumerate()
+    .for_each(|(idx_agent, (&target, &queue_id, updated_target))| {
+      *updated_target = match queue_id {
+        Some(queue_id) => {
+          let queue = &queues[queue_id];
+          match queue.rank(idx_agent) {
+            Some(rank) => queue.slot(rank),
+            None => queue.tail(),
+          }
+        }
+        None => target,
+      }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use approx::assert_relative_eq;
+
+  fn update(
+    q: &[Queue],
+    p: &[Vec2],
+    qi: &[Option<usize>],
+    st: &[f64],
+    time: f64,
+  ) -> (Vec<Queue>, Vec<Option<usize>>) {
+    let mut updated_q = Vec::new();
+    let mut updated_qi = vec![None; qi.len()];
+    update_queues(q, p, qi, st, time, &mut updated_q, &mut updated_qi);
+    (updated_q, updated_qi)
+  }
+
+  #[test]
+  fn test_queue_lifecycle() {
+    let q = vec![Queue::new(0.0, 0.0).direction(1.0, 0.0).spacing(1.0)];
+    let st = vec![1.0, 1.0, 1.0];
+    let t = vec![
+      Vec2::new(-5.0, 0.0),
+      Vec2::new(-5.0, 0.0),
+      Vec2::new(5.0, 5.0),
+    ];
+    let qi = vec![Some(0), Some(0), None];
+    let mut updated_t = vec![Vec2::default(); 3];
+
+    // Both agents head to the tail of the empty queue.
+    let p = vec![
+      Vec2::new(3.0, 0.0),
+      Vec2::new(5.0, 0.0),
+      Vec2::new(0.0, 0.0),
+    ];
+    let (q, qi) = update(&q, &p, &qi, &st, 0.0);
+    queue_navigator(&t, &qi, &q, &mut updated_t);
+    itertools::assert_equal(
+      updated_t.iter().copied(),
+      vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(0.0, 0.0),
+        Vec2::new(5.0, 5.0),
+      ],
+    );
+
+    // The first one joins, the second one heads to the new tail.
+    let p = vec![
+      Vec2::new(0.5, 0.0),
+      Vec2::new(3.0, 0.0),
+      Vec2::new(0.0, 0.0),
+    ];
+    let (q, qi) = update(&q, &p, &qi, &st, 1.0);
+    assert_eq!(q[0].get_members(), &[0]);
+    queue_navigator(&t, &qi, &q, &mut updated_t);
+    itertools::assert_equal(
+      updated_t.iter().copied(),
+      vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(5.0, 5.0),
+      ],
+    );
+
+    // The second one joins and the service of the first one starts.
+    let p = vec![
+      Vec2::new(0.0, 0.0),
+      Vec2::new(1.5, 0.0),
+      Vec2::new(0.0, 0.0),
+    ];
+    let (q, qi) = update(&q, &p, &qi, &st, 2.0);
+    assert_eq!(q[0].get_members(), &[0, 1]);
+
+    // The first one is served and goes to its own target, the second one advances.
+    let (q, qi) = update(&q, &p, &qi, &st, 3.0);
+    assert_eq!(q[0].get_members(), &[1]);
+    queue_navigator(&t, &qi, &q, &mut updated_t);
+    assert_relative_eq!(updated_t[0], Vec2::new(-5.0, 0.0));
+    assert_relative_eq!(updated_t[1], Vec2::new(0.0, 0.0));
+  }
+}
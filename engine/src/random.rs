@@ -0,0 +1,102 @@
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_SEED: u64 = 0;
+
+// Seeded pseudo random numbers generator (xoshiro256**).
+//
+// It only relies on integer arithmetic so that the same seed produces the
+// exact same sequence on every target, wasm included.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Random {
+  state: [u64; 4],
+}
+
+impl Random {
+  pub fn new(seed: u64) -> Self {
+    // Expand the seed using splitmix64, as recommended by xoshiro's authors.
+    let mut splitmix_state = seed;
+    let mut state = [0; 4];
+    state.iter_mut().for_each(|s| {
+      splitmix_state = splitmix_state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+      let mut z = splitmix_state;
+      z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+      z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+      *s = z ^ (z >> 31);
+    });
+    Random { state }
+  }
+
+  pub fn next_u64(&mut self) -> u64 {
+    let result = self.state[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+    let t = self.state[1] << 17;
+    self.state[2] ^= self.state[0];
+    self.state[3] ^= self.state[1];
+    self.state[1] ^= self.state[2];
+    self.state[0] ^= self.state[3];
+    self.state[2] ^= t;
+    self.state[3] = self.state[3].rotate_left(45);
+    result
+  }
+
+  // Uniformly distributed in [0, 1[.
+  pub fn next_f64(&mut self) -> f64 {
+    (self.next_u64() >> 11) as f64 * (1. / (1u64 << 53) as f64)
+  }
+
+  // Uniformly distributed in [min, max[.
+  pub fn uniform(&mut self, min: f64, max: f64) -> f64 {
+    min + (max - min) * self.next_f64()
+  }
+}
+
+impl Default for Random {
+  fn default() -> Self {
+    Random::new(DEFAULT_SEED)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_sequence() {
+    // Those values are shared with the wasm tests, to check that both targets
+    // produce the exact same sequence.
+    let mut random = Random::new(42);
+    itertools::assert_equal(
+      (0..3).map(|_| random.next_u64()),
+      vec![
+        1546998764402558742,
+        6990951692964543102,
+        12544586762248559009,
+      ],
+    );
+    let mut random = Random::new(42);
+    itertools::assert_equal(
+      (0..3).map(|_| random.next_f64()),
+      vec![0.08386297105988216, 0.3789802506626686, 0.6800434110281394],
+    );
+  }
+
+  #[test]
+  fn test_same_seed() {
+    let mut random_1 = Random::new(12);
+    let mut random_2 = Random::new(12);
+    let mut random_3 = Random::new(13);
+    let sequence_1: Vec<u64> = (0..10).map(|_| random_1.next_u64()).collect();
+    let sequence_2: Vec<u64> = (0..10).map(|_| random_2.next_u64()).collect();
+    let sequence_3: Vec<u64> = (0..10).map(|_| random_3.next_u64()).collect();
+    assert_eq!(sequence_1, sequence_2);
+    assert_ne!(sequence_1, sequence_3);
+  }
+
+  #[test]
+  fn test_uniform() {
+    let mut random = Random::new(7);
+    (0..1000).for_each(|_| {
+      let value = random.uniform(-2., 3.);
+      assert!((-2. ..3.).contains(&value));
+    });
+  }
+}
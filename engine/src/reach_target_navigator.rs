@@ -1,5 +1,7 @@
 use super::vec2::Vec2;
-use itertools::izip;
+use crate::par_izip;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 
 pub fn reach_target_navigator(
   positions: &[Vec2],
@@ -8,29 +10,46 @@ pub fn reach_target_navigator(
   desired_speeds: &[f64],
   maximum_accelerations: &[f64],
   dt: f64,
-) -> Vec<Vec2> {
-  izip!(
+  desired_velocities: &mut [Vec2],
+) {
+  par_izip!(
     positions,
     velocities,
     targets,
     desired_speeds,
-    maximum_accelerations
+    maximum_accelerations,
+    desired_velocities
   )
-  .map(
-    move |(&position, &velocity, &target, &desired_speed, &maximum_acceleration)| {
-      let desired_velocity = (target - position).cap_norm(desired_speed);
+  .for_each(
+    move |(
+      &position,
+      &velocity,
+      &target,
+      &desired_speed,
+      &maximum_acceleration,
+      updated_desired_velocity,
+    )| {
+      let to_target = target - position;
+      let distance = to_target.norm();
+      // Slow down soon enough to stop at the target without overshooting it,
+      // the highest speed from which decelerating at the maximum acceleration
+      // at each time step covers exactly the remaining distance.
+      let speed_step = maximum_acceleration * dt;
+      let braking_speed =
+        (speed_step * ((0.25 + 2. * distance / (speed_step * dt)).sqrt() - 0.5)).min(distance / dt);
+      let desired_velocity = to_target.normalize_to(desired_speed.min(braking_speed));
       let desired_acceleration = (desired_velocity - velocity) / dt;
       let clamped_acceleration = desired_acceleration.cap_norm(maximum_acceleration);
 
-      velocity + dt * clamped_acceleration
+      *updated_desired_velocity = velocity + dt * clamped_acceleration;
     },
-  )
-  .collect()
+  );
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
+  use approx::assert_relative_eq;
 
   #[test]
   fn test_reach_target() {
@@ -51,7 +70,8 @@ mod tests {
     ];
     let ds = vec![2.0, 2.0, 2.0];
     let ma = vec![10.0, 1.0, 1.0];
-    let updated_v: Vec<Vec2> = reach_target_navigator(&p, &v, &t, &ds, &ma, 0.5);
+    let mut updated_v = vec![Vec2::default(); v.len()];
+    reach_target_navigator(&p, &v, &t, &ds, &ma, 0.5, &mut updated_v);
     itertools::assert_equal(
       updated_v,
       vec![
@@ -68,7 +88,40 @@ mod tests {
     let t = vec![Vec2::new(1.0, 10.0)];
     let ds = vec![2.0];
     let ma = vec![10.0];
-    let updated_v: Vec<Vec2> = reach_target_navigator(&p, &v, &t, &ds, &ma, 0.5);
+    let mut updated_v = vec![Vec2::default(); v.len()];
+    reach_target_navigator(&p, &v, &t, &ds, &ma, 0.5, &mut updated_v);
     itertools::assert_equal(updated_v, vec![Vec2::new(0.0, 2.0)])
   }
+  #[test]
+  fn test_reach_target_braking() {
+    let p = vec![Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0)];
+    let v = vec![Vec2::new(2.0, 0.0), Vec2::new(0.0, 0.0)];
+    let t = vec![Vec2::new(1.0, 0.0), Vec2::new(0.0, 0.0)];
+    let ds = vec![2.0, 2.0];
+    let ma = vec![1.0, 1.0];
+    let mut updated_v = vec![Vec2::default(); v.len()];
+    reach_target_navigator(&p, &v, &t, &ds, &ma, 0.5, &mut updated_v);
+    // Braking, within the acceleration limit, to stop on the target
+    assert_relative_eq!(updated_v[0], Vec2::new(1.5, 0.0));
+    // Staying still on the target
+    assert_relative_eq!(updated_v[1], Vec2::new(0.0, 0.0));
+  }
+  #[test]
+  fn test_reach_target_stops_on_target() {
+    let mut p = vec![Vec2::new(0.0, 0.0)];
+    let mut v = vec![Vec2::new(0.0, 0.0)];
+    let t = vec![Vec2::new(3.0, 4.0)];
+    let ds = vec![2.0];
+    let ma = vec![3.0];
+    (0..40).for_each(|_| {
+      let mut updated_v = vec![Vec2::default(); 1];
+      reach_target_navigator(&p, &v, &t, &ds, &ma, 0.1, &mut updated_v);
+      v = updated_v;
+      p = vec![p[0] + 0.1 * v[0]];
+      // Never noticeably overshooting the target
+      assert!(p[0].x() <= 3.0 + 0.01);
+    });
+    assert_relative_eq!(p[0], t[0], epsilon = 0.001);
+    assert_relative_eq!(v[0], Vec2::new(0.0, 0.0), epsilon = 0.01);
+  }
 }
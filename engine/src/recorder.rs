@@ -0,0 +1,284 @@
+use super::agent::AgentKind;
+use super::agents::Agents;
+use super::trajectory::Trajectory;
+use super::vec2::Vec2;
+use itertools::izip;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::vec::Vec;
+
+// Tolerance used when comparing the simulated time to the sampling instants.
+const SAMPLING_TIME_EPSILON: f64 = 1e-9;
+
+pub const CSV_HEADER: &str = "time,id,x,y,vx,vy,dx,dy,radius";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AgentState {
+  // Navigating toward its target.
+  Walking,
+  // Lined up in a queue.
+  Queued,
+  // Arrived at its target.
+  Arrived,
+  // Following its prescribed trajectory.
+  Scripted,
+}
+
+impl AgentState {
+  fn as_str(self) -> &'static str {
+    match self {
+      AgentState::Walking => "Walking",
+      AgentState::Queued => "Queued",
+      AgentState::Arrived => "Arrived",
+      AgentState::Scripted => "Scripted",
+    }
+  }
+
+  pub fn parse(value: &str) -> Option<Self> {
+    match value {
+      "Walking" => Some(AgentState::Walking),
+      "Queued" => Some(AgentState::Queued),
+      "Arrived" => Some(AgentState::Arrived),
+      "Scripted" => Some(AgentState::Scripted),
+      _ => None,
+    }
+  }
+}
+
+// State of one agent at one sampling instant, flattened to ease the analysis
+// of the exported files.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Sample {
+  pub time: f64,
+  pub id: usize,
+  pub x: f64,
+  pub y: f64,
+  pub vx: f64,
+  pub vy: f64,
+  pub dx: f64,
+  pub dy: f64,
+  pub radius: f64,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub state: Option<AgentState>,
+}
+
+// Periodically samples the agents of a running simulation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Recorder {
+  // Simulated time between two samples, every step is sampled when zero.
+  sampling_period: f64,
+  record_state: bool,
+  next_sample_time: f64,
+  samples: Vec<Sample>,
+}
+
+impl Recorder {
+  pub fn new() -> Self {
+    Recorder {
+      sampling_period: 0.,
+      record_state: false,
+      next_sample_time: 0.,
+      samples: Vec::new(),
+    }
+  }
+
+  pub fn sampling_period(mut self, sampling_period: f64) -> Self {
+    debug_assert!(sampling_period >= 0.);
+    self.sampling_period = sampling_period;
+    self
+  }
+
+  pub fn record_state(mut self, record_state: bool) -> Self {
+    self.record_state = record_state;
+    self
+  }
+
+  #[allow(dead_code)]
+  pub fn get_samples(&self) -> &[Sample] {
+    &self.samples
+  }
+
+  // Drop the recorded samples and start again from the given time.
+  pub fn reset(&mut self, time: f64) {
+    self.samples.clear();
+    self.next_sample_time = time;
+  }
+
+  // Sample the agents if a sampling instant has been reached.
+  pub fn record(&mut self, time: f64, agents: &Agents) {
+    if time + SAMPLING_TIME_EPSILON < self.next_sample_time {
+      return;
+    }
+    if self.sampling_period > 0. {
+      while self.next_sample_time <= time + SAMPLING_TIME_EPSILON {
+        self.next_sample_time += self.sampling_period;
+      }
+    }
+    let record_state = self.record_state;
+    self.samples.extend(
+      izip!(
+        agents.get_ids(),
+        agents.get_positions(),
+        agents.get_velocities(),
+        agents.get_directions(),
+        agents.get_radii(),
+        agents.get_kinds(),
+        agents.get_queue_ids(),
+        agents.get_arrived()
+      )
+      .map(
+        |(&id, position, velocity, direction, &radius, &kind, queue_id, &arrived)| Sample {
+          time,
+          id,
+          x: position.x(),
+          y: position.y(),
+          vx: velocity.x(),
+          vy: velocity.y(),
+          dx: direction.x(),
+          dy: direction.y(),
+          radius,
+          state: if record_state {
+            Some(match (kind, queue_id, arrived) {
+              (AgentKind::Scripted, _, _) => AgentState::Scripted,
+              (_, Some(_), _) => AgentState::Queued,
+              (_, _, true) => AgentState::Arrived,
+              _ => AgentState::Walking,
+            })
+          } else {
+            None
+          },
+        },
+      ),
+    );
+  }
+
+  // One line per sample, preceded by a header line.
+  pub fn to_csv(&self) -> String {
+    let mut output = String::from(CSV_HEADER);
+    if self.record_state {
+      output.push_str(",state");
+    }
+    output.push('\n');
+    self.samples.iter().for_each(|sample| {
+      output.push_str(&format!(
+        "{},{},{},{},{},{},{},{},{}",
+        sample.time,
+        sample.id,
+        sample.x,
+        sample.y,
+        sample.vx,
+        sample.vy,
+        sample.dx,
+        sample.dy,
+        sample.radius
+      ));
+      if let Some(state) = sample.state {
+        output.push(',');
+        output.push_str(state.as_str());
+      }
+      output.push('\n');
+    });
+    output
+  }
+
+  // Recorded trajectory of each agent, sorted by agent id.
+  pub fn to_trajectories(&self) -> Vec<(usize, Trajectory)> {
+    self
+      .samples
+      .iter()
+      .fold(BTreeMap::new(), |mut trajectories, sample| {
+        trajectories
+          .entry(sample.id)
+          .or_insert_with(Vec::new)
+          .push((sample.time, Vec2::new(sample.x, sample.y)));
+        trajectories
+      })
+      .into_iter()
+      .map(|(id, samples)| (id, Trajectory::new(&samples)))
+      .collect()
+  }
+
+  // One JSON object per line and per sample.
+  pub fn to_json_lines(&self) -> String {
+    self
+      .samples
+      .iter()
+      .map(|sample| serde_json::to_string(sample).unwrap() + "\n")
+      .collect()
+  }
+}
+
+impl Default for Recorder {
+  fn default() -> Self {
+    Recorder::new()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::agent::Agent;
+  use crate::queue::Queue;
+
+  fn create_agents() -> Agents {
+    let mut agents = Agents::new();
+    let queue = agents.create_queue(Queue::new(0., 0.));
+    agents.create_agent(Agent::new().position(1.0, 2.0).velocity(0.5, 0.));
+    agents.create_agent(Agent::new().position(-1.0, 0.5).queue(queue));
+    agents
+  }
+
+  #[test]
+  fn test_sampling_period() {
+    let agents = create_agents();
+    let mut recorder = Recorder::new().sampling_period(0.5);
+    (0..11).for_each(|step| recorder.record(step as f64 * 0.1, &agents));
+    itertools::assert_equal(
+      recorder.get_samples().iter().map(|sample| sample.time),
+      vec![0., 0., 0.5, 0.5, 1., 1.],
+    );
+    recorder.reset(2.);
+    recorder.record(1.9, &agents);
+    assert!(recorder.get_samples().is_empty());
+    recorder.record(2.0, &agents);
+    assert_eq!(recorder.get_samples().len(), 2);
+  }
+
+  #[test]
+  fn test_to_trajectories() {
+    let mut agents = create_agents();
+    let mut recorder = Recorder::new();
+    recorder.record(0., &agents);
+    agents.set_positions(&[Vec2::new(2.0, 2.0), Vec2::new(-1.0, 1.5)]);
+    recorder.record(1., &agents);
+    let trajectories = recorder.to_trajectories();
+    assert_eq!(trajectories.len(), 2);
+    assert_eq!(trajectories[1].0, 1);
+    assert_eq!(trajectories[1].1.position_at(0.5), Vec2::new(-1.0, 1.0));
+  }
+
+  #[test]
+  fn test_to_csv() {
+    let agents = create_agents();
+    let mut recorder = Recorder::new().record_state(true);
+    recorder.record(0., &agents);
+    assert_eq!(
+      recorder.to_csv(),
+      "time,id,x,y,vx,vy,dx,dy,radius,state\n\
+      0,0,1,2,0.5,0,1,0,0.35,Walking\n\
+      0,1,-1,0.5,0,0,1,0,0.35,Queued\n"
+    );
+  }
+
+  #[test]
+  fn test_to_json_lines() {
+    let agents = create_agents();
+    let mut recorder = Recorder::new();
+    recorder.record(0.25, &agents);
+    assert_eq!(
+      recorder.to_json_lines(),
+      "{\"time\":0.25,\"id\":0,\"x\":1.0,\"y\":2.0,\"vx\":0.5,\"vy\":0.0,\"dx\":1.0,\"dy\":0.0,\"radius\":0.35}\n\
+      {\"time\":0.25,\"id\":1,\"x\":-1.0,\"y\":0.5,\"vx\":0.0,\"vy\":0.0,\"dx\":1.0,\"dy\":0.0,\"radius\":0.35}\n"
+    );
+  }
+}
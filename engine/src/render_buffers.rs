@@ -0,0 +1,104 @@
+use super::agents::Agents;
+use std::vec::Vec;
+
+// Agents state laid out in flat `f32` columns, 2D vectors as consecutive
+// `x, y` values, meant to be read from JS as typed arrays over the wasm
+// memory.
+//
+// The columns are rewritten in place, they only move in memory when the
+// number of agents grows beyond what they ever held.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RenderBuffers {
+  ids: Vec<u32>,
+  positions: Vec<f32>,
+  directions: Vec<f32>,
+  velocities: Vec<f32>,
+  radii: Vec<f32>,
+}
+
+impl RenderBuffers {
+  pub fn new() -> Self {
+    RenderBuffers::default()
+  }
+
+  pub fn update(&mut self, agents: &Agents) {
+    self.ids.clear();
+    self
+      .ids
+      .extend(agents.get_ids().iter().map(|&id| id as u32));
+    self.positions.clear();
+    self.positions.extend(
+      agents
+        .get_positions()
+        .iter()
+        .flat_map(|position| [position.x() as f32, position.y() as f32]),
+    );
+    self.directions.clear();
+    self.directions.extend(
+      agents
+        .get_directions()
+        .iter()
+        .flat_map(|direction| [direction.x() as f32, direction.y() as f32]),
+    );
+    self.velocities.clear();
+    self.velocities.extend(
+      agents
+        .get_velocities()
+        .iter()
+        .flat_map(|velocity| [velocity.x() as f32, velocity.y() as f32]),
+    );
+    self.radii.clear();
+    self
+      .radii
+      .extend(agents.get_radii().iter().map(|&radius| radius as f32));
+  }
+
+  pub fn get_ids(&self) -> &[u32] {
+    &self.ids
+  }
+  pub fn get_positions(&self) -> &[f32] {
+    &self.positions
+  }
+  pub fn get_directions(&self) -> &[f32] {
+    &self.directions
+  }
+  pub fn get_velocities(&self) -> &[f32] {
+    &self.velocities
+  }
+  pub fn get_radii(&self) -> &[f32] {
+    &self.radii
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::agent::Agent;
+
+  #[test]
+  fn test_update() {
+    let mut agents = Agents::new();
+    agents.create_agent(Agent::new().position(1.0, 2.0).direction(0.0, 1.0));
+    agents.create_agent(
+      Agent::new()
+        .position(-3.0, 4.0)
+        .velocity(0.5, 0.0)
+        .radius(0.3),
+    );
+    let mut buffers = RenderBuffers::new();
+    buffers.update(&agents);
+    assert_eq!(buffers.get_ids(), &[0, 1]);
+    assert_eq!(buffers.get_positions(), &[1.0, 2.0, -3.0, 4.0]);
+    assert_eq!(&buffers.get_directions()[0..2], &[0.0, 1.0]);
+    assert_eq!(&buffers.get_velocities()[2..4], &[0.5, 0.0]);
+    assert_eq!(buffers.get_radii(), &[0.35, 0.3]);
+
+    // Columns are reused as long as they are large enough
+    let positions_ptr = buffers.get_positions().as_ptr();
+    agents.remove_agent(0);
+    buffers.update(&agents);
+    assert_eq!(buffers.get_ids(), &[1]);
+    assert_eq!(buffers.get_positions(), &[-3.0, 4.0]);
+    assert_eq!(buffers.get_positions().as_ptr(), positions_ptr);
+  }
+}
@@ -0,0 +1,362 @@
+use super::agent::Agent;
+use super::agent_debug_info::AgentDebugInfo;
+use super::error::Error;
+use super::recorder::{AgentState, Sample, CSV_HEADER};
+use super::utils;
+use wasm_bindgen::prelude::*;
+
+use std::vec::Vec;
+
+// All the samples recorded at the same time.
+#[derive(Clone, Debug, PartialEq)]
+struct Frame {
+  time: f64,
+  samples: Vec<Sample>,
+}
+
+// Plays back recorded trajectories, exposing the same rendering API as
+// `Universe`.
+#[wasm_bindgen]
+pub struct Replay {
+  frames: Vec<Frame>,
+  frame_index: usize,
+  // Playback time, between the current frame's time and the next one's.
+  time: f64,
+}
+
+// Public methods w/ js bindings.
+#[wasm_bindgen]
+impl Replay {
+  pub fn new() -> Replay {
+    utils::set_panic_hook();
+
+    Replay {
+      frames: Vec::new(),
+      frame_index: 0,
+      time: 0.,
+    }
+  }
+  pub fn load_csv(&mut self, data: &str) -> Result<(), JsValue> {
+    let samples = parse_csv(data)?;
+    self.load_samples(samples);
+    Ok(())
+  }
+  pub fn load_json_lines(&mut self, data: &str) -> Result<(), JsValue> {
+    let samples = parse_json_lines(data)?;
+    self.load_samples(samples);
+    Ok(())
+  }
+  pub fn count_frames(&self) -> usize {
+    self.frames.len()
+  }
+  pub fn get_frame_index(&self) -> usize {
+    self.frame_index
+  }
+  pub fn get_time(&self) -> f64 {
+    self.time
+  }
+  pub fn get_start_time(&self) -> f64 {
+    self.frames.first().map_or(0., |frame| frame.time)
+  }
+  pub fn get_end_time(&self) -> f64 {
+    self.frames.last().map_or(0., |frame| frame.time)
+  }
+  // Move the playback to the last frame recorded at or before the given time.
+  pub fn seek(&mut self, time: f64) {
+    let time = time.max(self.get_start_time()).min(self.get_end_time());
+    self.frame_index = self
+      .frames
+      .iter()
+      .position(|frame| frame.time > time)
+      .unwrap_or(self.frames.len())
+      .saturating_sub(1);
+    self.time = time;
+  }
+  pub fn seek_frame(&mut self, frame_index: usize) {
+    if let Some(frame) = self.frames.get(frame_index) {
+      self.frame_index = frame_index;
+      self.time = frame.time;
+    }
+  }
+  // Play back the given elapsed real time, returns the number of frames
+  // skipped forward.
+  pub fn advance(&mut self, real_dt: f64) -> usize {
+    let previous_frame_index = self.frame_index;
+    self.seek(self.time + real_dt.max(0.));
+    self.frame_index - previous_frame_index
+  }
+  // Go to the next frame, if any.
+  pub fn step(&mut self) {
+    self.seek_frame(self.frame_index + 1);
+  }
+  pub fn render_agents(&self) -> Box<[f64]> {
+    self
+      .current_samples()
+      .iter()
+      .flat_map(|sample| {
+        vec![
+          sample.x,
+          sample.y,
+          sample.dx,
+          sample.dy,
+          sample.vx,
+          sample.vy,
+          sample.radius,
+        ]
+      })
+      .collect::<Vec<f64>>()
+      .into_boxed_slice()
+  }
+  pub fn render_debug_info(&self, idx_agent: usize) -> Result<String, JsValue> {
+    Ok(self.try_render_debug_info(idx_agent)?)
+  }
+
+  pub fn count_agents(&self) -> usize {
+    self.current_samples().len()
+  }
+}
+
+// Public methods, Rust only.
+impl Replay {
+  // Render the debug information of the agent at the given index in the
+  // current frame, JSON encoded.
+  pub fn try_render_debug_info(&self, idx_agent: usize) -> Result<String, Error> {
+    let samples = self.current_samples();
+    let sample = samples.get(idx_agent).ok_or(Error::InvalidAgentIndex {
+      index: idx_agent,
+      agents_count: samples.len(),
+    })?;
+    // Targets aren't recorded, the agent is shown as heading where it stands.
+    let agent = Agent::new()
+      .position(sample.x, sample.y)
+      .direction(sample.dx, sample.dy)
+      .velocity(sample.vx, sample.vy)
+      .target(sample.x, sample.y);
+    Ok(AgentDebugInfo::new().agent(agent).render())
+  }
+  pub fn load_samples(&mut self, mut samples: Vec<Sample>) {
+    samples.sort_by(|a, b| a.time.total_cmp(&b.time));
+    self.frames = Vec::new();
+    samples
+      .into_iter()
+      .for_each(|sample| match self.frames.last_mut() {
+        Some(frame) if frame.time == sample.time => frame.samples.push(sample),
+        _ => self.frames.push(Frame {
+          time: sample.time,
+          samples: vec![sample],
+        }),
+      });
+    self.frame_index = 0;
+    self.time = self.get_start_time();
+  }
+}
+
+// Private methods
+impl Replay {
+  fn current_samples(&self) -> &[Sample] {
+    self
+      .frames
+      .get(self.frame_index)
+      .map_or(&[], |frame| &frame.samples)
+  }
+}
+
+impl Default for Replay {
+  fn default() -> Replay {
+    Replay::new()
+  }
+}
+
+// Parse samples exported by `Recorder::to_csv`.
+pub fn parse_csv(data: &str) -> Result<Vec<Sample>, Error> {
+  let mut lines = data.lines().enumerate();
+  let header = lines.next().map_or("", |(_, line)| line.trim());
+  let has_state = if header == CSV_HEADER {
+    false
+  } else if header == format!("{},state", CSV_HEADER) {
+    true
+  } else {
+    return Err(Error::InvalidData(format!(
+      "Unexpected CSV header \"{}\"",
+      header
+    )));
+  };
+  lines
+    .filter(|(_, line)| !line.trim().is_empty())
+    .map(|(idx_line, line)| {
+      let parse_error =
+        |field: &str| Error::InvalidData(format!("Invalid {} at line {}", field, idx_line + 1));
+      let fields: Vec<&str> = line.trim().split(',').collect();
+      if fields.len() != if has_state { 10 } else { 9 } {
+        return Err(parse_error("number of fields"));
+      }
+      let parse_f64 = |idx: usize, field: &str| {
+        fields[idx]
+          .parse::<f64>()
+          .ok()
+          .filter(|value| value.is_finite())
+          .ok_or_else(|| parse_error(field))
+      };
+      Ok(Sample {
+        time: parse_f64(0, "time")?,
+        id: fields[1].parse::<usize>().map_err(|_| parse_error("id"))?,
+        x: parse_f64(2, "x")?,
+        y: parse_f64(3, "y")?,
+        vx: parse_f64(4, "vx")?,
+        vy: parse_f64(5, "vy")?,
+        dx: parse_f64(6, "dx")?,
+        dy: parse_f64(7, "dy")?,
+        radius: parse_f64(8, "radius")?,
+        state: if has_state {
+          Some(AgentState::parse(fields[9]).ok_or_else(|| parse_error("state"))?)
+        } else {
+          None
+        },
+      })
+    })
+    .collect()
+}
+
+// Parse samples exported by `Recorder::to_json_lines`.
+pub fn parse_json_lines(data: &str) -> Result<Vec<Sample>, Error> {
+  data
+    .lines()
+    .enumerate()
+    .filter(|(_, line)| !line.trim().is_empty())
+    .map(|(idx_line, line)| {
+      let sample: Sample = serde_json::from_str(line).map_err(|err| {
+        Error::InvalidData(format!("Invalid sample at line {}: {}", idx_line + 1, err))
+      })?;
+      // JSON has no non-finite numbers but too large ones overflow to infinity.
+      let values = [
+        sample.time,
+        sample.x,
+        sample.y,
+        sample.vx,
+        sample.vy,
+        sample.dx,
+        sample.dy,
+        sample.radius,
+      ];
+      if values.iter().all(|value| value.is_finite()) {
+        Ok(sample)
+      } else {
+        Err(Error::InvalidData(format!(
+          "Non-finite value in the sample at line {}",
+          idx_line + 1
+        )))
+      }
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::Universe;
+  use approx::assert_relative_eq;
+
+  #[test]
+  fn test_parse_csv() {
+    let samples = parse_csv(
+      "time,id,x,y,vx,vy,dx,dy,radius,state\n\
+      0,0,1,2,0.5,0,1,0,0.3,Walking\n\
+      0.5,1,-1,0.5,0,0,1,0,0.25,Queued\n",
+    )
+    .unwrap();
+    assert_eq!(samples.len(), 2);
+    assert_eq!(samples[1].id, 1);
+    assert_relative_eq!(samples[1].time, 0.5);
+    assert_relative_eq!(samples[1].radius, 0.25);
+    assert_eq!(samples[1].state, Some(AgentState::Queued));
+
+    assert!(parse_csv("time,x,y\n0,1,2\n").is_err());
+    assert!(parse_csv("time,id,x,y,vx,vy,dx,dy,radius\n0,0,1,2,0.5,0,1,0\n").is_err());
+    assert!(parse_csv("time,id,x,y,vx,vy,dx,dy,radius\n0,a,1,2,0.5,0,1,0,0.3\n").is_err());
+    assert!(matches!(
+      parse_csv("time,id,x,y,vx,vy,dx,dy,radius\nNaN,0,1,2,0.5,0,1,0,0.3\n"),
+      Err(Error::InvalidData(_))
+    ));
+    assert!(matches!(
+      parse_csv("time,id,x,y,vx,vy,dx,dy,radius\n0,0,inf,2,0.5,0,1,0,0.3\n"),
+      Err(Error::InvalidData(_))
+    ));
+  }
+
+  #[test]
+  fn test_parse_json_lines() {
+    let samples = parse_json_lines(
+      "{\"time\":0.5,\"id\":1,\"x\":-1,\"y\":0.5,\"vx\":0,\"vy\":0,\"dx\":1,\"dy\":0,\"radius\":0.25}\n",
+    )
+    .unwrap();
+    assert_eq!(samples.len(), 1);
+    assert_relative_eq!(samples[0].radius, 0.25);
+
+    assert!(matches!(
+      parse_json_lines(
+        "{\"time\":1e400,\"id\":1,\"x\":-1,\"y\":0.5,\"vx\":0,\"vy\":0,\"dx\":1,\"dy\":0,\"radius\":0.25}\n"
+      ),
+      Err(Error::InvalidData(_))
+    ));
+  }
+
+  #[test]
+  fn test_replay_recording() {
+    let mut universe = Universe::new();
+    universe
+      .try_load_scenario(
+        "{
+        \"scenario\": \"AntipodalCircle\",
+        \"agents_count\": 3,
+        \"radius\": 4.0
+      }",
+      )
+      .unwrap();
+    universe.try_set_timestep(0.25).unwrap();
+    universe.try_start_recording(0., true).unwrap();
+    let rendered_agents: Vec<Box<[f64]>> = (0..8)
+      .map(|_| {
+        let rendered = universe.render_agents();
+        universe.try_step().unwrap();
+        rendered
+      })
+      .collect();
+
+    let mut csv_replay = Replay::new();
+    csv_replay.load_samples(parse_csv(&universe.export_recording_to_csv().unwrap()).unwrap());
+    let mut json_lines_replay = Replay::new();
+    json_lines_replay
+      .load_samples(parse_json_lines(&universe.export_recording_to_json_lines().unwrap()).unwrap());
+    assert_eq!(csv_replay.count_frames(), 9);
+    assert_eq!(json_lines_replay.count_frames(), 9);
+    assert_relative_eq!(csv_replay.get_end_time(), 2.);
+
+    rendered_agents.iter().for_each(|rendered| {
+      assert_eq!(&csv_replay.render_agents(), rendered);
+      assert_eq!(&json_lines_replay.render_agents(), rendered);
+      csv_replay.step();
+      json_lines_replay.step();
+    });
+    assert_eq!(csv_replay.get_frame_index(), 8);
+    // Stays on the last frame
+    csv_replay.step();
+    assert_eq!(csv_replay.get_frame_index(), 8);
+
+    // Seeking
+    csv_replay.seek(0.6);
+    assert_eq!(csv_replay.get_frame_index(), 2);
+    assert_eq!(csv_replay.render_agents(), rendered_agents[2]);
+    assert_eq!(csv_replay.advance(0.2), 1);
+    assert_relative_eq!(csv_replay.get_time(), 0.8);
+    csv_replay.seek(-1.);
+    assert_eq!(csv_replay.get_frame_index(), 0);
+    csv_replay.seek_frame(5);
+    assert_relative_eq!(csv_replay.get_time(), 1.25);
+    assert_eq!(csv_replay.count_agents(), 3);
+    assert!(csv_replay
+      .try_render_debug_info(1)
+      .unwrap()
+      .contains("\"position\""));
+    assert!(csv_replay.try_render_debug_info(3).is_err());
+  }
+}
@@ -0,0 +1,113 @@
+use crate::agent::{
+  Agent, DEFAULT_DESIRED_SPEED, DEFAULT_MAXIMUM_ACCELERATION, DEFAULT_MAXIMUM_SPEED, DEFAULT_RADIUS,
+};
+use crate::distribution::Distribution;
+use crate::error::Error;
+use crate::random::Random;
+use serde::Deserialize;
+
+// Smallest value sampled for the physical attributes, whatever the tails of
+// their random distributions.
+const MINIMUM_PHYSICAL_VALUE: f64 = 0.01;
+
+// Distributions of the physical attributes of the generated agents.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct AgentAttributes {
+  pub desired_speed: Distribution,
+  pub maximum_speed: Distribution,
+  pub maximum_acceleration: Distribution,
+  pub radius: Distribution,
+}
+
+impl AgentAttributes {
+  // Check the attributes can't be fixed to non positive values.
+  pub fn check(&self) -> Result<(), Error> {
+    self.desired_speed.check_positive("desired speed")?;
+    self.maximum_speed.check_positive("maximum speed")?;
+    self
+      .maximum_acceleration
+      .check_positive("maximum acceleration")?;
+    self.radius.check_positive("radius")
+  }
+
+  // Sample the attributes of one agent, they are all strictly positive.
+  pub fn sample(&self, agent: Agent, random: &mut Random) -> Agent {
+    let mut sample =
+      |distribution: &Distribution| distribution.sample(random).max(MINIMUM_PHYSICAL_VALUE);
+    let desired_speed = sample(&self.desired_speed);
+    // An agent can always reach its desired speed.
+    let maximum_speed = sample(&self.maximum_speed).max(desired_speed);
+    agent
+      .desired_speed(desired_speed)
+      .maximum_speed(maximum_speed)
+      .maximum_acceleration(sample(&self.maximum_acceleration))
+      .radius(sample(&self.radius))
+  }
+}
+
+impl Default for AgentAttributes {
+  fn default() -> Self {
+    AgentAttributes {
+      desired_speed: Distribution::Fixed(DEFAULT_DESIRED_SPEED),
+      maximum_speed: Distribution::Fixed(DEFAULT_MAXIMUM_SPEED),
+      maximum_acceleration: Distribution::Fixed(DEFAULT_MAXIMUM_ACCELERATION),
+      radius: Distribution::Fixed(DEFAULT_RADIUS),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_sample() {
+    let attributes = AgentAttributes {
+      desired_speed: Distribution::Uniform { min: 1.2, max: 1.6 },
+      maximum_speed: Distribution::Fixed(1.4),
+      ..AgentAttributes::default()
+    };
+    let mut random = Random::new(2);
+    (0..100).for_each(|_| {
+      let agent = attributes.sample(Agent::new(), &mut random);
+      assert!((1.2..1.6).contains(&agent.desired_speed));
+      assert!(agent.maximum_speed >= agent.desired_speed);
+      assert_eq!(agent.radius, DEFAULT_RADIUS);
+    });
+  }
+
+  #[test]
+  fn test_sample_positive() {
+    let attributes = AgentAttributes {
+      desired_speed: Distribution::Normal {
+        mean: 0.,
+        standard_deviation: 1.,
+        min: f64::NEG_INFINITY,
+        max: f64::INFINITY,
+      },
+      ..AgentAttributes::default()
+    };
+    assert!(attributes.check().is_ok());
+    let mut random = Random::new(2);
+    (0..100).for_each(|_| {
+      let agent = attributes.sample(Agent::new(), &mut random);
+      assert!(agent.desired_speed >= MINIMUM_PHYSICAL_VALUE);
+    });
+  }
+
+  #[test]
+  fn test_check() {
+    assert!(AgentAttributes::default().check().is_ok());
+    let attributes = AgentAttributes {
+      radius: Distribution::Fixed(-0.3),
+      ..AgentAttributes::default()
+    };
+    assert_eq!(
+      attributes.check(),
+      Err(Error::InvalidScenario(
+        "non positive fixed radius -0.3".to_string()
+      ))
+    );
+  }
+}
@@ -1,15 +1,26 @@
+use super::agent_attributes::AgentAttributes;
 use super::scenario::Scenario;
-use crate::agent::Agent;
+use crate::agent::{Agent, ArrivalPolicy};
 use crate::agents::Agents;
+use crate::error::Error;
 use crate::navmesh::{Navmesh, NavmeshBuilder};
+use crate::random::Random;
 use crate::vec2::Vec2;
 use serde::Deserialize;
 
-#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize)]
 #[serde(default)]
 pub struct AntipodalCircleScenario {
   pub agents_count: usize,
   pub radius: f64,
+  // What agents do once arrived, with `NextTarget` they head back to their
+  // start position.
+  pub arrival_policy: ArrivalPolicy,
+  // Maximum random offset applied to the start positions, breaking the
+  // symmetry of the scenario.
+  pub jitter: f64,
+  #[serde(flatten)]
+  pub agent_attributes: AgentAttributes,
 }
 
 impl Default for AntipodalCircleScenario {
@@ -17,32 +28,70 @@ impl Default for AntipodalCircleScenario {
     AntipodalCircleScenario {
       agents_count: 2,
       radius: 5.0,
+      arrival_policy: ArrivalPolicy::Stop,
+      jitter: 0.,
+      agent_attributes: AgentAttributes::default(),
     }
   }
 }
 
 impl Scenario for AntipodalCircleScenario {
-  fn generate(&self) -> (Agents, Navmesh) {
+  fn generate(&self, random: &mut Random) -> Result<(Agents, Navmesh), Error> {
+    self.agent_attributes.check()?;
     let s = self.radius * 1.5;
-    (
+    Ok((
       (0..self.agents_count).fold(Agents::new(), |mut agents, i| {
         let angle = (i as f64) * 2.0 * std::f64::consts::PI / (self.agents_count as f64);
-        let from_x = self.radius * angle.cos();
-        let from_y = self.radius * angle.sin();
-        let to_x = -from_x;
-        let to_y = -from_y;
+        let to_x = -self.radius * libm::cos(angle);
+        let to_y = -self.radius * libm::sin(angle);
+        let from_x = -to_x + random.uniform(-self.jitter, self.jitter);
+        let from_y = -to_y + random.uniform(-self.jitter, self.jitter);
         agents.create_agent(
-          Agent::new()
+          self
+            .agent_attributes
+            .sample(Agent::new(), random)
             .position(from_x, from_y)
             .direction(to_x - from_x, to_y - from_y)
-            .target(to_x, to_y),
+            .target(to_x, to_y)
+            .arrival_policy(self.arrival_policy)
+            .waypoint(from_x, from_y),
         );
         agents
       }),
       NavmeshBuilder::new()
         .add_cell(Vec2::new(-s, -s), Vec2::new(s, -s), Vec2::new(s, s))
         .add_cell(Vec2::new(-s, -s), Vec2::new(-s, s), Vec2::new(s, s))
-        .build(),
-    )
+        .build()?,
+    ))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_generate_jitter() {
+    let s = AntipodalCircleScenario {
+      agents_count: 4,
+      radius: 5.0,
+      arrival_policy: ArrivalPolicy::Stop,
+      jitter: 0.1,
+      agent_attributes: AgentAttributes::default(),
+    };
+    let (agents, _) = s.generate(&mut Random::new(1)).unwrap();
+    agents
+      .get_positions()
+      .iter()
+      .zip(agents.get_targets())
+      .for_each(|(&position, &target)| {
+        let offset = position + target;
+        assert!(offset.x().abs() <= 0.1 && offset.y().abs() <= 0.1);
+        assert!(offset.norm() > 0.);
+      });
+
+    // The same seed generates the same scenario.
+    let (other_agents, _) = s.generate(&mut Random::new(1)).unwrap();
+    assert_eq!(agents, other_agents);
   }
 }
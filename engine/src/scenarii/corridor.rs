@@ -1,16 +1,24 @@
+use super::agent_attributes::AgentAttributes;
 use super::scenario::Scenario;
-use crate::agent::Agent;
+use crate::agent::{Agent, ArrivalPolicy};
 use crate::agents::Agents;
+use crate::error::Error;
 use crate::navmesh::{Navmesh, NavmeshBuilder};
+use crate::random::Random;
 use crate::vec2::Vec2;
 use serde::Deserialize;
 
-#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize)]
 #[serde(default)]
 pub struct CorridorScenario {
   pub agents_per_side_count: usize,
   pub length: f64,
   pub width: f64,
+  // What agents do once arrived, with `NextTarget` they head back to their
+  // start position.
+  pub arrival_policy: ArrivalPolicy,
+  #[serde(flatten)]
+  pub agent_attributes: AgentAttributes,
 }
 
 impl Default for CorridorScenario {
@@ -19,17 +27,20 @@ impl Default for CorridorScenario {
       agents_per_side_count: 1,
       length: 10.,
       width: 1.,
+      arrival_policy: ArrivalPolicy::Stop,
+      agent_attributes: AgentAttributes::default(),
     }
   }
 }
 
 impl Scenario for CorridorScenario {
-  fn generate(&self) -> (Agents, Navmesh) {
+  fn generate(&self, random: &mut Random) -> Result<(Agents, Navmesh), Error> {
+    self.agent_attributes.check()?;
     let h_width = self.width / 2.0;
     let h_length = self.length / 2.0;
     let agent_margin = self.width / (self.agents_per_side_count + 1) as f64;
     let length_margin = agent_margin;
-    (
+    Ok((
       [-1.0, 1.0].iter().fold(Agents::new(), |agents, &side| {
         (0..self.agents_per_side_count).fold(agents, |mut agents, i| {
           let from_x = h_length * side;
@@ -37,10 +48,14 @@ impl Scenario for CorridorScenario {
           let to_x = -h_length * side;
           let to_y = from_y;
           agents.create_agent(
-            Agent::new()
+            self
+              .agent_attributes
+              .sample(Agent::new(), random)
               .position(from_x, from_y)
               .direction(-side, 0.)
-              .target(to_x, to_y),
+              .target(to_x, to_y)
+              .arrival_policy(self.arrival_policy)
+              .waypoint(from_x, from_y),
           );
           agents
         })
@@ -56,8 +71,8 @@ impl Scenario for CorridorScenario {
           Vec2::new(-h_length - length_margin, h_width),
           Vec2::new(h_length + length_margin, h_width),
         )
-        .build(),
-    )
+        .build()?,
+    ))
   }
 }
 
@@ -71,8 +86,10 @@ mod tests {
       agents_per_side_count: 3,
       length: 10.0,
       width: 3.0,
+      arrival_policy: ArrivalPolicy::Stop,
+      agent_attributes: AgentAttributes::default(),
     };
-    let (agents, navmesh) = s.generate();
+    let (agents, navmesh) = s.generate(&mut Random::default()).unwrap();
     assert_eq!(agents.len(), 6);
 
     assert_eq!(navmesh.count_cells(), 2);
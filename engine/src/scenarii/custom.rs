@@ -0,0 +1,574 @@
+use super::agent_attributes::AgentAttributes;
+use super::scenario::Scenario;
+use crate::agent::{Agent, ArrivalPolicy};
+use crate::agents::Agents;
+use crate::distribution::Distribution;
+use crate::error::{check_non_negative, check_positive, Error};
+use crate::group::Group;
+use crate::navmesh::{Navmesh, NavmeshBuilder};
+use crate::queue::{Queue, DEFAULT_QUEUE_SPACING, DEFAULT_SERVICE_TIME};
+use crate::random::Random;
+use crate::trajectory::Trajectory;
+use crate::vec2::Vec2;
+use serde::Deserialize;
+use std::vec::Vec;
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct CustomAgent {
+  pub position: [f64; 2],
+  pub target: [f64; 2],
+  // Index of the agent's group in the scenario's groups.
+  pub group: Option<usize>,
+  // Index of the queue the agent goes through before reaching its target.
+  pub queue: Option<usize>,
+  // When defined, the agent is scripted and follows this trajectory.
+  pub trajectory: Option<Trajectory>,
+  // Overrides the desired speed sampled from the scenario's agent attributes.
+  pub desired_speed: Option<f64>,
+  // Maximum rotation speed of the body direction, in radians per second.
+  pub maximum_angular_speed: Option<f64>,
+  // Time over which the body direction catches up with the walking direction,
+  // in seconds.
+  pub smoothing_time: Option<f64>,
+  // When defined, prevents the agent from walking backward faster than this.
+  pub maximum_backward_speed: Option<f64>,
+  // Overrides the scenario's arrival policy for this agent.
+  pub arrival_policy: Option<ArrivalPolicy>,
+  // Targets to reach after the first one, with the `NextTarget` policy.
+  pub waypoints: Vec<[f64; 2]>,
+}
+
+impl Default for CustomAgent {
+  fn default() -> Self {
+    CustomAgent {
+      position: [0., 0.],
+      target: [0., 0.],
+      group: None,
+      queue: None,
+      trajectory: None,
+      desired_speed: None,
+      maximum_angular_speed: None,
+      smoothing_time: None,
+      maximum_backward_speed: None,
+      arrival_policy: None,
+      waypoints: Vec::new(),
+    }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct CustomQueue {
+  // Service point location.
+  pub position: [f64; 2],
+  // Direction along which the queue line extends from the service point.
+  pub direction: [f64; 2],
+  pub spacing: f64,
+  // Service time of each agent going through the queue.
+  pub service_time: Distribution,
+}
+
+impl Default for CustomQueue {
+  fn default() -> Self {
+    CustomQueue {
+      position: [0., 0.],
+      direction: [1., 0.],
+      spacing: DEFAULT_QUEUE_SPACING,
+      service_time: Distribution::Fixed(DEFAULT_SERVICE_TIME),
+    }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct CustomScenario {
+  // Navigable area, as [min_x, min_y, max_x, max_y].
+  pub bounds: [f64; 4],
+  // What agents do once arrived.
+  pub arrival_policy: ArrivalPolicy,
+  pub groups: Vec<Group>,
+  pub queues: Vec<CustomQueue>,
+  pub agents: Vec<CustomAgent>,
+  #[serde(flatten)]
+  pub agent_attributes: AgentAttributes,
+}
+
+impl Default for CustomScenario {
+  fn default() -> Self {
+    CustomScenario {
+      bounds: [-10., -10., 10., 10.],
+      arrival_policy: ArrivalPolicy::Stop,
+      groups: Vec::new(),
+      queues: Vec::new(),
+      agents: Vec::new(),
+      agent_attributes: AgentAttributes::default(),
+    }
+  }
+}
+
+impl CustomScenario {
+  // Check the groups and queues the agents reference exist, the agents'
+  // parameters are valid and their positions, targets and waypoints are in
+  // the navigable area.
+  fn check_references(&self) -> Result<(), Error> {
+    let [min_x, min_y, max_x, max_y] = self.bounds;
+    let is_in_bounds =
+      |&[x, y]: &[f64; 2]| (min_x..=max_x).contains(&x) && (min_y..=max_y).contains(&y);
+    self
+      .queues
+      .iter()
+      .try_for_each(|custom_queue| check_non_negative("spacing", custom_queue.spacing))?;
+    self
+      .agents
+      .iter()
+      .enumerate()
+      .try_for_each(|(idx_agent, custom_agent)| {
+        if let Some(group) = custom_agent
+          .group
+          .filter(|&group| group >= self.groups.len())
+        {
+          return Err(Error::InvalidScenario(format!(
+            "agent {} belongs to the unknown group {}",
+            idx_agent, group
+          )));
+        }
+        if let Some(queue) = custom_agent
+          .queue
+          .filter(|&queue| queue >= self.queues.len())
+        {
+          return Err(Error::InvalidScenario(format!(
+            "agent {} goes through the unknown queue {}",
+            idx_agent, queue
+          )));
+        }
+        if let Some(desired_speed) = custom_agent.desired_speed {
+          check_positive("desired_speed", desired_speed)?;
+        }
+        if let Some(maximum_angular_speed) = custom_agent.maximum_angular_speed {
+          check_non_negative("maximum_angular_speed", maximum_angular_speed)?;
+        }
+        if let Some(smoothing_time) = custom_agent.smoothing_time {
+          check_non_negative("smoothing_time", smoothing_time)?;
+        }
+        if let Some(maximum_backward_speed) = custom_agent.maximum_backward_speed {
+          check_non_negative("maximum_backward_speed", maximum_backward_speed)?;
+        }
+        match &custom_agent.trajectory {
+          Some(trajectory) if trajectory.is_empty() => {
+            return Err(Error::InvalidScenario(format!(
+              "agent {} follows an empty trajectory",
+              idx_agent
+            )));
+          }
+          // Scripted agents start where their trajectory does.
+          Some(_) => {}
+          None if !is_in_bounds(&custom_agent.position) => {
+            let [x, y] = custom_agent.position;
+            return Err(Error::InvalidScenario(format!(
+              "agent {} position [{}, {}] is out of the bounds",
+              idx_agent, x, y
+            )));
+          }
+          None => {}
+        }
+        if !is_in_bounds(&custom_agent.target) {
+          let [x, y] = custom_agent.target;
+          return Err(Error::InvalidScenario(format!(
+            "agent {} target [{}, {}] is out of the bounds",
+            idx_agent, x, y
+          )));
+        }
+        match custom_agent
+          .waypoints
+          .iter()
+          .find(|waypoint| !is_in_bounds(waypoint))
+        {
+          Some([x, y]) => Err(Error::InvalidScenario(format!(
+            "agent {} waypoint [{}, {}] is out of the bounds",
+            idx_agent, x, y
+          ))),
+          None => Ok(()),
+        }
+      })
+  }
+}
+
+impl Scenario for CustomScenario {
+  fn generate(&self, random: &mut Random) -> Result<(Agents, Navmesh), Error> {
+    self.agent_attributes.check()?;
+    self.check_references()?;
+    let [min_x, min_y, max_x, max_y] = self.bounds;
+    let agents = self
+      .groups
+      .iter()
+      .fold(Agents::new(), |mut agents, &group| {
+        agents.create_group(group);
+        agents
+      });
+    let agents = self.queues.iter().fold(agents, |mut agents, custom_queue| {
+      let [x, y] = custom_queue.position;
+      let [direction_x, direction_y] = custom_queue.direction;
+      agents.create_queue(
+        Queue::new(x, y)
+          .direction(direction_x, direction_y)
+          .spacing(custom_queue.spacing),
+      );
+      agents
+    });
+    Ok((
+      self.agents.iter().fold(agents, |mut agents, custom_agent| {
+        let [target_x, target_y] = custom_agent.target;
+        let agent = match &custom_agent.trajectory {
+          Some(trajectory) => {
+            let position = trajectory.position_at(0.);
+            let velocity = trajectory.velocity_at(0.);
+            self
+              .agent_attributes
+              .sample(Agent::new(), random)
+              .position(position.x(), position.y())
+              .velocity(velocity.x(), velocity.y())
+              .direction(velocity.x(), velocity.y())
+              .target(target_x, target_y)
+              .trajectory(trajectory.clone())
+          }
+          None => {
+            let [x, y] = custom_agent.position;
+            self
+              .agent_attributes
+              .sample(Agent::new(), random)
+              .position(x, y)
+              .direction(target_x - x, target_y - y)
+              .target(target_x, target_y)
+          }
+        };
+        let agent = custom_agent.waypoints.iter().fold(
+          agent.arrival_policy(custom_agent.arrival_policy.unwrap_or(self.arrival_policy)),
+          |agent, &[x, y]| agent.waypoint(x, y),
+        );
+        let agent = match custom_agent.desired_speed {
+          Some(desired_speed) => {
+            let maximum_speed = agent.maximum_speed.max(desired_speed);
+            agent
+              .desired_speed(desired_speed)
+              .maximum_speed(maximum_speed)
+          }
+          None => agent,
+        };
+        let agent = match custom_agent.maximum_angular_speed {
+          Some(maximum_angular_speed) => agent.maximum_angular_speed(maximum_angular_speed),
+          None => agent,
+        };
+        let agent = match custom_agent.smoothing_time {
+          Some(smoothing_time) => agent.smoothing_time(smoothing_time),
+          None => agent,
+        };
+        let agent = match custom_agent.maximum_backward_speed {
+          Some(maximum_backward_speed) => agent.maximum_backward_speed(maximum_backward_speed),
+          None => agent,
+        };
+        let agent = match custom_agent.group {
+          Some(group) => agent.group(group),
+          None => agent,
+        };
+        agents.create_agent(match custom_agent.queue {
+          Some(queue) => agent
+            .queue(queue)
+            .service_time(self.queues[queue].service_time.sample(random)),
+          None => agent,
+        });
+        agents
+      }),
+      NavmeshBuilder::new()
+        .add_cell(
+          Vec2::new(min_x, min_y),
+          Vec2::new(max_x, min_y),
+          Vec2::new(max_x, max_y),
+        )
+        .add_cell(
+          Vec2::new(min_x, min_y),
+          Vec2::new(min_x, max_y),
+          Vec2::new(max_x, max_y),
+        )
+        .build()?,
+    ))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::agent::{AgentKind, DEFAULT_MAXIMUM_ANGULAR_SPEED, DEFAULT_SMOOTHING_TIME};
+  use approx::assert_relative_eq;
+
+  #[test]
+  fn test_generate() {
+    let s = CustomScenario {
+      bounds: [-5., -5., 5., 5.],
+      arrival_policy: ArrivalPolicy::Despawn,
+      groups: Vec::new(),
+      queues: Vec::new(),
+      agents: vec![
+        CustomAgent {
+          position: [-2., 0.],
+          target: [2., 0.],
+          group: None,
+          queue: None,
+          trajectory: None,
+          desired_speed: Some(4.),
+          maximum_angular_speed: Some(1.),
+          smoothing_time: Some(0.5),
+          maximum_backward_speed: Some(0.5),
+          arrival_policy: Some(ArrivalPolicy::NextTarget),
+          waypoints: vec![[2., 2.]],
+        },
+        CustomAgent {
+          position: [0., 0.],
+          target: [0., 0.],
+          group: None,
+          queue: None,
+          trajectory: Some(Trajectory::new(&[
+            (0., Vec2::new(0., 2.)),
+            (2., Vec2::new(0., -2.)),
+          ])),
+          desired_speed: None,
+          maximum_angular_speed: None,
+          smoothing_time: None,
+          maximum_backward_speed: None,
+          arrival_policy: None,
+          waypoints: Vec::new(),
+        },
+      ],
+      agent_attributes: AgentAttributes {
+        radius: Distribution::Fixed(0.3),
+        ..AgentAttributes::default()
+      },
+    };
+    let (agents, navmesh) = s.generate(&mut Random::default()).unwrap();
+    itertools::assert_equal(agents.get_radii(), &vec![0.3, 0.3]);
+    // The maximum speed is raised to the overridden desired speed.
+    assert_relative_eq!(agents.get_desired_speeds()[0], 4.);
+    assert_relative_eq!(agents.get_maximum_speeds()[0], 4.);
+    assert_eq!(agents.len(), 2);
+    itertools::assert_equal(
+      agents.get_kinds(),
+      &vec![AgentKind::Navigating, AgentKind::Scripted],
+    );
+    assert_relative_eq!(agents.get_positions()[1], Vec2::new(0., 2.));
+    assert_relative_eq!(agents.get_velocities()[1], Vec2::new(0., -2.));
+    itertools::assert_equal(
+      agents.get_arrival_policies(),
+      &vec![ArrivalPolicy::NextTarget, ArrivalPolicy::Despawn],
+    );
+    itertools::assert_equal(
+      agents.get_maximum_angular_speeds(),
+      &vec![1., DEFAULT_MAXIMUM_ANGULAR_SPEED],
+    );
+    itertools::assert_equal(
+      agents.get_smoothing_times(),
+      &vec![0.5, DEFAULT_SMOOTHING_TIME],
+    );
+    itertools::assert_equal(agents.get_maximum_backward_speeds(), &vec![Some(0.5), None]);
+    itertools::assert_equal(&agents.get_waypoints()[0], &vec![Vec2::new(2., 2.)]);
+
+    assert_eq!(navmesh.count_cells(), 2);
+  }
+
+  #[test]
+  fn test_generate_service_times() {
+    let agent = CustomAgent {
+      queue: Some(0),
+      ..CustomAgent::default()
+    };
+    let s = CustomScenario {
+      queues: vec![CustomQueue {
+        service_time: Distribution::Uniform { min: 1., max: 3. },
+        ..CustomQueue::default()
+      }],
+      agents: vec![agent.clone(), agent, CustomAgent::default()],
+      ..CustomScenario::default()
+    };
+    let (agents, _) = s.generate(&mut Random::new(3)).unwrap();
+    let service_times = agents.get_service_times();
+    // Each agent going through the queue gets its own service time.
+    assert!(service_times[..2]
+      .iter()
+      .all(|service_time| (1.0..3.0).contains(service_time)));
+    assert_ne!(service_times[0], service_times[1]);
+    // The same seed gives the same service times.
+    let (agents, _) = s.generate(&mut Random::new(3)).unwrap();
+    itertools::assert_equal(agents.get_service_times(), service_times);
+  }
+
+  #[test]
+  fn test_generate_invalid_references() {
+    let agent = CustomAgent {
+      position: [-2., 0.],
+      target: [2., 0.],
+      ..CustomAgent::default()
+    };
+    let s = CustomScenario {
+      groups: vec![Group::default()],
+      queues: vec![CustomQueue::default()],
+      agents: vec![agent.clone()],
+      ..CustomScenario::default()
+    };
+    assert!(s.generate(&mut Random::default()).is_ok());
+
+    let unknown_group = CustomScenario {
+      agents: vec![CustomAgent {
+        group: Some(1),
+        ..agent.clone()
+      }],
+      ..s.clone()
+    };
+    assert_eq!(
+      unknown_group.generate(&mut Random::default()).err(),
+      Some(Error::InvalidScenario(
+        "agent 0 belongs to the unknown group 1".to_string()
+      ))
+    );
+
+    let unknown_queue = CustomScenario {
+      agents: vec![CustomAgent {
+        queue: Some(3),
+        ..agent.clone()
+      }],
+      ..s.clone()
+    };
+    assert_eq!(
+      unknown_queue.generate(&mut Random::default()).err(),
+      Some(Error::InvalidScenario(
+        "agent 0 goes through the unknown queue 3".to_string()
+      ))
+    );
+
+    let non_positive_desired_speed = CustomScenario {
+      agents: vec![CustomAgent {
+        desired_speed: Some(0.),
+        ..agent.clone()
+      }],
+      ..s.clone()
+    };
+    assert_eq!(
+      non_positive_desired_speed
+        .generate(&mut Random::default())
+        .err(),
+      Some(Error::InvalidParameter {
+        name: "desired_speed",
+        value: 0.
+      })
+    );
+
+    let non_positive_radius = CustomScenario {
+      agent_attributes: AgentAttributes {
+        radius: Distribution::Fixed(-0.3),
+        ..AgentAttributes::default()
+      },
+      ..s.clone()
+    };
+    assert!(non_positive_radius
+      .generate(&mut Random::default())
+      .is_err());
+
+    let empty_trajectory = CustomScenario {
+      agents: vec![CustomAgent {
+        trajectory: Some(Trajectory::new(&[])),
+        ..agent.clone()
+      }],
+      ..s.clone()
+    };
+    assert_eq!(
+      empty_trajectory.generate(&mut Random::default()).err(),
+      Some(Error::InvalidScenario(
+        "agent 0 follows an empty trajectory".to_string()
+      ))
+    );
+
+    let negative_parameters = [
+      CustomAgent {
+        smoothing_time: Some(-0.3),
+        ..agent.clone()
+      },
+      CustomAgent {
+        maximum_angular_speed: Some(-1.),
+        ..agent.clone()
+      },
+      CustomAgent {
+        maximum_backward_speed: Some(-0.5),
+        ..agent.clone()
+      },
+    ];
+    negative_parameters.iter().for_each(|custom_agent| {
+      let negative_parameter = CustomScenario {
+        agents: vec![custom_agent.clone()],
+        ..s.clone()
+      };
+      assert!(matches!(
+        negative_parameter.generate(&mut Random::default()),
+        Err(Error::InvalidParameter { .. })
+      ));
+    });
+
+    let negative_spacing = CustomScenario {
+      queues: vec![CustomQueue {
+        spacing: -1.,
+        ..CustomQueue::default()
+      }],
+      ..s.clone()
+    };
+    assert_eq!(
+      negative_spacing.generate(&mut Random::default()).err(),
+      Some(Error::InvalidParameter {
+        name: "spacing",
+        value: -1.
+      })
+    );
+
+    let out_of_bounds_position = CustomScenario {
+      agents: vec![CustomAgent {
+        position: [-20., 0.],
+        ..agent.clone()
+      }],
+      ..s.clone()
+    };
+    assert_eq!(
+      out_of_bounds_position
+        .generate(&mut Random::default())
+        .err(),
+      Some(Error::InvalidScenario(
+        "agent 0 position [-20, 0] is out of the bounds".to_string()
+      ))
+    );
+
+    let out_of_bounds_target = CustomScenario {
+      agents: vec![CustomAgent {
+        target: [2., 20.],
+        ..agent.clone()
+      }],
+      ..s.clone()
+    };
+    assert_eq!(
+      out_of_bounds_target.generate(&mut Random::default()).err(),
+      Some(Error::InvalidScenario(
+        "agent 0 target [2, 20] is out of the bounds".to_string()
+      ))
+    );
+
+    let out_of_bounds_waypoint = CustomScenario {
+      agents: vec![CustomAgent {
+        waypoints: vec![[2., 2.], [20., 0.]],
+        ..agent
+      }],
+      ..s
+    };
+    assert_eq!(
+      out_of_bounds_waypoint
+        .generate(&mut Random::default())
+        .err(),
+      Some(Error::InvalidScenario(
+        "agent 0 waypoint [20, 0] is out of the bounds".to_string()
+      ))
+    );
+  }
+}
@@ -1,6 +1,8 @@
 use super::scenario::Scenario;
 use crate::agents::Agents;
+use crate::error::Error;
 use crate::navmesh::{Navmesh, NavmeshBuilder};
+use crate::random::Random;
 use crate::vec2::Vec2;
 use serde::Deserialize;
 
@@ -20,13 +22,13 @@ impl Default for EmptyScenario {
 }
 
 impl Scenario for EmptyScenario {
-  fn generate(&self) -> (Agents, Navmesh) {
-    (
+  fn generate(&self, _random: &mut Random) -> Result<(Agents, Navmesh), Error> {
+    Ok((
       Agents::new(),
       NavmeshBuilder::new()
         .add_cell(Vec2::new(0., 0.), Vec2::new(1., 0.), Vec2::new(1., 1.))
         .add_cell(Vec2::new(0., 0.), Vec2::new(0., 1.), Vec2::new(1., 1.))
-        .build(),
-    )
+        .build()?,
+    ))
   }
 }
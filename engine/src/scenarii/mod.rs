@@ -1,56 +1,153 @@
+mod agent_attributes;
 mod antipodal_circle;
 mod corridor;
+mod custom;
 mod empty_scenario;
 mod scenario;
 
 use crate::agents::Agents;
+use crate::error::Error;
 use crate::navmesh::Navmesh;
+use crate::random::Random;
 use serde::Deserialize;
 
 pub use antipodal_circle::AntipodalCircleScenario;
 pub use corridor::CorridorScenario;
+pub use custom::{CustomAgent, CustomScenario};
 pub use empty_scenario::EmptyScenario;
 pub use scenario::Scenario;
 
-#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize)]
 #[serde(tag = "scenario")]
 enum Scenarii {
   AntipodalCircle(AntipodalCircleScenario),
   Corridor(CorridorScenario),
+  Custom(CustomScenario),
   Empty(EmptyScenario),
 }
 
 impl Scenario for Scenarii {
-  fn generate(&self) -> (Agents, Navmesh) {
+  fn generate(&self, random: &mut Random) -> Result<(Agents, Navmesh), Error> {
     match self {
-      Scenarii::Corridor(s) => s.generate(),
-      Scenarii::AntipodalCircle(s) => s.generate(),
-      Scenarii::Empty(s) => s.generate(),
+      Scenarii::Corridor(s) => s.generate(random),
+      Scenarii::AntipodalCircle(s) => s.generate(random),
+      Scenarii::Custom(s) => s.generate(random),
+      Scenarii::Empty(s) => s.generate(random),
     }
   }
 }
 
-fn load_concrete_scenario(data: &str) -> Scenarii {
-  serde_json::from_str::<Scenarii>(data)
-    .unwrap_or_else(|_| Scenarii::Empty(EmptyScenario::default()))
+// The seed is shared by all the scenarii.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+struct ScenarioSeed {
+  #[serde(default)]
+  seed: u64,
+}
+
+struct SeededScenario {
+  seed: u64,
+  scenario: Scenarii,
+}
+
+impl Scenario for SeededScenario {
+  fn generate(&self, random: &mut Random) -> Result<(Agents, Navmesh), Error> {
+    self.scenario.generate(random)
+  }
+  fn seed(&self) -> u64 {
+    self.seed
+  }
+}
+
+// The errors raised while deserializing, e.g. by the distributions, can
+// already be invalid scenario errors, their message is kept as is.
+fn invalid_scenario(err: serde_json::Error) -> Error {
+  let message = err.to_string();
+  let prefix = Error::InvalidScenario(String::new()).to_string();
+  Error::InvalidScenario(
+    message
+      .strip_prefix(&prefix)
+      .map_or(message.clone(), str::to_string),
+  )
+}
+
+fn load_concrete_scenario(data: &str) -> Result<Scenarii, Error> {
+  serde_json::from_str::<Scenarii>(data).map_err(invalid_scenario)
+}
+
+fn load_seed(data: &str) -> Result<u64, Error> {
+  serde_json::from_str::<ScenarioSeed>(data)
+    .map(|s| s.seed)
+    .map_err(invalid_scenario)
 }
 
-pub fn load_scenario(data: &str) -> impl Scenario {
-  load_concrete_scenario(data)
+pub fn load_scenario(data: &str) -> Result<impl Scenario, Error> {
+  Ok(SeededScenario {
+    seed: load_seed(data)?,
+    scenario: load_concrete_scenario(data)?,
+  })
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::agent::ArrivalPolicy;
+  use crate::distribution::Distribution;
+  use crate::group::{Formation, Group};
+  use crate::random::DEFAULT_SEED;
+  use agent_attributes::AgentAttributes;
 
   #[test]
   fn test_load_scenario_empty() {
-    match load_concrete_scenario("{}") {
-      Scenarii::Empty(s) => assert_eq!(s, EmptyScenario::new()),
+    match load_concrete_scenario("{ \"scenario\": \"Empty\" }") {
+      Ok(Scenarii::Empty(s)) => assert_eq!(s, EmptyScenario::new()),
       _ => panic!("Expecting an EmptyScenario."),
     }
   }
 
+  #[test]
+  fn test_load_scenario_invalid() {
+    assert!(matches!(
+      load_concrete_scenario("{}"),
+      Err(Error::InvalidScenario(_))
+    ));
+    assert!(matches!(
+      load_concrete_scenario("{ \"scenario\": \"Unknown\" }"),
+      Err(Error::InvalidScenario(_))
+    ));
+    assert!(matches!(
+      load_scenario("{ \"scenario\": \"Empty\", \"seed\": -1 }"),
+      Err(Error::InvalidScenario(_))
+    ));
+    assert!(matches!(
+      load_scenario(
+        "{
+          \"scenario\": \"Custom\",
+          \"radius\": { \"distribution\": \"Uniform\", \"min\": 0.4, \"max\": 0.3 }
+        }"
+      )
+      .err(),
+      Some(Error::InvalidScenario(message)) if message.starts_with("uniform distribution")
+    ));
+  }
+
+  #[test]
+  fn test_load_seed() {
+    assert_eq!(
+      load_seed("{ \"scenario\": \"Corridor\", \"seed\": 42 }"),
+      Ok(42)
+    );
+    assert_eq!(
+      load_seed("{ \"scenario\": \"Corridor\" }"),
+      Ok(DEFAULT_SEED)
+    );
+    assert_eq!(
+      load_scenario("{ \"scenario\": \"Empty\", \"seed\": 12 }")
+        .unwrap()
+        .seed(),
+      12
+    );
+  }
+
   #[test]
   fn test_load_scenario_antipodal_circle() {
     match load_concrete_scenario(
@@ -59,12 +156,17 @@ mod tests {
         \"agents_count\": 3,
         \"radius\": 4.0
       }",
-    ) {
+    )
+    .unwrap()
+    {
       Scenarii::AntipodalCircle(s) => assert_eq!(
         s,
         AntipodalCircleScenario {
           agents_count: 3,
           radius: 4.0,
+          arrival_policy: ArrivalPolicy::Stop,
+          jitter: 0.,
+          agent_attributes: AgentAttributes::default(),
         }
       ),
       _ => panic!("Expecting an AntipodalCircleScenario."),
@@ -78,34 +180,84 @@ mod tests {
         \"scenario\": \"Corridor\",
         \"agents_per_side_count\": 3,
         \"length\": 10,
-        \"width\": 3.0
+        \"width\": 3.0,
+        \"arrival_policy\": \"NextTarget\",
+        \"desired_speed\": { \"distribution\": \"Uniform\", \"min\": 1.2, \"max\": 1.6 },
+        \"radius\": 0.3
       }",
-    ) {
+    )
+    .unwrap()
+    {
       Scenarii::Corridor(s) => assert_eq!(
         s,
         CorridorScenario {
           agents_per_side_count: 3,
           length: 10.0,
           width: 3.0,
+          arrival_policy: ArrivalPolicy::NextTarget,
+          agent_attributes: AgentAttributes {
+            desired_speed: Distribution::Uniform { min: 1.2, max: 1.6 },
+            radius: Distribution::Fixed(0.3),
+            ..AgentAttributes::default()
+          },
         }
       ),
       _ => panic!("Expecting an CorridorScenario."),
     }
   }
 
+  #[test]
+  fn test_load_scenario_custom() {
+    match load_concrete_scenario(
+      "{
+        \"scenario\": \"Custom\",
+        \"bounds\": [-5, -5, 5, 5],
+        \"groups\": [{ \"formation\": \"Free\", \"spacing\": 1.2 }],
+        \"queues\": [{ \"position\": [0, 4], \"direction\": [0, -1], \"service_time\": 3 }],
+        \"agents\": [
+          { \"position\": [-4, 0], \"target\": [4, 0], \"group\": 0, \"queue\": 0 },
+          { \"trajectory\": [[0, 4, 0], [4, -4, 0]] }
+        ]
+      }",
+    )
+    .unwrap()
+    {
+      Scenarii::Custom(s) => {
+        assert_eq!(s.bounds, [-5., -5., 5., 5.]);
+        assert_eq!(s.groups.len(), 1);
+        assert_eq!(s.groups[0].formation, Formation::Free);
+        assert_eq!(s.groups[0].spacing, 1.2);
+        assert_eq!(s.groups[0].cohesion, Group::default().cohesion);
+        assert_eq!(s.queues.len(), 1);
+        assert_eq!(s.queues[0].direction, [0., -1.]);
+        assert_eq!(s.queues[0].service_time, Distribution::Fixed(3.));
+        assert_eq!(s.agents.len(), 2);
+        assert_eq!(s.agents[0].group, Some(0));
+        assert_eq!(s.agents[0].queue, Some(0));
+        assert_eq!(s.agents[0].trajectory, None);
+        assert_eq!(s.agents[1].trajectory.as_ref().unwrap().len(), 2);
+      }
+      _ => panic!("Expecting an CustomScenario."),
+    }
+  }
+
   #[test]
   fn test_load_scenario_corridor_default() {
     match load_concrete_scenario(
       "{
         \"scenario\": \"Corridor\"
       }",
-    ) {
+    )
+    .unwrap()
+    {
       Scenarii::Corridor(s) => assert_eq!(
         s,
         CorridorScenario {
           agents_per_side_count: 1,
           length: 10.0,
           width: 1.0,
+          arrival_policy: ArrivalPolicy::Stop,
+          agent_attributes: AgentAttributes::default(),
         }
       ),
       _ => panic!("Expecting an CorridorScenario."),
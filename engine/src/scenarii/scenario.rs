@@ -1,6 +1,12 @@
 use crate::agents::Agents;
+use crate::error::Error;
 use crate::navmesh::Navmesh;
+use crate::random::{Random, DEFAULT_SEED};
 
 pub trait Scenario {
-  fn generate(&self) -> (Agents, Navmesh);
+  fn generate(&self, random: &mut Random) -> Result<(Agents, Navmesh), Error>;
+  // Seed of the random numbers generator used to generate and run the scenario.
+  fn seed(&self) -> u64 {
+    DEFAULT_SEED
+  }
 }
@@ -0,0 +1,133 @@
+use super::agents::Agents;
+use super::clock::Clock;
+use super::error::Error;
+use super::navmesh::Navmesh;
+use super::random::Random;
+use super::travels::TravelRecord;
+use serde::{Deserialize, Serialize};
+
+// Incremented whenever the layout of the snapshot changes.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+// Full state of a simulation, restoring it and continuing the simulation gives
+// the exact same results as continuing the original one.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+  // Kept first so that it can be read before the rest of the binary layout.
+  version: u32,
+  pub agents: Agents,
+  pub navmesh: Navmesh,
+  pub clock: Clock,
+  pub random: Random,
+  pub travel_records: Vec<TravelRecord>,
+  pub time_horizon: f64,
+}
+
+#[derive(Deserialize)]
+struct SnapshotVersion {
+  version: u32,
+}
+
+impl Snapshot {
+  pub fn new(
+    agents: Agents,
+    navmesh: Navmesh,
+    clock: Clock,
+    random: Random,
+    travel_records: Vec<TravelRecord>,
+    time_horizon: f64,
+  ) -> Self {
+    Snapshot {
+      version: SNAPSHOT_VERSION,
+      agents,
+      navmesh,
+      clock,
+      random,
+      travel_records,
+      time_horizon,
+    }
+  }
+
+  pub fn to_json(&self) -> String {
+    serde_json::to_string(self).unwrap()
+  }
+
+  pub fn from_json(data: &str) -> Result<Self, Error> {
+    let SnapshotVersion { version } = serde_json::from_str(data).map_err(invalid_snapshot)?;
+    check_version(version)?;
+    serde_json::from_str(data).map_err(invalid_snapshot)
+  }
+
+  pub fn to_binary(&self) -> Vec<u8> {
+    bincode::serialize(self).unwrap()
+  }
+
+  pub fn from_binary(data: &[u8]) -> Result<Self, Error> {
+    let version: u32 = bincode::deserialize(data).map_err(invalid_snapshot)?;
+    check_version(version)?;
+    bincode::deserialize(data).map_err(invalid_snapshot)
+  }
+}
+
+fn invalid_snapshot<E: std::fmt::Display>(err: E) -> Error {
+  Error::InvalidData(format!("Invalid snapshot: {}", err))
+}
+
+fn check_version(version: u32) -> Result<(), Error> {
+  if version == SNAPSHOT_VERSION {
+    Ok(())
+  } else {
+    Err(Error::InvalidData(format!(
+      "Unsupported snapshot version {}, expected {}",
+      version, SNAPSHOT_VERSION
+    )))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::scenarii::{load_scenario, Scenario};
+
+  fn create_snapshot() -> Snapshot {
+    let scenario = load_scenario(
+      "{
+        \"scenario\": \"Custom\",
+        \"queues\": [{ \"position\": [5, 0] }],
+        \"agents\": [
+          { \"position\": [-5, 0], \"target\": [5, 0], \"queue\": 0 },
+          { \"trajectory\": [[0, 0, 5], [5, 0, -5]] }
+        ]
+      }",
+    )
+    .unwrap();
+    let mut random = Random::new(scenario.seed());
+    let (agents, navmesh) = scenario.generate(&mut random).unwrap();
+    Snapshot::new(agents, navmesh, Clock::new(), random, Vec::new(), 4.)
+  }
+
+  #[test]
+  fn test_json_round_trip() {
+    let snapshot = create_snapshot();
+    assert_eq!(Snapshot::from_json(&snapshot.to_json()).unwrap(), snapshot);
+  }
+
+  #[test]
+  fn test_binary_round_trip() {
+    let snapshot = create_snapshot();
+    assert_eq!(
+      Snapshot::from_binary(&snapshot.to_binary()).unwrap(),
+      snapshot
+    );
+  }
+
+  #[test]
+  fn test_version_mismatch() {
+    let mut snapshot = create_snapshot();
+    snapshot.version = SNAPSHOT_VERSION + 1;
+    assert!(Snapshot::from_json(&snapshot.to_json()).is_err());
+    assert!(Snapshot::from_binary(&snapshot.to_binary()).is_err());
+    assert!(Snapshot::from_json(&format!("{{ \"version\": {} }}", SNAPSHOT_VERSION)).is_err());
+    assert!(Snapshot::from_binary(&[1, 0]).is_err());
+  }
+}
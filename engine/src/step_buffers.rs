@@ -0,0 +1,78 @@
+use super::neighborhood::AgentNeighborhood;
+use super::queue::Queue;
+use super::vec2::Vec2;
+use std::vec::Vec;
+
+// Per agent intermediate results of a simulation step.
+//
+// They are kept from one step to the next so that, once sized for the
+// agents, steps don't allocate.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StepBuffers {
+  // Queues and queue of each agent once updated for the step.
+  pub queues: Vec<Queue>,
+  pub queue_ids: Vec<Option<usize>>,
+  pub neighborhoods: Vec<AgentNeighborhood>,
+  pub targets: Vec<Vec2>,
+  pub reach_target_velocities: Vec<Vec2>,
+  pub desired_velocities: Vec<Vec2>,
+  pub orca_velocities: Vec<Vec2>,
+  pub velocities: Vec<Vec2>,
+  pub positions: Vec<Vec2>,
+  pub cells: Vec<Option<usize>>,
+  pub path_lengths: Vec<f64>,
+  pub directions: Vec<Vec2>,
+  pub arrived: Vec<bool>,
+  // Scratch space for the members of the group being navigated.
+  pub group_members: Vec<usize>,
+  // Indices of the agents to remove at the end of the step.
+  pub despawned: Vec<usize>,
+}
+
+impl StepBuffers {
+  pub fn new() -> Self {
+    StepBuffers::default()
+  }
+
+  // Size the buffers for the given number of agents, only allocating when
+  // there are more agents than ever before.
+  pub fn resize(&mut self, agents_count: usize) {
+    self.queue_ids.resize(agents_count, None);
+    self
+      .neighborhoods
+      .resize(agents_count, AgentNeighborhood::default());
+    self.targets.resize(agents_count, Vec2::default());
+    self
+      .reach_target_velocities
+      .resize(agents_count, Vec2::default());
+    self
+      .desired_velocities
+      .resize(agents_count, Vec2::default());
+    self.orca_velocities.resize(agents_count, Vec2::default());
+    self.velocities.resize(agents_count, Vec2::default());
+    self.positions.resize(agents_count, Vec2::default());
+    self.cells.resize(agents_count, None);
+    self.path_lengths.resize(agents_count, 0.);
+    self.directions.resize(agents_count, Vec2::default());
+    self.arrived.resize(agents_count, false);
+    self.despawned.clear();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_resize() {
+    let mut buffers = StepBuffers::new();
+    buffers.resize(10);
+    assert_eq!(buffers.neighborhoods.len(), 10);
+    assert_eq!(buffers.arrived.len(), 10);
+    let capacity = buffers.desired_velocities.capacity();
+    buffers.resize(4);
+    assert_eq!(buffers.velocities.len(), 4);
+    // Shrinking keeps the allocated memory around
+    assert_eq!(buffers.desired_velocities.capacity(), capacity);
+  }
+}
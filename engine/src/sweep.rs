@@ -0,0 +1,385 @@
+use super::error::check_positive;
+use super::metrics::MetricsConfig;
+use super::random::DEFAULT_SEED;
+use super::travels::TravelsSummary;
+use super::Universe;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::thread;
+use std::vec::Vec;
+
+// Parameter applied to the universe instead of the scenario.
+pub const TIME_HORIZON_PARAMETER: &str = "time_horizon";
+
+pub const CSV_SUMMARY_COLUMNS: &str = "agents_count,arrived_count,mean_travel_time,\
+  mean_path_length_ratio,mean_speed,mean_density,total_agents_overlaps_count,\
+  total_walls_penetrations_count,maximum_agents_overlap_depth,maximum_wall_penetration_depth";
+
+// Values taken by one parameter of the sweep.
+//
+// The name is a field set by the base scenario, e.g. `agents_per_side_count`
+// or `desired_speed`, or `time_horizon` for the ORCA time horizon.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct SweepParameter {
+  pub name: String,
+  pub values: Vec<Value>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct SweepConfig {
+  // Scenario, as loaded by `Universe::load_scenario`, the parameters are
+  // applied to.
+  pub scenario: Value,
+  pub parameters: Vec<SweepParameter>,
+  // Every combination of the parameters is run once per seed, the seed of
+  // the base scenario is used when empty.
+  pub seeds: Vec<u64>,
+  // Simulated duration of each run.
+  pub duration: f64,
+  pub timestep: f64,
+  pub metrics: MetricsConfig,
+}
+
+impl Default for SweepConfig {
+  fn default() -> Self {
+    SweepConfig {
+      scenario: Value::Object(serde_json::Map::new()),
+      parameters: Vec::new(),
+      seeds: Vec::new(),
+      duration: 60.,
+      timestep: 0.1,
+      metrics: MetricsConfig::default(),
+    }
+  }
+}
+
+// One combination of the parameters values, with its seed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SweepRun {
+  pub index: usize,
+  pub parameters: Vec<(String, Value)>,
+  pub seed: u64,
+}
+
+impl SweepConfig {
+  // Runs covering the parameters grid, the last parameter varying fastest
+  // and the seeds innermost.
+  pub fn runs(&self) -> Vec<SweepRun> {
+    let base_seed = self
+      .scenario
+      .get("seed")
+      .and_then(Value::as_u64)
+      .unwrap_or(DEFAULT_SEED);
+    let seeds = if self.seeds.is_empty() {
+      vec![base_seed]
+    } else {
+      self.seeds.clone()
+    };
+    self
+      .parameters
+      .iter()
+      .fold(vec![Vec::new()], |combinations, parameter| {
+        combinations
+          .iter()
+          .flat_map(|combination: &Vec<(String, Value)>| {
+            parameter.values.iter().map(move |value| {
+              let mut combination = combination.clone();
+              combination.push((parameter.name.clone(), value.clone()));
+              combination
+            })
+          })
+          .collect()
+      })
+      .into_iter()
+      .flat_map(|parameters| seeds.iter().map(move |&seed| (parameters.clone(), seed)))
+      .enumerate()
+      .map(|(index, (parameters, seed))| SweepRun {
+        index,
+        parameters,
+        seed,
+      })
+      .collect()
+  }
+
+  // Check the durations are positive and the parameters known, a misspelled
+  // parameter would be silently ignored by the scenario.
+  pub fn check(&self) -> Result<(), String> {
+    check_positive("duration", self.duration)?;
+    check_positive("timestep", self.timestep)?;
+    let scenario_fields = self
+      .scenario
+      .as_object()
+      .ok_or("The base scenario should be an object")?;
+    match self.parameters.iter().find(|parameter| {
+      parameter.name != TIME_HORIZON_PARAMETER && !scenario_fields.contains_key(&parameter.name)
+    }) {
+      Some(parameter) => Err(format!(
+        "Unknown parameter {}, it should be set by the base scenario or be {}",
+        parameter.name, TIME_HORIZON_PARAMETER
+      )),
+      None => Ok(()),
+    }
+  }
+
+  pub fn parameters_names(&self) -> Vec<&str> {
+    self
+      .parameters
+      .iter()
+      .map(|parameter| parameter.name.as_str())
+      .collect()
+  }
+}
+
+// Metrics of one run, aggregated over its whole duration.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct RunSummary {
+  pub run: usize,
+  pub seed: u64,
+  pub parameters: Vec<(String, Value)>,
+  pub agents_count: usize,
+  pub arrived_count: usize,
+  pub mean_travel_time: Option<f64>,
+  pub mean_path_length_ratio: Option<f64>,
+  // Mean speed and global density, averaged over the steps.
+  pub mean_speed: f64,
+  pub mean_density: f64,
+  pub total_agents_overlaps_count: usize,
+  pub total_walls_penetrations_count: usize,
+  pub maximum_agents_overlap_depth: f64,
+  pub maximum_wall_penetration_depth: f64,
+}
+
+// Simulate a single run of the sweep.
+pub fn simulate_run(config: &SweepConfig, run: &SweepRun) -> Result<RunSummary, String> {
+  config.check()?;
+  let mut scenario = config.scenario.clone();
+  let scenario_fields = scenario
+    .as_object_mut()
+    .ok_or("The base scenario should be an object")?;
+  let mut universe = Universe::new();
+  universe.try_set_timestep(config.timestep)?;
+  universe.set_metrics(config.metrics.clone())?;
+  for (name, value) in &run.parameters {
+    if name == TIME_HORIZON_PARAMETER {
+      match value.as_f64() {
+        Some(time_horizon) => universe.try_set_time_horizon(time_horizon)?,
+        None => return Err(format!("Invalid value {} for {}", value, name)),
+      }
+    } else {
+      scenario_fields.insert(name.clone(), value.clone());
+    }
+  }
+  scenario_fields.insert("seed".to_string(), Value::from(run.seed));
+  universe.try_load_scenario(&scenario.to_string())?;
+
+  let agents_count = universe.count_agents();
+  let steps_count = (config.duration / config.timestep).round() as usize;
+  let (speeds_sum, densities_sum) =
+    (0..steps_count).try_fold((0., 0.), |(speeds_sum, densities_sum), _| {
+      universe.try_step()?;
+      Ok::<_, String>(
+        match universe.get_metrics().unwrap().get_last_step_metrics() {
+          Some(step_metrics) => (
+            speeds_sum + step_metrics.mean_speed,
+            densities_sum + step_metrics.global_density,
+          ),
+          None => (speeds_sum, densities_sum),
+        },
+      )
+    })?;
+  let travels_summary = TravelsSummary::new(universe.get_travel_records());
+  let collisions = universe
+    .get_metrics()
+    .unwrap()
+    .get_last_step_metrics()
+    .map(|step_metrics| step_metrics.collisions.clone())
+    .unwrap_or_default();
+  let steps_count = steps_count.max(1) as f64;
+  Ok(RunSummary {
+    run: run.index,
+    seed: run.seed,
+    parameters: run.parameters.clone(),
+    agents_count,
+    arrived_count: travels_summary.arrived_count,
+    mean_travel_time: travels_summary.travel_time.map(|summary| summary.mean),
+    mean_path_length_ratio: travels_summary
+      .path_length_ratio
+      .map(|summary| summary.mean),
+    mean_speed: speeds_sum / steps_count,
+    mean_density: densities_sum / steps_count,
+    total_agents_overlaps_count: collisions.total_agents_overlaps_count,
+    total_walls_penetrations_count: collisions.total_walls_penetrations_count,
+    maximum_agents_overlap_depth: collisions.total_maximum_agents_overlap_depth,
+    maximum_wall_penetration_depth: collisions.total_maximum_wall_penetration_depth,
+  })
+}
+
+// Simulate every run of the sweep, spread over `threads_count` threads.
+//
+// Runs are independent from each other, the summaries, ordered by run, don't
+// depend on the number of threads.
+pub fn run_sweep(config: &SweepConfig, threads_count: usize) -> Result<Vec<RunSummary>, String> {
+  let runs = config.runs();
+  let threads_count = threads_count.clamp(1, runs.len().max(1));
+  let mut summaries = thread::scope(|scope| {
+    let handles: Vec<_> = (0..threads_count)
+      .map(|idx_thread| {
+        let runs = &runs;
+        scope.spawn(move || {
+          runs
+            .iter()
+            .skip(idx_thread)
+            .step_by(threads_count)
+            .map(|run| simulate_run(config, run))
+            .collect::<Result<Vec<RunSummary>, String>>()
+        })
+      })
+      .collect();
+    handles
+      .into_iter()
+      .map(|handle| {
+        handle
+          .join()
+          .map_err(|_| "A sweep thread panicked".to_string())?
+      })
+      .collect::<Result<Vec<Vec<RunSummary>>, String>>()
+  })?
+  .concat();
+  summaries.sort_by_key(|summary| summary.run);
+  Ok(summaries)
+}
+
+// One line per run, preceded by a header line. Parameters values are written
+// as JSON.
+pub fn to_csv(parameters_names: &[&str], summaries: &[RunSummary]) -> String {
+  let header = ["run", "seed"]
+    .iter()
+    .chain(parameters_names)
+    .copied()
+    .collect::<Vec<&str>>()
+    .join(",");
+  let optional = |value: Option<f64>| value.map_or(String::new(), |value| value.to_string());
+  summaries.iter().fold(
+    format!("{},{}\n", header, CSV_SUMMARY_COLUMNS),
+    |mut output, summary| {
+      output.push_str(&format!("{},{}", summary.run, summary.seed));
+      summary.parameters.iter().for_each(|(_, value)| {
+        output.push_str(&format!(",{}", value));
+      });
+      output.push_str(&format!(
+        ",{},{},{},{},{},{},{},{},{},{}\n",
+        summary.agents_count,
+        summary.arrived_count,
+        optional(summary.mean_travel_time),
+        optional(summary.mean_path_length_ratio),
+        summary.mean_speed,
+        summary.mean_density,
+        summary.total_agents_overlaps_count,
+        summary.total_walls_penetrations_count,
+        summary.maximum_agents_overlap_depth,
+        summary.maximum_wall_penetration_depth
+      ));
+      output
+    },
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn config() -> SweepConfig {
+    serde_json::from_str(
+      "{
+        \"scenario\": {
+          \"scenario\": \"Corridor\",
+          \"agents_per_side_count\": 1,
+          \"length\": 6,
+          \"width\": 3
+        },
+        \"parameters\": [
+          { \"name\": \"agents_per_side_count\", \"values\": [1, 3] },
+          { \"name\": \"time_horizon\", \"values\": [2, 5] }
+        ],
+        \"seeds\": [1, 2],
+        \"duration\": 5
+      }",
+    )
+    .unwrap()
+  }
+
+  #[test]
+  fn test_runs() {
+    let runs = config().runs();
+    assert_eq!(runs.len(), 8);
+    assert_eq!(runs[0].seed, 1);
+    assert_eq!(runs[1].seed, 2);
+    assert_eq!(
+      runs[2].parameters,
+      vec![
+        ("agents_per_side_count".to_string(), Value::from(1)),
+        ("time_horizon".to_string(), Value::from(5))
+      ]
+    );
+    assert_eq!(runs[7].index, 7);
+    assert_eq!(SweepConfig::default().runs().len(), 1);
+  }
+
+  #[test]
+  fn test_run_sweep() {
+    let config = config();
+    let summaries = run_sweep(&config, 1).unwrap();
+    assert_eq!(summaries.len(), 8);
+    assert_eq!(summaries[0].agents_count, 2);
+    assert_eq!(summaries[7].agents_count, 6);
+    assert!(summaries[0].mean_speed > 0.);
+    // The summaries don't depend on the parallelism.
+    assert_eq!(run_sweep(&config, 3).unwrap(), summaries);
+
+    let csv = to_csv(&config.parameters_names(), &summaries);
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 9);
+    assert!(lines[0].starts_with("run,seed,agents_per_side_count,time_horizon,agents_count,"));
+    assert!(lines[8].starts_with("7,2,3,5,6,"));
+  }
+
+  #[test]
+  fn test_invalid_time_horizon() {
+    let config = SweepConfig {
+      parameters: vec![SweepParameter {
+        name: TIME_HORIZON_PARAMETER.to_string(),
+        values: vec![Value::from("long")],
+      }],
+      ..SweepConfig::default()
+    };
+    assert!(run_sweep(&config, 2).is_err());
+  }
+
+  #[test]
+  fn test_invalid_config() {
+    assert!(config().check().is_ok());
+    // A misspelled parameter
+    let mut config = config();
+    config.parameters[0].name = "agent_per_side_count".to_string();
+    assert!(config.check().is_err());
+    assert!(run_sweep(&config, 1).is_err());
+    // Durations
+    [0., -1., f64::INFINITY, f64::NAN]
+      .iter()
+      .for_each(|&value| {
+        assert!(SweepConfig {
+          duration: value,
+          ..SweepConfig::default()
+        }
+        .check()
+        .is_err());
+        assert!(SweepConfig {
+          timestep: value,
+          ..SweepConfig::default()
+        }
+        .check()
+        .is_err());
+      });
+  }
+}
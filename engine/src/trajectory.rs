@@ -0,0 +1,151 @@
+use super::vec2::Vec2;
+use serde::{Deserialize, Serialize};
+use std::vec::Vec;
+
+// A sequence of time-stamped positions, sorted by time.
+//
+// It is (de)serialized as a list of `[t, x, y]` samples.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(from = "Vec<(f64, f64, f64)>", into = "Vec<(f64, f64, f64)>")]
+pub struct Trajectory {
+  samples: Vec<(f64, Vec2)>,
+}
+
+impl Trajectory {
+  pub fn new(samples: &[(f64, Vec2)]) -> Self {
+    let mut samples = samples.to_vec();
+    samples.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    Trajectory { samples }
+  }
+
+  #[allow(dead_code)]
+  pub fn len(&self) -> usize {
+    self.samples.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.samples.is_empty()
+  }
+
+  pub fn get_samples(&self) -> &[(f64, Vec2)] {
+    &self.samples
+  }
+
+  // First and last sample times, if any.
+  pub fn time_range(&self) -> Option<(f64, f64)> {
+    match (self.samples.first(), self.samples.last()) {
+      (Some(&(t_first, _)), Some(&(t_last, _))) => Some((t_first, t_last)),
+      _ => None,
+    }
+  }
+
+  // Index of the segment [i, i + 1] containing time `t`, clamped to the first
+  // and last segments.
+  fn segment_index(&self, t: f64) -> usize {
+    let next_sample_index = self.samples.iter().position(|&(t_i, _)| t_i > t);
+    match next_sample_index {
+      Some(0) => 0,
+      Some(i) => i - 1,
+      None => self.samples.len().saturating_sub(2),
+    }
+  }
+
+  // Position at time `t`, linearly interpolated between samples.
+  //
+  // Before the first sample (resp. after the last sample) the position is the
+  // first (resp. last) sample.
+  pub fn position_at(&self, t: f64) -> Vec2 {
+    match self.samples.len() {
+      0 => Vec2::default(),
+      1 => self.samples[0].1,
+      _ => {
+        let i = self.segment_index(t);
+        let (t_0, p_0) = self.samples[i];
+        let (t_1, p_1) = self.samples[i + 1];
+        if t <= t_0 {
+          p_0
+        } else if t >= t_1 || (t_1 - t_0).abs() < f64::EPSILON {
+          p_1
+        } else {
+          p_0 + (t - t_0) / (t_1 - t_0) * (p_1 - p_0)
+        }
+      }
+    }
+  }
+
+  // Velocity at time `t`, null outside of the trajectory time range.
+  pub fn velocity_at(&self, t: f64) -> Vec2 {
+    if self.samples.len() < 2 {
+      return Vec2::default();
+    }
+    let i = self.segment_index(t);
+    let (t_0, p_0) = self.samples[i];
+    let (t_1, p_1) = self.samples[i + 1];
+    if t < t_0 || t >= t_1 || (t_1 - t_0).abs() < f64::EPSILON {
+      Vec2::default()
+    } else {
+      (p_1 - p_0) / (t_1 - t_0)
+    }
+  }
+}
+
+impl From<Vec<(f64, f64, f64)>> for Trajectory {
+  fn from(samples: Vec<(f64, f64, f64)>) -> Self {
+    Trajectory::new(
+      &samples
+        .iter()
+        .map(|&(t, x, y)| (t, Vec2::new(x, y)))
+        .collect::<Vec<(f64, Vec2)>>(),
+    )
+  }
+}
+
+impl From<Trajectory> for Vec<(f64, f64, f64)> {
+  fn from(trajectory: Trajectory) -> Self {
+    trajectory
+      .samples
+      .iter()
+      .map(|&(t, position)| (t, position.x(), position.y()))
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use approx::assert_relative_eq;
+
+  #[test]
+  fn test_position_at() {
+    let trajectory = Trajectory::new(&[
+      (1.0, Vec2::new(0.0, 0.0)),
+      (3.0, Vec2::new(2.0, 0.0)),
+      (4.0, Vec2::new(2.0, 3.0)),
+    ]);
+    assert_relative_eq!(trajectory.position_at(0.0), Vec2::new(0.0, 0.0));
+    assert_relative_eq!(trajectory.position_at(2.0), Vec2::new(1.0, 0.0));
+    assert_relative_eq!(trajectory.position_at(3.0), Vec2::new(2.0, 0.0));
+    assert_relative_eq!(trajectory.position_at(3.5), Vec2::new(2.0, 1.5));
+    assert_relative_eq!(trajectory.position_at(10.0), Vec2::new(2.0, 3.0));
+  }
+
+  #[test]
+  fn test_velocity_at() {
+    let trajectory = Trajectory::new(&[
+      (3.0, Vec2::new(2.0, 0.0)),
+      (1.0, Vec2::new(0.0, 0.0)),
+      (4.0, Vec2::new(2.0, 3.0)),
+    ]);
+    assert_relative_eq!(trajectory.velocity_at(0.0), Vec2::new(0.0, 0.0));
+    assert_relative_eq!(trajectory.velocity_at(1.0), Vec2::new(1.0, 0.0));
+    assert_relative_eq!(trajectory.velocity_at(3.5), Vec2::new(0.0, 3.0));
+    assert_relative_eq!(trajectory.velocity_at(4.0), Vec2::new(0.0, 0.0));
+  }
+
+  #[test]
+  fn test_deserialize() {
+    let trajectory: Trajectory = serde_json::from_str("[[0, 1.0, 2.0], [1.0, 3.0, 2.0]]").unwrap();
+    assert_eq!(trajectory.len(), 2);
+    assert_relative_eq!(trajectory.position_at(0.5), Vec2::new(2.0, 2.0));
+  }
+}
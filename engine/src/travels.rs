@@ -0,0 +1,178 @@
+use super::vec2::Vec2;
+use itertools::izip;
+use serde::{Deserialize, Serialize};
+use std::vec::Vec;
+
+pub const CSV_HEADER: &str =
+  "id,arrival_time,travel_time,path_length,straight_line_length,path_length_ratio";
+
+// Trip of an agent from its initial position to its first arrival.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TravelRecord {
+  pub id: usize,
+  pub arrival_time: f64,
+  // Time from the agent's departure to its arrival.
+  pub travel_time: f64,
+  // Length of the walked path.
+  pub path_length: f64,
+  // Distance between the initial position and the arrival position.
+  pub straight_line_length: f64,
+  // Walked path length over the straight line length, 1 for a straight
+  // trip in an open space.
+  pub path_length_ratio: f64,
+}
+
+impl TravelRecord {
+  pub fn new(
+    id: usize,
+    departure_time: f64,
+    arrival_time: f64,
+    departure_position: Vec2,
+    arrival_position: Vec2,
+    path_length: f64,
+  ) -> Self {
+    let straight_line_length = (arrival_position - departure_position).norm();
+    TravelRecord {
+      id,
+      arrival_time,
+      travel_time: arrival_time - departure_time,
+      path_length,
+      straight_line_length,
+      path_length_ratio: if straight_line_length > 0. {
+        path_length / straight_line_length
+      } else {
+        1.
+      },
+    }
+  }
+}
+
+// Summary of the distribution of a quantity over the agents.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct ValuesSummary {
+  pub min: f64,
+  pub max: f64,
+  pub mean: f64,
+  pub standard_deviation: f64,
+  pub median: f64,
+  pub percentile_90: f64,
+}
+
+impl ValuesSummary {
+  pub fn new(values: &[f64]) -> Option<Self> {
+    if values.is_empty() {
+      return None;
+    }
+    let mut sorted_values = values.to_vec();
+    sorted_values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let count = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / count;
+    let variance = values
+      .iter()
+      .map(|value| (value - mean) * (value - mean))
+      .sum::<f64>()
+      / count;
+    Some(ValuesSummary {
+      min: sorted_values[0],
+      max: sorted_values[sorted_values.len() - 1],
+      mean,
+      standard_deviation: variance.sqrt(),
+      median: percentile(&sorted_values, 0.5),
+      percentile_90: percentile(&sorted_values, 0.9),
+    })
+  }
+}
+
+// Linearly interpolated percentile of sorted values.
+fn percentile(sorted_values: &[f64], ratio: f64) -> f64 {
+  let rank = ratio * (sorted_values.len() - 1) as f64;
+  let lower_index = rank.floor() as usize;
+  let upper_index = rank.ceil() as usize;
+  sorted_values[lower_index]
+    + (rank - lower_index as f64) * (sorted_values[upper_index] - sorted_values[lower_index])
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct TravelsSummary {
+  pub arrived_count: usize,
+  pub travel_time: Option<ValuesSummary>,
+  pub path_length: Option<ValuesSummary>,
+  pub path_length_ratio: Option<ValuesSummary>,
+}
+
+impl TravelsSummary {
+  pub fn new(records: &[TravelRecord]) -> Self {
+    let summarize = |value: fn(&TravelRecord) -> f64| {
+      ValuesSummary::new(&records.iter().map(value).collect::<Vec<f64>>())
+    };
+    TravelsSummary {
+      arrived_count: records.len(),
+      travel_time: summarize(|record| record.travel_time),
+      path_length: summarize(|record| record.path_length),
+      path_length_ratio: summarize(|record| record.path_length_ratio),
+    }
+  }
+}
+
+// Accumulate the length walked during the step.
+pub fn update_path_lengths(
+  path_lengths: &[f64],
+  velocities: &[Vec2],
+  dt: f64,
+  updated_path_lengths: &mut [f64],
+) {
+  izip!(path_lengths, velocities, updated_path_lengths).for_each(
+    |(path_length, velocity, updated_path_length)| {
+      *updated_path_length = path_length + velocity.norm() * dt
+    },
+  );
+}
+
+// One line per record, preceded by a header line.
+pub fn to_csv(records: &[TravelRecord]) -> String {
+  records
+    .iter()
+    .fold(format!("{}\n", CSV_HEADER), |mut output, record| {
+      output.push_str(&format!(
+        "{},{},{},{},{},{}\n",
+        record.id,
+        record.arrival_time,
+        record.travel_time,
+        record.path_length,
+        record.straight_line_length,
+        record.path_length_ratio
+      ));
+      output
+    })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use approx::assert_relative_eq;
+
+  #[test]
+  fn test_travel_record() {
+    let record = TravelRecord::new(3, 0., 5., Vec2::new(0., 0.), Vec2::new(3., 4.), 7.5);
+    assert_relative_eq!(record.travel_time, 5.);
+    assert_relative_eq!(record.straight_line_length, 5.);
+    assert_relative_eq!(record.path_length_ratio, 1.5);
+    assert_eq!(
+      to_csv(&[record]),
+      "id,arrival_time,travel_time,path_length,straight_line_length,path_length_ratio\n\
+      3,5,5,7.5,5,1.5\n"
+    );
+  }
+
+  #[test]
+  fn test_values_summary() {
+    assert_eq!(ValuesSummary::new(&[]), None);
+    let summary = ValuesSummary::new(&[4., 1., 3., 2., 5.]).unwrap();
+    assert_relative_eq!(summary.min, 1.);
+    assert_relative_eq!(summary.max, 5.);
+    assert_relative_eq!(summary.mean, 3.);
+    assert_relative_eq!(summary.standard_deviation, 2_f64.sqrt());
+    assert_relative_eq!(summary.median, 3.);
+    assert_relative_eq!(summary.percentile_90, 4.6);
+  }
+}
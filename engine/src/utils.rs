@@ -27,3 +27,28 @@ macro_rules! log {
     println!($( $t )*);
   };
 }
+
+// Zip slices of per agent values, like `izip!(..)`, into a parallel iterator
+// when the `parallel` feature is enabled.
+//
+// The iterated closures must only depend on their agent's values, the result
+// is then the same whatever the iteration order. `rayon::prelude::*` needs
+// to be in scope to `map` and `collect` the parallel iterator.
+#[cfg(feature = "parallel")]
+#[macro_export]
+macro_rules! par_izip {
+  ( $( $slice:expr ),+ $(,)? ) => {
+    ( $( rayon::iter::IntoParallelIterator::into_par_iter($slice) ),+ ).into_par_iter()
+  };
+}
+
+#[cfg(not(feature = "parallel"))]
+#[macro_export]
+macro_rules! par_izip {
+  ( $( $slice:expr ),+ $(,)? ) => {
+    {
+      use itertools::izip;
+      izip!( $( $slice ),+ )
+    }
+  };
+}
@@ -1,9 +1,9 @@
 use approx::{AbsDiffEq, RelativeEq, UlpsEq};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::ops::{Add, Div, DivAssign, Mul, MulAssign, Neg, Sub};
 
-#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Vec2 {
   x: f64,
   y: f64,
@@ -56,9 +56,24 @@ impl Vec2 {
     }
   }
 
+  pub fn is_finite(&self) -> bool {
+    self.x.is_finite() && self.y.is_finite()
+  }
+
   pub fn det(v1: Self, v2: Self) -> f64 {
     v1.x * v2.y - v1.y * v2.x
   }
+
+  // Counterclockwise rotation by the given angle, in radians.
+  pub fn rotate(&self, angle: f64) -> Self {
+    let (sin, cos) = libm::sincos(angle);
+    Vec2::new(cos * self.x - sin * self.y, sin * self.x + cos * self.y)
+  }
+
+  // Signed angle, in ]-π, π], from v1 to v2.
+  pub fn angle(v1: Self, v2: Self) -> f64 {
+    libm::atan2(Vec2::det(v1, v2), v1 * v2)
+  }
 }
 
 impl Default for Vec2 {
@@ -234,4 +249,24 @@ mod tests {
     assert_relative_eq!(Vec2::new(1.0, 2.0).cap_norm(0.5).norm(), 0.5);
     assert_relative_eq!(Vec2::new(0., 0.).cap_norm(0.5).norm(), 0.);
   }
+
+  #[test]
+  fn test_rotate() {
+    assert_relative_eq!(
+      Vec2::new(1.0, 2.0).rotate(std::f64::consts::FRAC_PI_2),
+      Vec2::new(-2.0, 1.0)
+    );
+  }
+
+  #[test]
+  fn test_angle() {
+    assert_relative_eq!(
+      Vec2::angle(Vec2::new(1.0, 0.0), Vec2::new(1.0, 1.0)),
+      std::f64::consts::FRAC_PI_4
+    );
+    assert_relative_eq!(
+      Vec2::angle(Vec2::new(0.0, 2.0), Vec2::new(1.0, 0.0)),
+      -std::f64::consts::FRAC_PI_2
+    );
+  }
 }
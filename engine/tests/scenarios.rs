@@ -0,0 +1,247 @@
+//! Scenario regression tests.
+//!
+//! Each `tests/scenarios/<name>.json` fixture is simulated and its outcome is
+//! checked against the invariants it declares and against the golden summary
+//! stored in `tests/scenarios/<name>.golden.json`.
+//!
+//! A fixture violating its invariants today declares it, with the reason, as
+//! a `known_failure`, and fails once it doesn't violate them anymore.
+//!
+//! Run with `BLESS=1` to (re)write the golden summaries from the current
+//! outcomes, e.g. `BLESS=1 cargo test --test scenarios`.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+extern crate rusty_pedestrians_engine;
+use rusty_pedestrians_engine::metrics::MetricsConfig;
+use rusty_pedestrians_engine::Universe;
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Relative tolerance on the golden numbers.
+const TOLERANCE: f64 = 0.000000001;
+
+// Invariants checked over the whole simulation, unchecked when not set.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+struct Invariants {
+  // Agents overlapping each other by at most this depth, `0` for no
+  // overlaps.
+  maximum_agents_overlap_depth: Option<f64>,
+  // Agents bodies getting out of the navmesh by at most this depth, `0` for
+  // agents staying on the navmesh.
+  maximum_wall_penetration_depth: Option<f64>,
+  // Every agent reached its target by the end of the simulation.
+  all_arrived: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+struct Fixture {
+  scenario: Value,
+  duration: f64,
+  timestep: f64,
+  invariants: Invariants,
+  // Why the invariants are violated today, unset when they hold.
+  known_failure: Option<String>,
+}
+
+impl Default for Fixture {
+  fn default() -> Self {
+    Fixture {
+      scenario: Value::Object(serde_json::Map::new()),
+      duration: 30.,
+      timestep: 0.1,
+      invariants: Invariants::default(),
+      known_failure: None,
+    }
+  }
+}
+
+struct Outcome {
+  summary: Value,
+  violations: Vec<String>,
+}
+
+fn simulate(fixture: &Fixture) -> Outcome {
+  let mut universe = Universe::new();
+  universe.try_set_timestep(fixture.timestep).unwrap();
+  universe.set_metrics(MetricsConfig::default()).unwrap();
+  universe
+    .try_load_scenario(&fixture.scenario.to_string())
+    .unwrap();
+  let agents_count = universe.count_agents();
+
+  let steps_count = (fixture.duration / fixture.timestep).round() as usize;
+  (0..steps_count).for_each(|_| universe.try_step().unwrap());
+
+  let collisions = universe
+    .get_metrics()
+    .unwrap()
+    .get_last_step_metrics()
+    .map(|step_metrics| step_metrics.collisions.clone())
+    .unwrap_or_default();
+  let mut violations = Vec::new();
+  if let Some(maximum_depth) = fixture.invariants.maximum_agents_overlap_depth {
+    if collisions.total_maximum_agents_overlap_depth > maximum_depth {
+      violations.push(format!(
+        "agents overlapping by up to {:.3}m, {} overlaps overall",
+        collisions.total_maximum_agents_overlap_depth, collisions.total_agents_overlaps_count
+      ));
+    }
+  }
+  if let Some(maximum_depth) = fixture.invariants.maximum_wall_penetration_depth {
+    if collisions.total_maximum_wall_penetration_depth > maximum_depth {
+      violations.push(format!(
+        "agents out of the navmesh by up to {:.3}m, {} penetrations overall",
+        collisions.total_maximum_wall_penetration_depth, collisions.total_walls_penetrations_count
+      ));
+    }
+  }
+  let travels_summary: Value = serde_json::from_str(&universe.render_travels_summary()).unwrap();
+  if fixture.invariants.all_arrived && travels_summary["arrived_count"] != json!(agents_count) {
+    violations.push(format!(
+      "{} agents out of {} arrived",
+      travels_summary["arrived_count"], agents_count
+    ));
+  }
+
+  let final_agents: Vec<Value> = universe
+    .render_agents()
+    .chunks(7)
+    .map(|agent| json!({ "position": [agent[0], agent[1]], "velocity": [agent[4], agent[5]] }))
+    .collect();
+  Outcome {
+    summary: json!({
+      "steps_count": steps_count,
+      "agents_count": agents_count,
+      "travels": travels_summary,
+      "total_agents_overlaps_count": collisions.total_agents_overlaps_count,
+      "total_walls_penetrations_count": collisions.total_walls_penetrations_count,
+      "maximum_agents_overlap_depth": collisions.total_maximum_agents_overlap_depth,
+      "maximum_wall_penetration_depth": collisions.total_maximum_wall_penetration_depth,
+      "final_agents": final_agents,
+    }),
+    violations,
+  }
+}
+
+// First difference between the golden and actual values, numbers being
+// compared with a relative tolerance.
+fn find_difference(path: &str, golden: &Value, actual: &Value) -> Option<String> {
+  match (golden, actual) {
+    (Value::Number(golden_number), Value::Number(actual_number)) => {
+      let golden_number = golden_number.as_f64().unwrap();
+      let actual_number = actual_number.as_f64().unwrap();
+      let scale = golden_number.abs().max(actual_number.abs()).max(1.);
+      if (golden_number - actual_number).abs() <= TOLERANCE * scale {
+        None
+      } else {
+        Some(format!(
+          "{}: expected {}, got {}",
+          path, golden_number, actual_number
+        ))
+      }
+    }
+    (Value::Array(golden_items), Value::Array(actual_items)) => {
+      if golden_items.len() != actual_items.len() {
+        Some(format!(
+          "{}: expected {} items, got {}",
+          path,
+          golden_items.len(),
+          actual_items.len()
+        ))
+      } else {
+        golden_items.iter().zip(actual_items).enumerate().find_map(
+          |(index, (golden_item, actual_item))| {
+            find_difference(&format!("{}[{}]", path, index), golden_item, actual_item)
+          },
+        )
+      }
+    }
+    (Value::Object(golden_fields), Value::Object(actual_fields)) => golden_fields
+      .keys()
+      .chain(actual_fields.keys())
+      .find_map(|key| {
+        let field_path = format!("{}.{}", path, key);
+        match (golden_fields.get(key), actual_fields.get(key)) {
+          (Some(golden_field), Some(actual_field)) => {
+            find_difference(&field_path, golden_field, actual_field)
+          }
+          (Some(_), None) => Some(format!("{}: missing", field_path)),
+          (None, _) => Some(format!("{}: unexpected", field_path)),
+        }
+      }),
+    _ if golden == actual => None,
+    _ => Some(format!("{}: expected {}, got {}", path, golden, actual)),
+  }
+}
+
+fn fixtures_paths() -> Vec<PathBuf> {
+  let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/scenarios");
+  let mut paths: Vec<PathBuf> = fs::read_dir(&directory)
+    .unwrap()
+    .map(|entry| entry.unwrap().path())
+    .filter(|path| {
+      path.extension() == Some("json".as_ref()) && !path.to_string_lossy().ends_with(".golden.json")
+    })
+    .collect();
+  paths.sort();
+  paths
+}
+
+fn check_fixture(path: &Path, bless: bool) -> Vec<String> {
+  let fixture: Fixture = serde_json::from_str(&fs::read_to_string(path).unwrap())
+    .unwrap_or_else(|error| panic!("Invalid fixture {}: {}", path.display(), error));
+  let outcome = simulate(&fixture);
+  let golden_path = path.with_extension("golden.json");
+  let mut failures = match (&fixture.known_failure, outcome.violations.is_empty()) {
+    (Some(reason), true) => vec![format!(
+      "invariants hold, remove the known failure: {}",
+      reason
+    )],
+    (Some(_), false) => Vec::new(),
+    (None, _) => outcome.violations,
+  };
+  if bless {
+    fs::write(
+      &golden_path,
+      serde_json::to_string_pretty(&outcome.summary).unwrap() + "\n",
+    )
+    .unwrap();
+  } else {
+    match fs::read_to_string(&golden_path) {
+      Ok(golden_data) => {
+        let golden: Value = serde_json::from_str(&golden_data).unwrap();
+        failures.extend(find_difference("", &golden, &outcome.summary));
+      }
+      Err(_) => failures.push("no golden summary, run with BLESS=1 to create it".to_string()),
+    }
+  }
+  failures
+}
+
+#[test]
+fn test_scenarios() {
+  let bless = env::var("BLESS").as_deref() == Ok("1");
+  let paths = fixtures_paths();
+  assert!(!paths.is_empty());
+  let failures: Vec<String> = paths
+    .iter()
+    .flat_map(|path| {
+      let name = path.file_stem().unwrap().to_string_lossy().to_string();
+      check_fixture(path, bless)
+        .into_iter()
+        .map(move |failure| format!("{}: {}", name, failure))
+    })
+    .collect();
+  assert!(
+    failures.is_empty(),
+    "Scenarios regressions:\n{}",
+    failures.join("\n")
+  );
+}
@@ -32,13 +32,15 @@ pub fn test_initial_universe() {
 #[wasm_bindgen_test]
 pub fn test_simple_antipodal_scenario() {
   let mut universe = Universe::new();
-  universe.load_scenario(
-    "{
+  universe
+    .load_scenario(
+      "{
       \"scenario\": \"AntipodalCircle\",
       \"agents_count\": 4,
       \"radius\": 10.0
     }",
-  );
+    )
+    .unwrap();
   assert_eq!(universe.count_agents(), 4);
   universe
     .render_agents()
@@ -50,8 +52,8 @@ pub fn test_simple_antipodal_scenario() {
     .for_each(|(value, expected)| assert_relative_eq!(value, &expected, epsilon = 0.0001));
 
   (0..100).for_each(|_| {
-    universe.update(0.25);
-    let _debug_info = universe.render_debug_info(0);
+    universe.update(0.25).unwrap();
+    let _debug_info = universe.render_debug_info(0).unwrap();
   });
 
   let end_state = universe.render_agents();
@@ -75,3 +77,48 @@ pub fn test_simple_antipodal_scenario() {
     f 1 3 4\n"
   );
 }
+
+#[wasm_bindgen_test]
+pub fn test_seeded_scenario_golden() {
+  // The expected values are shared by the native and wasm tests, checking that
+  // both targets produce bit-identical results.
+  let mut universe = Universe::new();
+  universe
+    .load_scenario(
+      "{
+      \"scenario\": \"AntipodalCircle\",
+      \"agents_count\": 3,
+      \"radius\": 4.0,
+      \"jitter\": 0.2,
+      \"seed\": 42
+    }",
+    )
+    .unwrap();
+  (0..40).for_each(|_| universe.update(0.1).unwrap());
+  assert_eq!(
+    universe.render_agents().to_vec(),
+    vec![
+      -3.721627043647701,
+      -0.018796720501117765,
+      -0.9977926844157325,
+      0.06640601573988747,
+      -1.4492133338962965,
+      0.09785597832738877,
+      0.35,
+      1.9999999999999991,
+      -3.464101615137755,
+      0.19116399526644268,
+      -0.9815581118373846,
+      0.0,
+      -0.0,
+      0.35,
+      1.9038455384278132,
+      3.223750400759977,
+      0.373551110616365,
+      0.9276095987845753,
+      0.5219733435327538,
+      1.3047436898883622,
+      0.35
+    ]
+  );
+}
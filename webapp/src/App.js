@@ -62,7 +62,7 @@ const SELECT_SCENARIO_STYLES = {
   }),
 };
 
-const App = ({ universe }) => {
+const App = ({ universe, memory }) => {
   const [paused, togglePaused] = useToggle(true);
 
   const [selectedAgentIdx, setSelectedAgentIdx] = useState(null);
@@ -93,6 +93,7 @@ const App = ({ universe }) => {
     restart,
   } = useSimulation({
     universe,
+    memory,
     scenario: selectedScenario,
     selectedAgentIdx,
     paused,
@@ -109,9 +110,9 @@ const App = ({ universe }) => {
           onPointerMissed={handleClearSelection}
         >
           <Environment color={GREY} />
-          {agents.map(({ index, position, direction, radius }) => (
+          {agents.map(({ index, id, position, direction, radius }) => (
             <Pedestrian
-              key={index}
+              key={id}
               position={position}
               direction={direction}
               radius={radius}
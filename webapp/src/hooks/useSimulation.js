@@ -1,8 +1,8 @@
 import { useCallback, useEffect, useState } from 'react';
-import chunk from 'lodash.chunk';
 
 const useSimulation = ({
   universe,
+  memory,
   scenario,
   selectedAgentIdx,
   paused,
@@ -13,68 +13,133 @@ const useSimulation = ({
   const [selectedAgentDebugInfo, setSelectedAgentDebugInfo] = useState(null);
 
   const renderAgents = useCallback(() => {
-    const agents = chunk(universe.render_agents(), 7).map(
-      ([posX, posY, dirX, dirY, velX, velY, r], index) => ({
-        index,
-        position: { x: posX, y: posY },
-        direction: { x: dirX, y: dirY },
-        radius: r,
-      })
+    // Views over the engine render buffers, recreated at each frame as the
+    // wasm memory buffer changes when it grows.
+    universe.update_render_buffers();
+    const count = universe.count_agents();
+    const ids = new Uint32Array(
+      memory.buffer,
+      universe.get_render_ids_ptr(),
+      count
     );
+    const positions = new Float32Array(
+      memory.buffer,
+      universe.get_render_positions_ptr(),
+      2 * count
+    );
+    const directions = new Float32Array(
+      memory.buffer,
+      universe.get_render_directions_ptr(),
+      2 * count
+    );
+    const radii = new Float32Array(
+      memory.buffer,
+      universe.get_render_radii_ptr(),
+      count
+    );
+    const agents = Array.from(ids, (id, index) => ({
+      index,
+      id,
+      position: { x: positions[2 * index], y: positions[2 * index + 1] },
+      direction: { x: directions[2 * index], y: directions[2 * index + 1] },
+      radius: radii[index],
+    }));
     setAgents(agents);
+    let debugInfo = null;
     if (selectedAgentIdx != null && selectedAgentIdx < agents.length) {
-      setSelectedAgentDebugInfo(
-        JSON.parse(universe.render_debug_info(selectedAgentIdx))
-      );
-    } else {
-      setSelectedAgentDebugInfo(null);
+      try {
+        debugInfo = JSON.parse(universe.render_debug_info(selectedAgentIdx));
+      } catch (error) {
+        console.error('Agent debug info rendering failed:', error);
+      }
     }
-  }, [setAgents, setSelectedAgentDebugInfo, universe, selectedAgentIdx]);
+    setSelectedAgentDebugInfo(debugInfo);
+  }, [
+    setAgents,
+    setSelectedAgentDebugInfo,
+    universe,
+    memory,
+    selectedAgentIdx,
+  ]);
 
   // Simulation restart
   const [started, setStarted] = useState(false);
+  // Set when the engine fails to compute a step, the simulation stops until
+  // restarted.
+  const [failed, setFailed] = useState(false);
   const [navmeshObj, setNavmeshObj] = useState(false);
   const restart = useCallback(() => {
     const scenario_str = JSON.stringify(scenario);
-    universe.load_scenario(scenario_str);
-    renderAgents();
-    setNavmeshObj(universe.render_navmesh());
     setStarted(false);
-  }, [universe, renderAgents, scenario]);
+    try {
+      universe.set_timestep(1 / simulationFrequency);
+      universe.load_scenario(scenario_str);
+      setNavmeshObj(universe.render_navmesh());
+      setFailed(false);
+    } catch (error) {
+      console.error('Scenario loading failed:', error);
+      setFailed(true);
+    }
+    renderAgents();
+  }, [universe, renderAgents, scenario, simulationFrequency]);
 
   // Scenario changes effect, basically restart.
   useEffect(restart, [restart]);
 
   // Simulation step effect
   const computeSimulationStep = useCallback(() => {
-    universe.update(1 / simulationFrequency);
+    if (failed) {
+      return;
+    }
+    try {
+      universe.step();
+    } catch (error) {
+      console.error('Simulation step failed:', error);
+      setFailed(true);
+    }
     setStarted(true);
     renderAgents();
-  }, [universe, renderAgents, setStarted, simulationFrequency]);
+  }, [universe, renderAgents, setStarted, failed]);
 
   useEffect(() => {
-    if (paused) {
+    if (paused || failed) {
       renderAgents();
     } else {
-      const interval = setInterval(
-        computeSimulationStep,
-        1000 / simulationFrequency
-      );
-      return () => clearInterval(interval);
+      // The universe runs fixed timesteps covering the elapsed real time, the
+      // results don't depend on the frame rate.
+      let animationFrame = null;
+      let lastTimestamp = null;
+      const animate = (timestamp) => {
+        if (lastTimestamp != null) {
+          try {
+            const stepsCount = universe.advance(
+              (timestamp - lastTimestamp) / 1000
+            );
+            if (stepsCount > 0) {
+              setStarted(true);
+              renderAgents();
+            }
+          } catch (error) {
+            console.error('Simulation step failed:', error);
+            setFailed(true);
+            renderAgents();
+            return;
+          }
+        }
+        lastTimestamp = timestamp;
+        animationFrame = requestAnimationFrame(animate);
+      };
+      animationFrame = requestAnimationFrame(animate);
+      return () => cancelAnimationFrame(animationFrame);
     }
-  }, [
-    universe,
-    paused,
-    renderAgents,
-    computeSimulationStep,
-    simulationFrequency,
-  ]);
+  }, [universe, paused, failed, renderAgents, setStarted]);
 
   return {
     agents,
     selectedAgentDebugInfo,
     navmeshObj,
     started,
+    failed,
     paused,
     restart,
     computeSimulationStep,
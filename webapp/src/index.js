@@ -15,7 +15,7 @@ import('rusty-pedestrians-engine')
     const universe = engine.Universe.new();
     ReactDOM.render(
       <React.StrictMode>
-        <App universe={universe} />
+        <App universe={universe} memory={engine.memory()} />
       </React.StrictMode>,
       document.getElementById('root')
     );
@@ -4,12 +4,85 @@ const SCENARII = {
     agents_count: 9,
     radius: 6,
   },
+  'Antipodal Circle Jittered - 12': {
+    scenario: 'AntipodalCircle',
+    agents_count: 12,
+    radius: 6,
+    jitter: 0.1,
+    seed: 1,
+    desired_speed: {
+      distribution: 'Normal',
+      mean: 1.4,
+      standard_deviation: 0.2,
+      min: 0.8,
+      max: 2.0,
+    },
+    radius: { distribution: 'Uniform', min: 0.25, max: 0.35 },
+  },
   'Corridor - 2': {
     scenario: 'Corridor',
     agents_per_side_count: 1,
     length: 15,
     width: 1.5,
   },
+  'Corridor Shuttle - 6': {
+    scenario: 'Corridor',
+    agents_per_side_count: 3,
+    length: 12,
+    width: 3,
+    arrival_policy: 'NextTarget',
+  },
+  'Wander - 5': {
+    scenario: 'Custom',
+    bounds: [-6, -6, 6, 6],
+    arrival_policy: 'Wander',
+    agents: [
+      { position: [-4, -4], target: [4, 4] },
+      { position: [4, -4], target: [-4, 4] },
+      { position: [0, 4], target: [0, -4] },
+      { position: [-4, 0], target: [4, 0] },
+      { position: [4, 2], target: [-4, -2], arrival_policy: 'Despawn' },
+    ],
+  },
+  'Groups - 6': {
+    scenario: 'Custom',
+    bounds: [-8, -4, 8, 4],
+    groups: [{ formation: 'SideBySide' }, { formation: 'Free' }],
+    agents: [
+      { position: [-7, 0], target: [7, 0], group: 0 },
+      { position: [-6, 1], target: [7, 1], group: 0 },
+      { position: [-7, -1], target: [7, -1], group: 0 },
+      { position: [7, 0.5], target: [-7, 0.5], group: 1 },
+      { position: [6, -0.5], target: [-7, -0.5], group: 1 },
+      { position: [7, -2], target: [-7, -2] },
+    ],
+  },
+  'Queue - 5': {
+    scenario: 'Custom',
+    bounds: [-8, -4, 8, 4],
+    queues: [{ position: [0, 0], direction: [-1, 0], service_time: 1.5 }],
+    agents: [
+      { position: [-5, -2], target: [6, -2], queue: 0 },
+      { position: [-6, 2], target: [6, -1], queue: 0 },
+      { position: [-7, 0], target: [6, 0], queue: 0 },
+      { position: [-7, -3], target: [6, 1], queue: 0 },
+      { position: [-7, 3], target: [6, 2], queue: 0 },
+    ],
+  },
+  'Scripted Crossing - 3': {
+    scenario: 'Custom',
+    bounds: [-6, -6, 6, 6],
+    agents: [
+      { position: [-5, 0.5], target: [5, 0.5] },
+      { position: [-5, -0.5], target: [5, -0.5] },
+      {
+        trajectory: [
+          [0, 0, 5],
+          [5, 0, -5],
+        ],
+      },
+    ],
+  },
 };
 
 export default SCENARII;